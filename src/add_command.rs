@@ -1,26 +1,59 @@
 use chrono::prelude::*;
 use std::io::prelude::*;
-use std::fs::OpenOptions;
+use std::fs::{self, OpenOptions};
+use std::path::Path;
 use std::io;
 use std::fmt;
 use std::error::Error;
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 
 use crate::daily_score::DailyScore;
+use crate::journal;
+use crate::tag::TagRules;
+use crate::timezone;
 use crate::GlobalConfig;
 
 pub struct AddCommand {
-    pub score: i8,
+    pub score: f64,
+    pub metrics: BTreeMap<String, f64>,
     pub datetime: Option<DateTime<Local>>,
     pub tags: HashSet<String>,
+    pub attributes: BTreeMap<String, String>,
     pub comment: Option<String>,
     pub global_config: GlobalConfig,
 }
 
+// What is done when a record is added to a day which already has records.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum SameDayPolicy {
+    // records are added as is
+    #[default]
+    Allow,
+    Warn,
+    // records of the day are replaced with the new one
+    Replace,
+    Reject,
+}
+
+impl SameDayPolicy {
+    pub fn parse(policy_str: &str) -> Option<Self> {
+        match policy_str {
+            "allow" => Some(Self::Allow),
+            "warn" => Some(Self::Warn),
+            "replace" => Some(Self::Replace),
+            "reject" => Some(Self::Reject),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum AddCommandError {
     CannotOpenFile { file_path: String, open_error: io::Error },
     CannotWriteToFile { file_path: String, write_error: io::Error },
+    JournalReadError(journal::JournalError),
+    JournalWriteError(journal::JournalError),
+    DayAlreadyRated(NaiveDate),
 }
 
 impl std::error::Error for AddCommandError {
@@ -28,6 +61,9 @@ impl std::error::Error for AddCommandError {
         match self {
             Self::CannotOpenFile { file_path: _, open_error } => Some(open_error),
             Self::CannotWriteToFile { file_path: _, write_error } => Some(write_error),
+            Self::JournalReadError(journal_error) => Some(journal_error),
+            Self::JournalWriteError(journal_error) => Some(journal_error),
+            Self::DayAlreadyRated(_) => None,
         }
     }
 }
@@ -37,22 +73,56 @@ impl fmt::Display for AddCommandError {
         match self {
             Self::CannotOpenFile { file_path, open_error: _ } => write!(f, "cannot open journal file '{}'", file_path),
             Self::CannotWriteToFile { file_path, write_error: _ } => write!(f, "cannot write to journal file '{}'", file_path),
+            Self::JournalReadError(_journal_error) => write!(f, "cannot parse journal"),
+            Self::JournalWriteError(_journal_error) => write!(f, "cannot write journal"),
+            Self::DayAlreadyRated(date) => write!(f, "{} is already rated, use --force to add another record", date),
         }
     }
 }
 
 impl AddCommand {
     pub fn run(self) -> Result<(), AddCommandError> {
-        let local_datetime = self.datetime.unwrap_or_else(Local::now);
+        // records are written with the offset the configured zone has, or with the local one
+        let datetime = timezone::convert(self.global_config.timezone, &self.datetime.unwrap_or_else(Local::now));
         let journal_file_path = self.global_config.journal_file_path;
 
         let daily_score = DailyScore {
             score: self.score,
-            tags: self.tags,
+            metrics: self.metrics,
+            tags: self.global_config.tag_rules.apply_all(&self.tags),
+            attributes: self.attributes,
             comment: self.comment,
-            datetime: local_datetime.with_timezone(local_datetime.offset())
+            datetime,
         };
 
+        let same_day_policy = self.global_config.same_day_policy;
+        if same_day_policy != SameDayPolicy::Allow {
+            // journal is read as is, so nothing but the day's records is changed if they are replaced
+            let daily_scores = journal::read_if_exists(&journal_file_path, &TagRules::default())
+                .map_err(AddCommandError::JournalReadError)?;
+            let date = daily_score.datetime.naive_local().date();
+            let same_day_count = day_records_count(&daily_scores, date);
+
+            match same_day_policy {
+                _ if same_day_count == 0 => {},
+                SameDayPolicy::Allow => {},
+                SameDayPolicy::Warn => eprintln!("Warning: {} is already rated ({} records), a new record is added", date, same_day_count),
+                SameDayPolicy::Reject => return Err(AddCommandError::DayAlreadyRated(date)),
+                SameDayPolicy::Replace => {
+                    journal::write(&journal_file_path, &replace_day_records(daily_scores, daily_score))
+                        .map_err(AddCommandError::JournalWriteError)?;
+                    println!("Replaced {} records of {}", same_day_count, date);
+                    return Ok(());
+                },
+            }
+        }
+
+        // default journal lives in user's data directory, which may not exist yet
+        if let Some(dir) = Path::new(&journal_file_path).parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)
+                .map_err(|open_error| AddCommandError::CannotOpenFile { file_path: journal_file_path.clone(), open_error })?;
+        }
+
         let mut file = OpenOptions::new()
             .read(true)
             .append(true)
@@ -67,19 +137,56 @@ impl AddCommand {
     }
 }
 
+// Records are on the dates they were made on, the same way the new record is dated
+// by the configured zone, e.g. `timezone::today(global_config.timezone)` for today.
+pub fn day_records_count(daily_scores: &[DailyScore], date: NaiveDate) -> usize {
+    daily_scores.iter().filter(|daily_score| daily_score.datetime.naive_local().date() == date).count()
+}
+
+// The new record takes the place of the first record of its day, other records of the day are dropped.
+pub fn replace_day_records(daily_scores: Vec<DailyScore>, daily_score: DailyScore) -> Vec<DailyScore> {
+    let date = daily_score.datetime.naive_local().date();
+    let mut new_daily_score = Some(daily_score);
+
+    daily_scores.into_iter()
+        .filter_map(|other| if other.datetime.naive_local().date() == date { new_daily_score.take() } else { Some(other) })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn same_day_records_replacing() {
+        let daily_score_at = |day, hour, score| {
+            let mut daily_score = DailyScore::with_score(score);
+            daily_score.datetime = FixedOffset::east(3600).ymd(2020, 1, day).and_hms(hour, 0, 0);
+            daily_score
+        };
+        let daily_scores = vec![daily_score_at(1, 9, 1.0), daily_score_at(2, 9, -1.0), daily_score_at(2, 20, 0.0), daily_score_at(3, 9, 2.0)];
+
+        let replaced_daily_scores = replace_day_records(daily_scores, daily_score_at(2, 22, 1.5));
+        assert_eq!(replaced_daily_scores.iter().map(|daily_score| daily_score.score).collect::<Vec<f64>>(), vec![1.0, 1.5, 2.0]);
+        assert_eq!(replaced_daily_scores[1].datetime, FixedOffset::east(3600).ymd(2020, 1, 2).and_hms(22, 0, 0));
+
+        assert_eq!(SameDayPolicy::parse("replace"), Some(SameDayPolicy::Replace));
+        assert_eq!(SameDayPolicy::parse("ask"), None);
+    }
+
     #[test]
     fn errors_display() {
         let file_path = String::from("path/to/file");
-        let io_error = io::Error::new(io::ErrorKind::Other, "error text");
-        let another_io_error = io::Error::new(io::ErrorKind::Other, "error text");
+        let io_error = io::Error::other("error text");
+        let another_io_error = io::Error::other("error text");
 
         assert_eq!(AddCommandError::CannotOpenFile { file_path: file_path.clone(), open_error: io_error }.to_string(),
             "cannot open journal file 'path/to/file'");
         assert_eq!(AddCommandError::CannotWriteToFile { file_path, write_error: another_io_error }.to_string(),
             "cannot write to journal file 'path/to/file'");
+        assert_eq!(AddCommandError::DayAlreadyRated(NaiveDate::from_ymd(2020, 1, 2)).to_string(),
+            "2020-01-02 is already rated, use --force to add another record");
+        assert_eq!(AddCommandError::JournalWriteError(journal::JournalError::XlsxWriteError(io::Error::other("error text"))).to_string(),
+            "cannot write journal");
     }
 }
@@ -0,0 +1,295 @@
+use chrono::NaiveDate;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+
+use crate::add_command::{self, AddCommand, AddCommandError, SameDayPolicy};
+use crate::attribute;
+use crate::daily_score::DailyScore;
+use crate::GlobalConfig;
+use crate::journal;
+use crate::score_scale::ScoreScale;
+use crate::tag;
+use crate::timezone::{self, Timezone};
+use crate::TAGS_SEPARATOR;
+
+const OFFERED_TAGS_LIMIT: usize = 10;
+
+pub struct AskCommand {
+    pub global_config: GlobalConfig,
+}
+
+#[derive(Debug)]
+pub enum AskCommandError {
+    JournalReadError(journal::JournalError),
+    CannotReadAnswer(io::Error),
+    AddError(AddCommandError),
+    DayAlreadyRated(NaiveDate),
+}
+
+impl std::error::Error for AskCommandError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::JournalReadError(journal_error) => Some(journal_error),
+            Self::CannotReadAnswer(read_error) => Some(read_error),
+            Self::AddError(add_error) => Some(add_error),
+            Self::DayAlreadyRated(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for AskCommandError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::JournalReadError(_journal_error) => write!(f, "cannot parse journal"),
+            Self::CannotReadAnswer(_read_error) => write!(f, "cannot read answer"),
+            Self::AddError(_add_error) => write!(f, "cannot add record"),
+            Self::DayAlreadyRated(date) => write!(f, "{} is already rated, nothing is added", date),
+        }
+    }
+}
+
+impl AskCommand {
+    pub fn run(mut self) -> Result<(), AskCommandError> {
+        let daily_scores = journal::read_if_exists(&self.global_config.journal_file_path, &self.global_config.tag_rules)
+            .map_err(AskCommandError::JournalReadError)?;
+
+        let stdin = io::stdin();
+        let mut input = stdin.lock();
+
+        let today_records = today_records(&daily_scores, self.global_config.timezone);
+        if today_records > 0 {
+            match self.global_config.same_day_policy {
+                SameDayPolicy::Allow => eprintln!("Warning: today is already rated ({} records), a new record will be added", today_records),
+                SameDayPolicy::Replace => eprintln!("Warning: today is already rated ({} records), they will be replaced", today_records),
+                SameDayPolicy::Reject => return Err(AskCommandError::DayAlreadyRated(timezone::today(self.global_config.timezone))),
+                SameDayPolicy::Warn => match ask_same_day_policy(&mut input, today_records)? {
+                    // the choice is made, so the record is not checked again when it's added
+                    Some(same_day_policy) => self.global_config.same_day_policy = same_day_policy,
+                    None => {
+                        println!("\nNothing is added");
+                        return Ok(());
+                    },
+                },
+            }
+        }
+
+        let score = match ask_score(&mut input, &self.global_config.score_scale)? {
+            Some(score) => score,
+            None => {
+                println!("\nNothing is added");
+                return Ok(());
+            },
+        };
+        let (tags, attributes) = attribute::split_tags(ask_tags(&mut input, &known_tags(&daily_scores))?);
+        let comment = prompt(&mut input, "Comment (empty for none): ")?.filter(|comment| !comment.is_empty());
+
+        AddCommand { score, metrics: Default::default(), tags, attributes, comment, datetime: None, global_config: self.global_config }
+            .run()
+            .map_err(AskCommandError::AddError)?;
+        println!("Added, have a nice day!");
+
+        Ok(())
+    }
+}
+
+// Today is the one of the configured zone, the same as for the record `add` makes.
+fn today_records(daily_scores: &[DailyScore], timezone: Option<Timezone>) -> usize {
+    add_command::day_records_count(daily_scores, timezone::today(timezone))
+}
+
+// Known tags, most used first.
+fn known_tags(daily_scores: &[DailyScore]) -> Vec<String> {
+    let mut tag_counts: HashMap<&str, usize> = HashMap::new();
+    for tag in daily_scores.iter().flat_map(|daily_score| daily_score.tags.iter()) {
+        *tag_counts.entry(tag).or_default() += 1;
+    }
+
+    let mut tags = tag_counts.into_iter().collect::<Vec<(&str, usize)>>();
+    tags.sort_by(|(tag, count), (other_tag, other_count)| other_count.cmp(count).then(tag.cmp(other_tag)));
+    tags.into_iter().map(|(tag, _)| tag.to_string()).collect()
+}
+
+// Asks until a valid score is given, `None` means the input is closed.
+fn ask_score<R: BufRead>(input: &mut R, score_scale: &ScoreScale) -> Result<Option<f64>, AskCommandError> {
+    let question = if *score_scale == ScoreScale::default() {
+        "How are you today? Score from -1 to 1 (or any number, e.g. 0.5): ".to_string()
+    } else {
+        format!("How are you today? Score {}: ", score_scale.description())
+    };
+
+    loop {
+        let answer = match prompt(input, &question)? {
+            Some(answer) => answer,
+            None => return Ok(None),
+        };
+
+        match score_scale.parse(&answer) {
+            Some(score) if score_scale.is_allowed(score) => return Ok(Some(score)),
+            Some(score) => println!("score {} is not allowed, expected {}", score, score_scale.description()),
+            None => println!("'{}' is not a valid score", answer),
+        }
+    }
+}
+
+// Asks whether today's records are replaced or a new one is added, `None` means the input is closed.
+fn ask_same_day_policy<R: BufRead>(input: &mut R, today_records: usize) -> Result<Option<SameDayPolicy>, AskCommandError> {
+    let question = format!("Today is already rated ({} records), replace them or add a new one? [r/A]: ", today_records);
+
+    loop {
+        match prompt(input, &question)?.as_deref().map(str::to_lowercase).as_deref() {
+            Some("r") | Some("replace") => return Ok(Some(SameDayPolicy::Replace)),
+            Some("") | Some("a") | Some("add") => return Ok(Some(SameDayPolicy::Allow)),
+            Some(answer) => println!("'{}' is not a valid answer, expected r or a", answer),
+            None => return Ok(None),
+        }
+    }
+}
+
+// Tags are comma separated, numbers pick offered tags, and unambiguous beginnings of known tags
+// are completed, so a typo does not create a new tag.
+fn ask_tags<R: BufRead>(input: &mut R, known_tags: &[String]) -> Result<HashSet<String>, AskCommandError> {
+    let offered_tags = &known_tags[..known_tags.len().min(OFFERED_TAGS_LIMIT)];
+    if !offered_tags.is_empty() {
+        let offer = offered_tags.iter()
+            .enumerate()
+            .map(|(i, tag)| format!("{}) {}", i + 1, tag))
+            .collect::<Vec<String>>()
+            .join("  ");
+        println!("Used tags: {}", offer);
+    }
+
+    // tags go to the journal line as is, so the question is asked again until every tag is valid
+    let tags = loop {
+        let answer = prompt(input, "Tags (names or numbers separated by commas, empty for none): ")?.unwrap_or_default();
+        let tags = answer.split(TAGS_SEPARATOR)
+            .map(str::trim)
+            .filter(|tag| !tag.is_empty())
+            .map(|tag| complete_tag(tag, offered_tags, known_tags))
+            .collect::<HashSet<String>>();
+
+        match tags.iter().find(|tag| !tag::is_valid(tag) && attribute::parse_attribute(tag).is_none()) {
+            Some(invalid_tag) => println!("'{}' is not a valid tag", invalid_tag),
+            None => break tags,
+        }
+    };
+
+    if !tags.is_empty() {
+        let mut sorted_tags = tags.iter().map(String::as_str).collect::<Vec<&str>>();
+        sorted_tags.sort_unstable();
+        println!("Tags: {}", sorted_tags.join(", "));
+    }
+
+    Ok(tags)
+}
+
+fn complete_tag(tag: &str, offered_tags: &[String], known_tags: &[String]) -> String {
+    if let Some(offered_tag) = tag.parse::<usize>().ok().and_then(|i| offered_tags.get(i.wrapping_sub(1))) {
+        return offered_tag.clone();
+    }
+    if known_tags.iter().any(|known_tag| known_tag == tag) {
+        return tag.to_string();
+    }
+
+    let mut matching_tags = known_tags.iter().filter(|known_tag| known_tag.starts_with(tag));
+    match (matching_tags.next(), matching_tags.next()) {
+        (Some(matching_tag), None) => matching_tag.clone(),
+        _ => tag.to_string(),
+    }
+}
+
+// Returns trimmed answer, or `None` if input is closed.
+fn prompt<R: BufRead>(input: &mut R, question: &str) -> Result<Option<String>, AskCommandError> {
+    print!("{}", question);
+    io::stdout().flush().map_err(AskCommandError::CannotReadAnswer)?;
+
+    let mut answer = String::new();
+    let read_bytes = input.read_line(&mut answer).map_err(AskCommandError::CannotReadAnswer)?;
+
+    Ok(if read_bytes == 0 { None } else { Some(answer.trim().to_string()) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tags(tags: &[&str]) -> Vec<String> {
+        tags.iter().map(|tag| tag.to_string()).collect()
+    }
+
+    #[test]
+    fn score_asking() {
+        let any_score = ScoreScale::default();
+        assert_eq!(ask_score(&mut "1\n".as_bytes(), &any_score).unwrap(), Some(1.0));
+        assert_eq!(ask_score(&mut "great\n0.5\n".as_bytes(), &any_score).unwrap(), Some(0.5));
+        assert_eq!(ask_score(&mut "great\n".as_bytes(), &any_score).unwrap(), None);
+
+        let score_scale = ScoreScale { allowed: Some(vec![-1.0, 1.0]), aliases: vec![("good".to_string(), 1.0)].into_iter().collect(), ..ScoreScale::default() };
+        assert_eq!(ask_score(&mut "0\ngood\n".as_bytes(), &score_scale).unwrap(), Some(1.0));
+    }
+
+    #[test]
+    fn today_in_timezone() {
+        let zone = |hours| Some(Timezone::Offset(chrono::FixedOffset::east(hours * 3600)));
+        // the zones are 26 hours apart, so their dates always differ
+        let daily_scores = vec![
+            DailyScore { datetime: timezone::now(zone(14)), ..DailyScore::with_score(1) },
+            DailyScore { datetime: timezone::now(zone(-12)), ..DailyScore::with_score(1) },
+            DailyScore { datetime: timezone::now(zone(-12)), ..DailyScore::with_score(1) },
+        ];
+
+        assert_eq!(today_records(&daily_scores, zone(14)), 1);
+        assert_eq!(today_records(&daily_scores, zone(-12)), 2);
+    }
+
+    #[test]
+    fn same_day_policy_asking() {
+        assert_eq!(ask_same_day_policy(&mut "R\n".as_bytes(), 1).unwrap(), Some(SameDayPolicy::Replace));
+        assert_eq!(ask_same_day_policy(&mut "no\n\n".as_bytes(), 1).unwrap(), Some(SameDayPolicy::Allow));
+        assert_eq!(ask_same_day_policy(&mut "".as_bytes(), 1).unwrap(), None);
+    }
+
+    #[test]
+    fn tags_asking() {
+        let known_tags = tags(&["work", "running", "run", "reading"]);
+        let asked_tags = ask_tags(&mut "2, wo,r, new tag,,\n".as_bytes(), &known_tags).unwrap();
+
+        assert_eq!(asked_tags, tags(&["running", "work", "r", "new tag"]).into_iter().collect());
+        assert!(ask_tags(&mut "\n".as_bytes(), &known_tags).unwrap().is_empty());
+        assert!(ask_tags(&mut "".as_bytes(), &known_tags).unwrap().is_empty());
+
+        let asked_tags = ask_tags(&mut "a|b, run\nrun, weather=rain\n".as_bytes(), &known_tags).unwrap();
+        assert_eq!(asked_tags, tags(&["run", "weather=rain"]).into_iter().collect());
+    }
+
+    #[test]
+    fn tag_completion() {
+        let known_tags = tags(&["work", "run", "running"]);
+
+        assert_eq!(complete_tag("1", &known_tags, &known_tags), "work");
+        assert_eq!(complete_tag("0", &known_tags, &known_tags), "0");
+        assert_eq!(complete_tag("run", &known_tags, &known_tags), "run");
+        assert_eq!(complete_tag("runn", &known_tags, &known_tags), "running");
+        assert_eq!(complete_tag("r", &known_tags, &known_tags), "r");
+    }
+
+    #[test]
+    fn known_tags_order() {
+        let daily_score = |tags: &[&str]| {
+            let mut daily_score = DailyScore::with_score(1);
+            daily_score.tags = tags.iter().map(|tag| tag.to_string()).collect();
+            daily_score
+        };
+        let daily_scores = vec![daily_score(&["b", "a"]), daily_score(&["c", "b"])];
+
+        assert_eq!(known_tags(&daily_scores), tags(&["b", "a", "c"]));
+    }
+
+    #[test]
+    fn errors_display() {
+        assert_eq!(AskCommandError::CannotReadAnswer(io::Error::other("error text")).to_string(), "cannot read answer");
+        assert_eq!(AskCommandError::DayAlreadyRated(NaiveDate::from_ymd(2020, 1, 2)).to_string(),
+            "2020-01-02 is already rated, nothing is added");
+    }
+}
@@ -0,0 +1,91 @@
+use std::collections::{BTreeMap, HashSet};
+
+use crate::{JOURNAL_SEPARATOR, TAGS_SEPARATOR};
+
+const ATTRIBUTE_VALUE_SEPARATOR: char = '=';
+
+// Keys start with a letter and contain letters, digits, `_` and `-`.
+pub fn is_key_valid(key: &str) -> bool {
+    key.chars().next().is_some_and(char::is_alphabetic) &&
+        key.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+}
+
+// Parses `KEY=VALUE` pair, value can not contain journal separators.
+pub fn parse_attribute(attribute_str: &str) -> Option<(String, String)> {
+    let (key, value) = attribute_str.split_once(ATTRIBUTE_VALUE_SEPARATOR)?;
+    let value = value.trim();
+    let value_valid = !value.is_empty() &&
+        !value.contains(TAGS_SEPARATOR) &&
+        !value.contains(JOURNAL_SEPARATOR) &&
+        !value.contains(ATTRIBUTE_VALUE_SEPARATOR) &&
+        !value.contains(&['\n', '\r'][..]);
+
+    if is_key_valid(key.trim()) && value_valid { Some((key.trim().to_string(), value.to_string())) } else { None }
+}
+
+pub fn format_attribute(key: &str, value: &str) -> String {
+    format!("{}{}{}", key, ATTRIBUTE_VALUE_SEPARATOR, value)
+}
+
+// Attributes share the tags column, so `KEY=VALUE` items are taken as attributes and the rest as tags.
+// Items looking like attributes, but not valid ones, are kept as tags, so older journals still read the same.
+// If a key is repeated, the last item is the attribute and the other ones are kept as tags, which are written
+// before attributes, so the record reads back the same.
+pub fn split_tags<I>(items: I) -> (HashSet<String>, BTreeMap<String, String>)
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut tags = HashSet::new();
+    let mut attributes = BTreeMap::new();
+
+    for item in items.into_iter().collect::<Vec<String>>().into_iter().rev() {
+        match parse_attribute(&item) {
+            Some((key, value)) => match attributes.get(&key) {
+                None => { attributes.insert(key, value); },
+                Some(attribute_value) if *attribute_value != value => { tags.insert(item); },
+                Some(_) => {},
+            },
+            None => { tags.insert(item); },
+        }
+    }
+
+    (tags, attributes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn items(items: &[&str]) -> Vec<String> {
+        items.iter().map(|item| item.to_string()).collect()
+    }
+
+    #[test]
+    fn attribute_parsing() {
+        assert_eq!(parse_attribute("weather=rain"), Some(("weather".to_string(), "rain".to_string())));
+        assert_eq!(parse_attribute("location=at home"), Some(("location".to_string(), "at home".to_string())));
+        assert_eq!(parse_attribute("weather="), None);
+        assert_eq!(parse_attribute("=rain"), None);
+        assert_eq!(parse_attribute("1st=rain"), None);
+        assert_eq!(parse_attribute("weather=rain=snow"), None);
+        assert_eq!(parse_attribute("weather"), None);
+        assert_eq!(format_attribute("weather", "rain"), "weather=rain");
+    }
+
+    #[test]
+    fn tags_splitting() {
+        let (tags, attributes) = split_tags(items(&["run", "weather=rain", "location=home", "a=b=c"]));
+
+        assert_eq!(tags, items(&["run", "a=b=c"]).into_iter().collect());
+        assert_eq!(attributes.into_iter().collect::<Vec<(String, String)>>(),
+            vec![("location".to_string(), "home".to_string()), ("weather".to_string(), "rain".to_string())]);
+    }
+
+    #[test]
+    fn repeated_keys() {
+        let (tags, attributes) = split_tags(items(&["weather=rain", "run", "weather=sun", "weather=sun"]));
+
+        assert_eq!(tags, items(&["run", "weather=rain"]).into_iter().collect());
+        assert_eq!(attributes.get("weather").map(String::as_str), Some("sun"));
+    }
+}
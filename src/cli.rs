@@ -0,0 +1,418 @@
+// Declarative description of command line arguments, used both to parse them and to print help.
+
+pub enum OptionKind {
+    Flag,
+    Value(&'static str),
+    // takes all following arguments up to the next option of the command, e.g. multi-word comment
+    Words(&'static str),
+}
+
+pub struct OptionSpec {
+    pub long: &'static str,
+    pub short: Option<&'static str>,
+    pub kind: OptionKind,
+    pub help: &'static str,
+}
+
+pub struct CommandSpec {
+    pub name: &'static str,
+    pub args: &'static str,
+    pub about: &'static str,
+    pub option_groups: &'static [&'static [OptionSpec]],
+}
+
+pub struct ParsedOption {
+    pub name: &'static str,
+    pub value: Option<String>,
+}
+
+#[derive(Default)]
+pub struct ParsedArgs {
+    pub options: Vec<ParsedOption>,
+    pub positionals: Vec<String>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ParseError {
+    OptionInvalid(String),
+    OptionValueMissing(String),
+}
+
+const fn flag(long: &'static str, short: Option<&'static str>, help: &'static str) -> OptionSpec {
+    OptionSpec { long, short, kind: OptionKind::Flag, help }
+}
+
+const fn value(long: &'static str, short: Option<&'static str>, value_name: &'static str, help: &'static str) -> OptionSpec {
+    OptionSpec { long, short, kind: OptionKind::Value(value_name), help }
+}
+
+pub const GLOBAL_OPTIONS: &[OptionSpec] = &[
+    value("--file", Some("-f"), "FILEPATH", "journal file path"),
+    flag("--help", Some("-h"), "print help"),
+];
+
+pub const TOP_LEVEL_OPTIONS: &[OptionSpec] = &[
+    flag("--version", Some("-V"), "print version"),
+];
+
+pub const METRIC_OPTIONS: &[OptionSpec] = &[
+    value("--metric", Some("-m"), "METRIC", "make report of this metric instead of score"),
+];
+
+pub const FILTER_OPTIONS: &[OptionSpec] = &[
+    value("--tag", None, "TAG", "only records with this tag, may be repeated"),
+    value("--from", None, "DATE", "only records made from this date (YYYY-MM-DD)"),
+    value("--to", None, "DATE", "only records made up to this date (YYYY-MM-DD)"),
+    value("--min-score", None, "SCORE", "only records with at least this score"),
+    value("--max-score", None, "SCORE", "only records with at most this score"),
+];
+
+pub const TOP_LEVEL: CommandSpec = CommandSpec {
+    name: "",
+    args: "[COMMAND [ARGS ...]]",
+    about: "Mood journal: rate your days and see how they go",
+    option_groups: &[GLOBAL_OPTIONS, TOP_LEVEL_OPTIONS],
+};
+
+pub const COMMANDS: &[CommandSpec] = &[
+    CommandSpec {
+        name: "ask",
+        args: "",
+        about: "Ask how your day is going and rate it (default command)",
+        option_groups: &[GLOBAL_OPTIONS],
+    },
+    CommandSpec {
+        name: "add",
+        args: "SCORE [TAG ...] [-m NAME=VALUE ...] [-c COMMENT] [--force]",
+        about: "Rate the current day",
+        option_groups: &[
+            &[
+                OptionSpec { long: "--comment", short: Some("-c"), kind: OptionKind::Words("COMMENT"),
+                             help: "note for the record, takes words up to the next option" },
+                value("--metric", Some("-m"), "NAME=VALUE", "named metric of the record, may be repeated"),
+                flag("--force", None, "add the record even if the day is already rated"),
+            ],
+            GLOBAL_OPTIONS,
+        ],
+    },
+    CommandSpec {
+        name: "mood",
+        args: "[TAG ...] [-t REPORT_TYPE | -g KEY | --by-hour] [-m METRIC] [--plot PLOT_BACKEND] [FILTER ...]",
+        about: "Print mood report",
+        option_groups: &[
+            &[
+                value("--type", Some("-t"), "REPORT_TYPE", "w, 7d, m, 30d, lm, ly or mm"),
+                value("--group-by", Some("-g"), "KEY", "sum up by values of this attribute instead"),
+                flag("--by-hour", None, "sum up by hours of day instead"),
+                value("--plot", None, "PLOT_BACKEND", "gnuplot or none"),
+            ],
+            METRIC_OPTIONS,
+            FILTER_OPTIONS,
+            GLOBAL_OPTIONS,
+        ],
+    },
+    CommandSpec {
+        name: "tags",
+        args: "[rename OLD NEW] [-m METRIC] [FILTER ...]",
+        about: "Print tags with parent tags summed up over their children, or rename a tag",
+        option_groups: &[
+            METRIC_OPTIONS,
+            FILTER_OPTIONS,
+            GLOBAL_OPTIONS,
+        ],
+    },
+    CommandSpec {
+        name: "export",
+        args: "[EXPORT_FILEPATH] [-s] [-m METRIC] [FILTER ...] [REDACTION ...]",
+        about: "Export journal to XLSX, CSV, JSON, NDJSON or iCalendar file",
+        option_groups: &[
+            &[
+                flag("--summary", Some("-s"), "add summary sheets to XLSX export"),
+                flag("--redact-comments", None, "drop comments"),
+                value("--allow-tags", None, "TAGS", "keep only tags from comma separated list"),
+                value("--hash-tags", None, "SALT", "replace tags with salted hashes"),
+                flag("--date-only", None, "truncate record times to the beginning of the day"),
+            ],
+            METRIC_OPTIONS,
+            FILTER_OPTIONS,
+            GLOBAL_OPTIONS,
+        ],
+    },
+    CommandSpec {
+        name: "import",
+        args: "IMPORT_FILEPATH [--merge | --replace] [-y] [--date-format DATE_FORMAT] [--columns COLUMNS]",
+        about: "Import records from CSV, JSON, NDJSON or XLSX file",
+        option_groups: &[
+            &[
+                flag("--merge", None, "add records which are not in the journal yet (default)"),
+                flag("--replace", None, "replace journal records with imported ones"),
+                flag("--yes", Some("-y"), "do not ask for confirmation"),
+                value("--date-format", Some("-d"), "DATE_FORMAT", "strftime format of imported dates"),
+                value("--columns", None, "COLUMNS", "comma separated CSV columns: date, score, tags, comment or _"),
+            ],
+            GLOBAL_OPTIONS,
+        ],
+    },
+    CommandSpec {
+        name: "report",
+        args: "--html OUTPUT_DIR [-m METRIC] [FILTER ...]",
+        about: "Write HTML report",
+        option_groups: &[
+            &[
+                value("--html", None, "OUTPUT_DIR", "directory to write index.html to"),
+            ],
+            METRIC_OPTIONS,
+            FILTER_OPTIONS,
+            GLOBAL_OPTIONS,
+        ],
+    },
+    CommandSpec {
+        name: "review",
+        args: "[PERIOD] [-o REVIEW_FILEPATH] [-m METRIC] [FILTER ...]",
+        about: "Print Markdown review of a month (YYYY-MM) or a year (YYYY)",
+        option_groups: &[
+            &[
+                value("--output", Some("-o"), "REVIEW_FILEPATH", "file to write review to"),
+            ],
+            METRIC_OPTIONS,
+            FILTER_OPTIONS,
+            GLOBAL_OPTIONS,
+        ],
+    },
+    CommandSpec {
+        name: "tui",
+        args: "",
+        about: "Browse, rate and review days in a full-screen calendar",
+        option_groups: &[GLOBAL_OPTIONS],
+    },
+    CommandSpec {
+        name: "serve",
+        args: "[--bind ADDRESS] [--token TOKEN]",
+        about: "Serve REST API and web page to rate days from a browser",
+        option_groups: &[
+            &[
+                value("--bind", None, "ADDRESS", "address to listen on (default 127.0.0.1:8080)"),
+                value("--token", None, "TOKEN", "token clients have to send, generated for non-loopback addresses"),
+            ],
+            GLOBAL_OPTIONS,
+        ],
+    },
+    CommandSpec {
+        name: "completions",
+        args: "SHELL",
+        about: "Print completion script for bash, zsh or fish",
+        option_groups: &[GLOBAL_OPTIONS],
+    },
+];
+
+// Commands which are not shown in help, used by completion scripts.
+pub const HIDDEN_COMMANDS: &[CommandSpec] = &[
+    CommandSpec {
+        name: "__complete-tags",
+        args: "",
+        about: "Print tags used in the journal",
+        option_groups: &[GLOBAL_OPTIONS],
+    },
+];
+
+impl CommandSpec {
+    pub fn find(name: &str) -> Option<&'static CommandSpec> {
+        COMMANDS.iter().chain(HIDDEN_COMMANDS.iter()).find(|command| command.name == name)
+    }
+
+    fn option(&self, name: &str) -> Option<&'static OptionSpec> {
+        self.option_groups.iter()
+            .flat_map(|group| group.iter())
+            .find(|option| option.long == name || option.short == Some(name))
+    }
+
+    // Words like `-ish` are kept in a words value, only options of the command end it.
+    fn ends_words(&self, arg: &str) -> bool {
+        let name = arg.split_once('=').map_or(arg, |(name, _)| name);
+        arg == "--" || (is_option(arg) && self.option(name).is_some())
+    }
+
+    // Options are accepted in any position, `--` ends them.
+    // With `stop_at_positional`, parsing stops after the first positional argument.
+    pub fn parse<I>(&self, args: &mut I, stop_at_positional: bool) -> Result<ParsedArgs, ParseError>
+        where
+        I: Iterator<Item = String>,
+    {
+        let mut parsed_args = ParsedArgs::default();
+        let mut args = args.peekable();
+
+        while let Some(arg) = args.next() {
+            if arg == "--" {
+                if stop_at_positional {
+                    parsed_args.positionals.extend(args.next());
+                } else {
+                    parsed_args.positionals.extend(args);
+                }
+                break;
+            }
+
+            if !is_option(&arg) {
+                parsed_args.positionals.push(arg);
+                if stop_at_positional {
+                    break;
+                }
+                continue;
+            }
+
+            let (name, inline_value) = match arg.split_once('=') {
+                Some((name, value)) if name.starts_with("--") => (name.to_string(), Some(value.to_string())),
+                _ => (arg, None),
+            };
+            let option = self.option(&name).ok_or_else(|| ParseError::OptionInvalid(name.clone()))?;
+
+            let value = match option.kind {
+                OptionKind::Flag if inline_value.is_some() => return Err(ParseError::OptionInvalid(name)),
+                OptionKind::Flag => None,
+                OptionKind::Value(_) => Some(inline_value.or_else(|| args.next()).ok_or(ParseError::OptionValueMissing(name))?),
+                OptionKind::Words(_) => {
+                    let mut words: Vec<String> = inline_value.into_iter().collect();
+                    while let Some(word) = args.next_if(|word| !self.ends_words(word)) {
+                        words.push(word);
+                    }
+                    Some(words.join(" "))
+                },
+            };
+
+            parsed_args.options.push(ParsedOption { name: option.long, value });
+        }
+
+        Ok(parsed_args)
+    }
+
+    pub fn usage(&self) -> String {
+        if self.name.is_empty() {
+            format!("howdy [-f FILEPATH] {}", self.args)
+        } else {
+            format!("howdy [-f FILEPATH] {} {}", self.name, self.args)
+        }
+    }
+
+    pub fn help(&self) -> String {
+        let mut lines = vec![format!("Usage: {}", self.usage()), String::new(), self.about.to_string()];
+
+        if self.name.is_empty() {
+            lines.push(String::new());
+            lines.push("Commands:".to_string());
+            lines.extend(COMMANDS.iter().map(|command| format!("  {:<10}{}", command.name, command.about)));
+            lines.push(format!("  {:<10}{}", "help", "Print help of the command"));
+        }
+
+        lines.push(String::new());
+        lines.push("Options:".to_string());
+        for option in self.option_groups.iter().flat_map(|group| group.iter()) {
+            let mut names = match option.short {
+                Some(short) => format!("{}, {}", short, option.long),
+                None => format!("    {}", option.long),
+            };
+            match option.kind {
+                OptionKind::Flag => {},
+                OptionKind::Value(value_name) => names = format!("{} {}", names, value_name),
+                OptionKind::Words(value_name) => names = format!("{} {} ...", names, value_name),
+            }
+            lines.push(format!("  {:<32}{}", names, option.help));
+        }
+
+        lines.join("\n")
+    }
+}
+
+impl ParsedArgs {
+    pub fn has(&self, name: &str) -> bool {
+        self.options.iter().any(|option| option.name == name)
+    }
+
+    // Last value wins if option is repeated.
+    pub fn value(&self, name: &str) -> Option<&str> {
+        self.options.iter().rev()
+            .find(|option| option.name == name)
+            .and_then(|option| option.value.as_deref())
+    }
+}
+
+// Negative numbers (e.g. `add -1`) are positional arguments, not options.
+fn is_option(arg: &str) -> bool {
+    arg.len() > 1 && arg.starts_with('-') && arg[1..].parse::<f64>().is_err()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::build_cli_args;
+
+    fn parse(command_name: &str, args_str: &str) -> Result<ParsedArgs, ParseError> {
+        CommandSpec::find(command_name).unwrap().parse(&mut build_cli_args(args_str), false)
+    }
+
+    #[test]
+    fn options_in_any_position() {
+        let parsed_args = parse("mood", "--from 2020-01-01 tag -t w --tag=other -f path tag2").unwrap();
+
+        assert_eq!(parsed_args.positionals, vec!["tag", "tag2"]);
+        assert_eq!(parsed_args.value("--from"), Some("2020-01-01"));
+        assert_eq!(parsed_args.value("--type"), Some("w"));
+        assert_eq!(parsed_args.value("--tag"), Some("other"));
+        assert_eq!(parsed_args.value("--file"), Some("path"));
+    }
+
+    #[test]
+    fn words_option() {
+        let parsed_args = parse("add", "-1 tag -c nice day -f path").unwrap();
+
+        assert_eq!(parsed_args.positionals, vec!["-1", "tag"]);
+        assert_eq!(parsed_args.value("--comment"), Some("nice day"));
+        assert_eq!(parsed_args.value("--file"), Some("path"));
+    }
+
+    #[test]
+    fn dashed_words() {
+        let parsed_args = parse("add", "1 -c feeling -ish - today --force").unwrap();
+
+        assert_eq!(parsed_args.value("--comment"), Some("feeling -ish - today"));
+        assert!(parsed_args.has("--force"));
+        assert_eq!(parse("add", "1 -c fine -x").unwrap().value("--comment"), Some("fine -x"));
+        assert_eq!(parse("add", "1 -x").err(), Some(ParseError::OptionInvalid("-x".to_string())));
+    }
+
+    #[test]
+    fn options_end() {
+        let parsed_args = parse("add", "1 -c text -- -c -t").unwrap();
+
+        assert_eq!(parsed_args.positionals, vec!["1", "-c", "-t"]);
+        assert_eq!(parsed_args.value("--comment"), Some("text"));
+    }
+
+    #[test]
+    fn stop_at_positional() {
+        let mut args = build_cli_args("-f path add 1 -c text");
+        let parsed_args = TOP_LEVEL.parse(&mut args, true).unwrap();
+
+        assert_eq!(parsed_args.positionals, vec!["add"]);
+        assert_eq!(parsed_args.value("--file"), Some("path"));
+        assert_eq!(args.collect::<Vec<String>>(), vec!["1", "-c", "text"]);
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(parse("mood", "--foo").err(), Some(ParseError::OptionInvalid("--foo".to_string())));
+        assert_eq!(parse("export", "--summary=yes").err(), Some(ParseError::OptionInvalid("--summary".to_string())));
+        assert_eq!(parse("review", "-o").err(), Some(ParseError::OptionValueMissing("-o".to_string())));
+    }
+
+    #[test]
+    fn help() {
+        let help = CommandSpec::find("add").unwrap().help();
+
+        assert!(help.starts_with("Usage: howdy [-f FILEPATH] add SCORE [TAG ...] [-m NAME=VALUE ...] [-c COMMENT] [--force]\n\nRate the current day\n"));
+        assert!(help.contains("\n  -c, --comment COMMENT ...       note for the record"));
+        assert!(help.contains("\n  -f, --file FILEPATH             journal file path"));
+
+        let top_level_help = TOP_LEVEL.help();
+        assert!(top_level_help.contains("\n  review    Print Markdown review"));
+        assert!(top_level_help.contains("\n  -V, --version"));
+    }
+}
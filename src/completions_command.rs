@@ -0,0 +1,177 @@
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::fmt;
+
+use crate::attribute;
+use crate::cli::{CommandSpec, OptionKind, OptionSpec, COMMANDS, TOP_LEVEL};
+use crate::GlobalConfig;
+use crate::journal;
+use crate::tag;
+
+mod bash;
+mod fish;
+mod zsh;
+
+const REPORT_TYPES: &[&str] = &["w", "weekly", "7d", "m", "monthly", "30d", "lm", "ly", "mm", "moving"];
+const PLOT_BACKENDS: &[&str] = &["gnuplot", "none"];
+const SHELLS: &[&str] = &["bash", "zsh", "fish"];
+
+pub struct CompletionsCommand {
+    pub shell: Shell,
+}
+
+// Hidden command used by completion scripts, prints tags used in the journal.
+pub struct CompleteTagsCommand {
+    pub global_config: GlobalConfig,
+}
+
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl Shell {
+    pub fn parse(shell_str: &str) -> Option<Self> {
+        match shell_str {
+            "bash" => Some(Self::Bash),
+            "zsh" => Some(Self::Zsh),
+            "fish" => Some(Self::Fish),
+            _ => None,
+        }
+    }
+}
+
+// What completes an option value or a command positional argument.
+enum Values {
+    Nothing,
+    Files,
+    Dirs,
+    Tags,
+    Commands,
+    Words(&'static [&'static str]),
+}
+
+#[derive(Debug)]
+pub enum CompletionsCommandError {
+    JournalReadError(journal::JournalError),
+}
+
+impl std::error::Error for CompletionsCommandError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::JournalReadError(journal_error) => Some(journal_error),
+        }
+    }
+}
+
+impl fmt::Display for CompletionsCommandError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::JournalReadError(_journal_error) => write!(f, "cannot parse journal"),
+        }
+    }
+}
+
+impl CompletionsCommand {
+    pub fn run(self) -> Result<(), CompletionsCommandError> {
+        let script = match self.shell {
+            Shell::Bash => bash::script(),
+            Shell::Zsh => zsh::script(),
+            Shell::Fish => fish::script(),
+        };
+        print!("{}", script);
+
+        Ok(())
+    }
+}
+
+impl CompleteTagsCommand {
+    pub fn run(self) -> Result<(), CompletionsCommandError> {
+        let daily_scores = journal::read_if_exists(&self.global_config.journal_file_path, &self.global_config.tag_rules)
+            .map_err(CompletionsCommandError::JournalReadError)?;
+
+        // parent tags are completed to filter by, attributes are completed as `KEY=VALUE`, the same way they are typed
+        let tags = daily_scores.iter()
+            .flat_map(|daily_score| daily_score.tags.iter().flat_map(|own_tag| tag::with_parents(own_tag)).map(str::to_string)
+                .chain(daily_score.attributes.iter().map(|(key, value)| attribute::format_attribute(key, value))))
+            .collect::<BTreeSet<String>>();
+        for tag in tags {
+            println!("{}", tag);
+        }
+
+        Ok(())
+    }
+}
+
+fn positional_values(command: &CommandSpec) -> Values {
+    match command.name {
+        "add" | "mood" => Values::Tags,
+        "export" | "import" => Values::Files,
+        "completions" => Values::Words(SHELLS),
+        _ => Values::Nothing,
+    }
+}
+
+fn option_values(option: &OptionSpec) -> Values {
+    match option.kind {
+        OptionKind::Value("FILEPATH") | OptionKind::Value("REVIEW_FILEPATH") => Values::Files,
+        OptionKind::Value("OUTPUT_DIR") => Values::Dirs,
+        OptionKind::Value("TAG") => Values::Tags,
+        OptionKind::Value("REPORT_TYPE") => Values::Words(REPORT_TYPES),
+        OptionKind::Value("PLOT_BACKEND") => Values::Words(PLOT_BACKENDS),
+        _ => Values::Nothing,
+    }
+}
+
+fn options(command: &CommandSpec) -> impl Iterator<Item = &'static OptionSpec> {
+    command.option_groups.iter().flat_map(|group| group.iter())
+}
+
+// Options of all commands which take a value, each option once.
+fn value_options() -> Vec<&'static OptionSpec> {
+    let mut value_options: Vec<&'static OptionSpec> = Vec::new();
+
+    for option in std::iter::once(&TOP_LEVEL).chain(COMMANDS.iter()).flat_map(options) {
+        let takes_value = matches!(option.kind, OptionKind::Value(_));
+        if takes_value && !value_options.iter().any(|value_option| value_option.long == option.long) {
+            value_options.push(option);
+        }
+    }
+
+    value_options
+}
+
+fn option_names(option: &OptionSpec) -> Vec<&'static str> {
+    option.short.into_iter().chain(std::iter::once(option.long)).collect()
+}
+
+fn command_names() -> Vec<&'static str> {
+    COMMANDS.iter().map(|command| command.name).chain(std::iter::once("help")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn value_options_are_unique() {
+        let value_options = value_options();
+
+        assert!(value_options.iter().any(|option| option.long == "--file"));
+        assert_eq!(value_options.iter().filter(|option| option.long == "--tag").count(), 1);
+        assert!(!value_options.iter().any(|option| option.long == "--comment"));
+    }
+
+    #[test]
+    fn scripts_cover_commands() {
+        for script in [bash::script(), zsh::script(), fish::script()].iter() {
+            for command_name in command_names() {
+                assert!(script.contains(command_name), "'{}' is missing", command_name);
+            }
+            assert!(script.contains("__complete-tags"));
+            assert!(script.contains("min-score"));
+            assert!(script.contains("30d"));
+        }
+    }
+}
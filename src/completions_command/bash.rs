@@ -0,0 +1,81 @@
+use super::{command_names, option_names, option_values, options, positional_values, value_options, Values};
+use crate::cli::{CommandSpec, COMMANDS, TOP_LEVEL};
+
+pub fn script() -> String {
+    let value_option_names = value_options().iter()
+        .flat_map(|option| option_names(option))
+        .filter(|name| !matches!(*name, "-f" | "--file"))
+        .collect::<Vec<&str>>()
+        .join("|");
+
+    let value_option_arms = value_options().iter()
+        .map(|option| format!("        {})\n            {}\n            return ;;\n",
+                              option_names(option).join("|"), action(&option_values(option))))
+        .collect::<String>();
+
+    let command_arms = COMMANDS.iter()
+        .map(|command| format!("        {})\n            if [[ \"$cur\" == -* ]]; then\n                {}\n            else\n                {}\n            fi ;;\n",
+                               command.name, words_action(&option_list(command)), action(&positional_values(command))))
+        .collect::<String>();
+
+    let mut top_level_words = command_names();
+    top_level_words.extend(option_list(&TOP_LEVEL));
+
+    format!("# bash completion for howdy
+_howdy_tags() {{
+    local IFS=$'\\n'
+    COMPREPLY=($(compgen -W \"$(howdy \"${{journal[@]}}\" __complete-tags 2>/dev/null)\" -- \"$cur\"))
+}}
+
+_howdy() {{
+    local cur=\"${{COMP_WORDS[COMP_CWORD]}}\"
+    local prev=\"${{COMP_WORDS[COMP_CWORD-1]}}\"
+    local command=\"\" journal=() i
+
+    for ((i = 1; i < COMP_CWORD; i++)); do
+        case \"${{COMP_WORDS[i]}}\" in
+            -f|--file)
+                journal=(-f \"${{COMP_WORDS[i+1]}}\")
+                ((i++)) ;;
+            {})
+                ((i++)) ;;
+            -*) ;;
+            *)
+                [[ -z \"$command\" ]] && command=\"${{COMP_WORDS[i]}}\" ;;
+        esac
+    done
+
+    case \"$prev\" in
+{}    esac
+
+    case \"$command\" in
+        \"\")
+            {} ;;
+        help)
+            {} ;;
+{}    esac
+}}
+
+complete -F _howdy howdy
+",
+            value_option_names, value_option_arms, words_action(&top_level_words), action(&Values::Commands), command_arms)
+}
+
+fn option_list(command: &CommandSpec) -> Vec<&'static str> {
+    options(command).flat_map(option_names).collect()
+}
+
+fn action(values: &Values) -> String {
+    match values {
+        Values::Nothing => "COMPREPLY=()".to_string(),
+        Values::Files => "COMPREPLY=($(compgen -f -- \"$cur\"))".to_string(),
+        Values::Dirs => "COMPREPLY=($(compgen -d -- \"$cur\"))".to_string(),
+        Values::Tags => "_howdy_tags".to_string(),
+        Values::Commands => words_action(&command_names()),
+        Values::Words(words) => words_action(words),
+    }
+}
+
+fn words_action(words: &[&str]) -> String {
+    format!("COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))", words.join(" "))
+}
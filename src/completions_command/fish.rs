@@ -0,0 +1,96 @@
+use super::{command_names, option_values, options, positional_values, Values};
+use crate::cli::{CommandSpec, OptionKind, OptionSpec, COMMANDS, TOP_LEVEL};
+
+pub fn script() -> String {
+    let mut lines = vec![
+        "# fish completion for howdy".to_string(),
+        "function __howdy_tags".to_string(),
+        "    set -l args (commandline -opc)".to_string(),
+        "    set -l journal".to_string(),
+        "    for i in (seq (count $args))".to_string(),
+        "        if contains -- $args[$i] -f --file".to_string(),
+        "            set journal -f $args[(math $i + 1)]".to_string(),
+        "        end".to_string(),
+        "    end".to_string(),
+        "    howdy $journal __complete-tags 2>/dev/null".to_string(),
+        "end".to_string(),
+        String::new(),
+        "complete -c howdy -f".to_string(),
+    ];
+
+    for command in COMMANDS.iter() {
+        lines.push(format!("complete -c howdy -n __fish_use_subcommand -a {} -d '{}'", command.name, escape(command.about)));
+    }
+    lines.push(format!("complete -c howdy -n '__fish_seen_subcommand_from help' -a '{}'", command_names().join(" ")));
+    lines.push("complete -c howdy -n __fish_use_subcommand -a help -d 'Print help of the command'".to_string());
+
+    for option in options(&TOP_LEVEL) {
+        lines.push(format!("complete -c howdy -n __fish_use_subcommand{}", option_completion(option)));
+    }
+
+    for command in COMMANDS.iter() {
+        let condition = format!("-n '__fish_seen_subcommand_from {}'", command.name);
+        for option in options(command) {
+            lines.push(format!("complete -c howdy {}{}", condition, option_completion(option)));
+        }
+        if let Some(positional_completion) = positional_completion(command) {
+            lines.push(format!("complete -c howdy {} {}", condition, positional_completion));
+        }
+    }
+
+    lines.join("\n") + "\n"
+}
+
+fn option_completion(option: &OptionSpec) -> String {
+    let mut completion = String::new();
+
+    if let Some(short) = option.short {
+        completion.push_str(&format!(" -s {}", short.trim_start_matches('-')));
+    }
+    completion.push_str(&format!(" -l {}", option.long.trim_start_matches("--")));
+    if let OptionKind::Value(_) = option.kind {
+        completion.push_str(&match option_values(option) {
+            Values::Files | Values::Dirs => " -r -F".to_string(),
+            values => format!(" -x{}", arguments(&values)),
+        });
+    }
+    completion.push_str(&format!(" -d '{}'", escape(option.help)));
+
+    completion
+}
+
+fn positional_completion(command: &CommandSpec) -> Option<String> {
+    match positional_values(command) {
+        Values::Nothing => None,
+        Values::Files | Values::Dirs => Some("-F".to_string()),
+        values => Some(arguments(&values).trim_start().to_string()),
+    }
+}
+
+fn arguments(values: &Values) -> String {
+    match values {
+        Values::Tags => " -a '(__howdy_tags)'".to_string(),
+        Values::Commands => format!(" -a '{}'", command_names().join(" ")),
+        Values::Words(words) => format!(" -a '{}'", words.join(" ")),
+        Values::Nothing | Values::Files | Values::Dirs => String::new(),
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('\'', "\\'")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::FILTER_OPTIONS;
+
+    #[test]
+    fn option_completions() {
+        let type_option = CommandSpec::find("mood").unwrap().option_groups[0].iter().next().unwrap();
+        assert_eq!(option_completion(type_option),
+            " -s t -l type -x -a 'w weekly 7d m monthly 30d lm ly mm moving' -d 'w, 7d, m, 30d, lm, ly or mm'");
+        assert_eq!(option_completion(&FILTER_OPTIONS[0]),
+            " -l tag -x -a '(__howdy_tags)' -d 'only records with this tag, may be repeated'");
+    }
+}
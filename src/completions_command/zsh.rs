@@ -0,0 +1,119 @@
+use super::{command_names, option_names, option_values, options, positional_values, Values};
+use crate::cli::{CommandSpec, OptionKind, OptionSpec, COMMANDS, TOP_LEVEL};
+
+pub fn script() -> String {
+    let command_descriptions = COMMANDS.iter()
+        .map(|command| format!("        '{}:{}'\n", command.name, escape(command.about)))
+        .chain(std::iter::once("        'help:Print help of the command'\n".to_string()))
+        .collect::<String>();
+
+    let command_arms = COMMANDS.iter()
+        .map(|command| format!("                {})\n                    _arguments \\\n{}                        {} ;;\n",
+                               command.name, option_specs(command, 24), positional_spec(command)))
+        .collect::<String>();
+
+    format!("#compdef howdy
+
+_howdy_tags() {{
+    local -a tags journal
+    local i
+    for ((i = 1; i < ${{#words}}; i++)); do
+        [[ \"$words[i]\" == (-f|--file) ]] && journal=(-f \"$words[i+1]\")
+    done
+    tags=(${{(f)\"$(howdy $journal __complete-tags 2>/dev/null)\"}})
+    compadd -a tags
+}}
+
+_howdy() {{
+    local -a commands
+    commands=(
+{}    )
+    local state
+
+    _arguments -C \\
+{}        '1:command:->command' \\
+        '*::arg:->args'
+
+    case $state in
+        command)
+            _describe 'command' commands ;;
+        args)
+            case $words[1] in
+                help)
+                    _values 'command' {} ;;
+{}            esac ;;
+    esac
+}}
+
+_howdy \"$@\"
+",
+            command_descriptions, option_specs(&TOP_LEVEL, 8), command_names().join(" "), command_arms)
+}
+
+fn option_specs(command: &CommandSpec, indent: usize) -> String {
+    options(command)
+        .map(|option| format!("{:indent$}{} \\\n", "", option_spec(option), indent = indent))
+        .collect()
+}
+
+fn option_spec(option: &OptionSpec) -> String {
+    let names = option_names(option);
+    let exclusions = names.join(" ");
+    // repeatable options are not excluded after the first use
+    let prefix = if option.long == "--tag" { "*".to_string() } else { format!("({})", exclusions) };
+    // brace expansion gives the same spec for short and long names
+    let names = if names.len() > 1 { format!("{{{}}}", names.join(",")) } else { names[0].to_string() };
+
+    let argument = match option.kind {
+        OptionKind::Flag => String::new(),
+        OptionKind::Value(value_name) | OptionKind::Words(value_name) => format!(":{}:{}", value_name, action(&option_values(option))),
+    };
+
+    format!("'{}'{}'[{}]{}'", prefix, names, escape(option.help), argument)
+}
+
+fn positional_spec(command: &CommandSpec) -> String {
+    match positional_values(command) {
+        Values::Nothing => "'1:argument: '".to_string(),
+        values => format!("'*:argument:{}'", action(&values)),
+    }
+}
+
+fn action(values: &Values) -> String {
+    match values {
+        Values::Nothing => " ".to_string(),
+        Values::Files => "_files".to_string(),
+        Values::Dirs => "_files -/".to_string(),
+        Values::Tags => "_howdy_tags".to_string(),
+        Values::Commands => format!("({})", command_names().join(" ")),
+        Values::Words(words) => format!("({})", words.join(" ")),
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('[', "\\[")
+        .replace(']', "\\]")
+        .replace(':', "\\:")
+        .replace('\'', "'\\''")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::FILTER_OPTIONS;
+
+    #[test]
+    fn option_specs() {
+        let type_option = CommandSpec::find("mood").unwrap().option_groups[0].iter().next().unwrap();
+        assert_eq!(option_spec(type_option),
+            "'(-t --type)'{-t,--type}'[w, 7d, m, 30d, lm, ly or mm]:REPORT_TYPE:(w weekly 7d m monthly 30d lm ly mm moving)'");
+
+        assert_eq!(option_spec(&FILTER_OPTIONS[0]),
+            "'*'--tag'[only records with this tag, may be repeated]:TAG:_howdy_tags'");
+    }
+
+    #[test]
+    fn escaping() {
+        assert_eq!(escape("columns: [date]"), "columns\\: \\[date\\]");
+    }
+}
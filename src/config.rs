@@ -0,0 +1,398 @@
+use chrono::Weekday;
+use std::collections::BTreeMap;
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::add_command::SameDayPolicy;
+use crate::daily_aggregation::DailyAggregation;
+use crate::metric;
+use crate::mood_command::{MoodReportType, PlotBackend};
+use crate::score_scale::ScoreScale;
+use crate::timezone::{ReportDays, Timezone};
+
+const CONFIG_DIR_NAME: &str = "howdy";
+const CONFIG_FILE_NAME: &str = "config.toml";
+const LOCAL_CONFIG_FILE_NAME: &str = ".howdy.toml";
+const JOURNAL_FILE_NAME: &str = "howdy.journal";
+const CONFIG_FILE_PATH_ENV: &str = "HOWDY_CONFIG";
+
+// Settings read from config files. Everything is optional, missing settings fall back to
+// less specific config files and then to defaults.
+#[derive(Default)]
+pub struct Config {
+    pub journal_file_path: Option<String>,
+    pub report_type: Option<MoodReportType>,
+    pub week_start: Option<Weekday>,
+    pub timezone: Option<Timezone>,
+    pub report_days: Option<ReportDays>,
+    pub plot_backend: Option<PlotBackend>,
+    pub plot_date_format: Option<String>,
+    pub import_date_format: Option<String>,
+    pub normalize_tags: Option<bool>,
+    pub tag_aliases: Option<BTreeMap<String, String>>,
+    pub score_scale: Option<ScoreScale>,
+    pub daily_aggregation: Option<DailyAggregation>,
+    pub same_day_policy: Option<SameDayPolicy>,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    CannotReadFile { file_path: String, read_error: io::Error },
+    CannotParseFile { file_path: String, parse_error: toml::de::Error },
+    InvalidValue { file_path: String, key: &'static str },
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::CannotReadFile { file_path: _, read_error } => Some(read_error),
+            Self::CannotParseFile { file_path: _, parse_error } => Some(parse_error),
+            Self::InvalidValue { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::CannotReadFile { file_path, read_error: _ } => write!(f, "cannot read config file '{}'", file_path),
+            Self::CannotParseFile { file_path, parse_error: _ } => write!(f, "cannot parse config file '{}'", file_path),
+            Self::InvalidValue { file_path, key } => write!(f, "config file '{}' has invalid '{}' value", file_path, key),
+        }
+    }
+}
+
+impl Config {
+    // Project-local `.howdy.toml` takes precedence over user's config file.
+    // Both are replaced by the file `HOWDY_CONFIG` points to, if it is set.
+    pub fn load() -> Result<Self, ConfigError> {
+        let mut config = Self::default();
+
+        let file_paths = match env::var_os(CONFIG_FILE_PATH_ENV).filter(|file_path| !file_path.is_empty()) {
+            Some(file_path) => vec![Some(PathBuf::from(file_path))],
+            None => vec![user_config_file_path(), Some(PathBuf::from(LOCAL_CONFIG_FILE_NAME))],
+        };
+        for file_path in file_paths.iter().flatten() {
+            if file_path.is_file() {
+                config = Self::read(file_path)?.or(config);
+            }
+        }
+
+        Ok(config)
+    }
+
+    pub fn read(file_path: &Path) -> Result<Self, ConfigError> {
+        let file_path_string = file_path.display().to_string();
+        let text = fs::read_to_string(file_path)
+            .map_err(|read_error| ConfigError::CannotReadFile { file_path: file_path_string.clone(), read_error })?;
+
+        Self::parse(&text, file_path)
+    }
+
+    fn parse(text: &str, file_path: &Path) -> Result<Self, ConfigError> {
+        let file_path_string = file_path.display().to_string();
+        let table: toml::value::Table = toml::from_str(text)
+            .map_err(|parse_error| ConfigError::CannotParseFile { file_path: file_path_string.clone(), parse_error })?;
+        let invalid_value = |key| ConfigError::InvalidValue { file_path: file_path_string.clone(), key };
+
+        let string_value = |table: &toml::value::Table, key: &'static str| -> Result<Option<String>, ConfigError> {
+            match table.get(key) {
+                None => Ok(None),
+                Some(toml::Value::String(value)) => Ok(Some(value.clone())),
+                Some(_) => Err(invalid_value(key)),
+            }
+        };
+
+        let date_formats = match table.get("date_formats") {
+            None => toml::value::Table::new(),
+            Some(toml::Value::Table(date_formats)) => date_formats.clone(),
+            Some(_) => return Err(invalid_value("date_formats")),
+        };
+
+        let tags = match table.get("tags") {
+            None => toml::value::Table::new(),
+            Some(toml::Value::Table(tags)) => tags.clone(),
+            Some(_) => return Err(invalid_value("tags")),
+        };
+
+        let normalize_tags = match tags.get("normalize") {
+            None => None,
+            Some(toml::Value::Boolean(normalize_tags)) => Some(*normalize_tags),
+            Some(_) => return Err(invalid_value("tags.normalize")),
+        };
+
+        // alias is a key, tag it stands for is a value, e.g. `sports = "sport"`
+        let tag_aliases = match tags.get("aliases") {
+            None => None,
+            Some(toml::Value::Table(aliases)) => Some(aliases.iter()
+                .map(|(alias, tag)| match tag {
+                    toml::Value::String(tag) => Ok((alias.clone(), tag.clone())),
+                    _ => Err(invalid_value("tags.aliases")),
+                })
+                .collect::<Result<BTreeMap<String, String>, ConfigError>>()?),
+            Some(_) => return Err(invalid_value("tags.aliases")),
+        };
+
+        let score_scale = match table.get("scores") {
+            None => None,
+            Some(toml::Value::Table(scores)) => Some(parse_score_scale(scores).map_err(invalid_value)?),
+            Some(_) => return Err(invalid_value("scores")),
+        };
+
+        // relative journal path is relative to the config file, not to the current directory
+        let config_dir = file_path.parent().unwrap_or_else(|| Path::new(""));
+        let journal_file_path = string_value(&table, "journal")?
+            .map(|journal_file_path| config_dir.join(expand_home(&journal_file_path)).display().to_string());
+
+        let report_type = string_value(&table, "report_type")?
+            .map(|report_type| MoodReportType::parse(&report_type).ok_or_else(|| invalid_value("report_type")))
+            .transpose()?;
+
+        let week_start = string_value(&table, "week_start")?
+            .map(|week_start| week_start.parse::<Weekday>().map_err(|_| invalid_value("week_start")))
+            .transpose()?;
+
+        let timezone = string_value(&table, "timezone")?
+            .map(|timezone| Timezone::parse(&timezone).ok_or_else(|| invalid_value("timezone")))
+            .transpose()?;
+
+        let report_days = string_value(&table, "report_days")?
+            .map(|report_days| ReportDays::parse(&report_days).ok_or_else(|| invalid_value("report_days")))
+            .transpose()?;
+
+        let plot_backend = string_value(&table, "plot_backend")?
+            .map(|plot_backend| PlotBackend::parse(&plot_backend).ok_or_else(|| invalid_value("plot_backend")))
+            .transpose()?;
+
+        let daily_aggregation = string_value(&table, "daily_aggregation")?
+            .map(|daily_aggregation| DailyAggregation::parse(&daily_aggregation).ok_or_else(|| invalid_value("daily_aggregation")))
+            .transpose()?;
+
+        let same_day_policy = string_value(&table, "same_day")?
+            .map(|same_day_policy| SameDayPolicy::parse(&same_day_policy).ok_or_else(|| invalid_value("same_day")))
+            .transpose()?;
+
+        Ok(Self {
+            journal_file_path,
+            report_type,
+            week_start,
+            timezone,
+            report_days,
+            plot_backend,
+            plot_date_format: string_value(&date_formats, "plot")?,
+            import_date_format: string_value(&date_formats, "import")?,
+            normalize_tags,
+            tag_aliases,
+            score_scale,
+            daily_aggregation,
+            same_day_policy,
+        })
+    }
+
+    // Takes settings from `self`, and missing ones from `other`.
+    fn or(self, other: Self) -> Self {
+        Self {
+            journal_file_path: self.journal_file_path.or(other.journal_file_path),
+            report_type: self.report_type.or(other.report_type),
+            week_start: self.week_start.or(other.week_start),
+            timezone: self.timezone.or(other.timezone),
+            report_days: self.report_days.or(other.report_days),
+            plot_backend: self.plot_backend.or(other.plot_backend),
+            plot_date_format: self.plot_date_format.or(other.plot_date_format),
+            import_date_format: self.import_date_format.or(other.import_date_format),
+            normalize_tags: self.normalize_tags.or(other.normalize_tags),
+            tag_aliases: self.tag_aliases.or(other.tag_aliases),
+            score_scale: self.score_scale.or(other.score_scale),
+            daily_aggregation: self.daily_aggregation.or(other.daily_aggregation),
+            same_day_policy: self.same_day_policy.or(other.same_day_policy),
+        }
+    }
+}
+
+fn user_config_file_path() -> Option<PathBuf> {
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .filter(|config_home| !config_home.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+
+    Some(config_home.join(CONFIG_DIR_NAME).join(CONFIG_FILE_NAME))
+}
+
+// Journal in user's data directory, so the same journal is used wherever howdy is run from.
+pub fn user_journal_file_path() -> Option<String> {
+    let data_home = env::var_os("XDG_DATA_HOME")
+        .filter(|data_home| !data_home.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".local").join("share")))?;
+
+    Some(data_home.join(CONFIG_DIR_NAME).join(JOURNAL_FILE_NAME).display().to_string())
+}
+
+fn expand_home(file_path: &str) -> PathBuf {
+    match (file_path.strip_prefix("~/"), env::var_os("HOME")) {
+        (Some(relative_path), Some(home)) => Path::new(&home).join(relative_path),
+        _ => PathBuf::from(file_path),
+    }
+}
+
+// `[scores]` table with `min` and `max`, or `allowed` list, and `aliases` table of scores by names.
+// Returns the invalid key on error.
+fn parse_score_scale(scores: &toml::value::Table) -> Result<ScoreScale, &'static str> {
+    // both `1` and `7.5` are scores
+    let score = |value: &toml::Value, key| match value {
+        toml::Value::Integer(score) => Ok(*score as f64),
+        toml::Value::Float(score) if score.is_finite() => Ok(*score),
+        _ => Err(key),
+    };
+
+    let min = scores.get("min").map(|min| score(min, "scores.min")).transpose()?;
+    let max = scores.get("max").map(|max| score(max, "scores.max")).transpose()?;
+    if let (Some(min), Some(max)) = (min, max) {
+        if min > max {
+            return Err("scores.max");
+        }
+    }
+
+    let allowed = match scores.get("allowed") {
+        None => None,
+        Some(toml::Value::Array(allowed)) if !allowed.is_empty() => Some(allowed.iter()
+            .map(|allowed_score| score(allowed_score, "scores.allowed"))
+            .collect::<Result<Vec<f64>, &'static str>>()
+            .map(|mut allowed| {
+                allowed.sort_by(f64::total_cmp);
+                allowed.dedup();
+                allowed
+            })?),
+        Some(_) => return Err("scores.allowed"),
+    };
+
+    let aliases = match scores.get("aliases") {
+        None => BTreeMap::new(),
+        Some(toml::Value::Table(aliases)) => aliases.iter()
+            .map(|(alias, alias_score)| Ok((alias.trim().to_string(), score(alias_score, "scores.aliases")?)))
+            .collect::<Result<BTreeMap<String, f64>, &'static str>>()?,
+        Some(_) => return Err("scores.aliases"),
+    };
+
+    let score_scale = ScoreScale { min, max, allowed, aliases };
+    // aliases should not stand for scores which can't be added, nor hide numbers
+    let aliases_valid = score_scale.aliases.iter()
+        .all(|(alias, score)| !alias.is_empty() && metric::parse_value(alias).is_none() && score_scale.is_allowed(*score));
+    if !aliases_valid {
+        return Err("scores.aliases");
+    }
+
+    Ok(score_scale)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::FixedOffset;
+
+    use super::*;
+
+    #[test]
+    fn parsing() {
+        let text = "journal = \"journals/howdy.journal\"\n\
+                    report_type = \"weekly\"\n\
+                    week_start = \"sunday\"\n\
+                    timezone = \"+03:30\"\n\
+                    report_days = \"timezone\"\n\
+                    plot_backend = \"none\"\n\
+                    daily_aggregation = \"mean\"\n\
+                    same_day = \"replace\"\n\
+                    [date_formats]\n\
+                    plot = \"%Y-%m-%d\"\n\
+                    import = \"%d.%m.%Y\"\n\
+                    [tags]\n\
+                    normalize = true\n\
+                    [tags.aliases]\n\
+                    sports = \"sport\"\n\
+                    \"спорт\" = \"sport\"\n\
+                    [scores]\n\
+                    min = -1\n\
+                    max = 1\n\
+                    [scores.aliases]\n\
+                    good = 1\n\
+                    \"-\" = -1\n";
+        let config = Config::parse(text, Path::new("/home/user/.config/howdy/config.toml")).unwrap();
+
+        assert_eq!(config.journal_file_path.as_deref(), Some("/home/user/.config/howdy/journals/howdy.journal"));
+        assert!(matches!(config.report_type, Some(MoodReportType::WeeklyIterative)));
+        assert_eq!(config.week_start, Some(Weekday::Sun));
+        assert_eq!(config.timezone, Some(Timezone::Offset(FixedOffset::east(3 * 3600 + 30 * 60))));
+        assert_eq!(config.report_days, Some(ReportDays::Timezone));
+        assert!(matches!(config.plot_backend, Some(PlotBackend::Disabled)));
+        assert_eq!(config.daily_aggregation, Some(DailyAggregation::Mean));
+        assert_eq!(config.same_day_policy, Some(SameDayPolicy::Replace));
+        assert_eq!(config.plot_date_format.as_deref(), Some("%Y-%m-%d"));
+        assert_eq!(config.import_date_format.as_deref(), Some("%d.%m.%Y"));
+        assert_eq!(config.normalize_tags, Some(true));
+        assert_eq!(config.tag_aliases.unwrap().into_iter().collect::<Vec<(String, String)>>(),
+            vec![("sports".to_string(), "sport".to_string()), ("спорт".to_string(), "sport".to_string())]);
+        assert_eq!(config.score_scale, Some(ScoreScale {
+            min: Some(-1.0),
+            max: Some(1.0),
+            allowed: None,
+            aliases: vec![("-".to_string(), -1.0), ("good".to_string(), 1.0)].into_iter().collect(),
+        }));
+    }
+
+    #[test]
+    fn fractional_scores() {
+        let config = Config::parse("[scores]\nallowed = [1, 0.5, 1, 0]\naliases = { half = 0.5 }", Path::new("config.toml")).unwrap();
+        let score_scale = config.score_scale.unwrap();
+
+        assert_eq!(score_scale.allowed, Some(vec![0.0, 0.5, 1.0]));
+        assert_eq!(score_scale.alias("half"), Some(0.5));
+    }
+
+    #[test]
+    fn merging() {
+        let local_config = Config::parse("week_start = \"sunday\"", Path::new(".howdy.toml")).unwrap();
+        let user_config = Config::parse("week_start = \"monday\"\njournal = \"/tmp/howdy.journal\"", Path::new("config.toml")).unwrap();
+        let config = local_config.or(user_config);
+
+        assert_eq!(config.week_start, Some(Weekday::Sun));
+        assert_eq!(config.journal_file_path.as_deref(), Some("/tmp/howdy.journal"));
+        assert_eq!(config.timezone, None);
+
+        let zone_config = Config::parse("timezone = \"America/New_York\"", Path::new("config.toml")).unwrap();
+        assert_eq!(zone_config.timezone, Some(Timezone::Zone(chrono_tz::America::New_York)));
+    }
+
+    #[test]
+    fn invalid_values() {
+        let file_path = Path::new("config.toml");
+
+        assert!(matches!(Config::parse("journal = 1", file_path), Err(ConfigError::InvalidValue { key: "journal", .. })));
+        assert!(matches!(Config::parse("report_type = \"foo\"", file_path), Err(ConfigError::InvalidValue { key: "report_type", .. })));
+        assert!(matches!(Config::parse("timezone = \"Mars\"", file_path), Err(ConfigError::InvalidValue { key: "timezone", .. })));
+        assert!(matches!(Config::parse("report_days = \"utc\"", file_path), Err(ConfigError::InvalidValue { key: "report_days", .. })));
+        assert!(matches!(Config::parse("daily_aggregation = \"median\"", file_path), Err(ConfigError::InvalidValue { key: "daily_aggregation", .. })));
+        assert!(matches!(Config::parse("same_day = \"ask\"", file_path), Err(ConfigError::InvalidValue { key: "same_day", .. })));
+        assert!(matches!(Config::parse("date_formats = 1", file_path), Err(ConfigError::InvalidValue { key: "date_formats", .. })));
+        assert!(matches!(Config::parse("[tags]\nnormalize = \"yes\"", file_path), Err(ConfigError::InvalidValue { key: "tags.normalize", .. })));
+        assert!(matches!(Config::parse("[tags.aliases]\nsports = 1", file_path), Err(ConfigError::InvalidValue { key: "tags.aliases", .. })));
+        assert!(matches!(Config::parse("[scores]\nmin = 1\nmax = -1", file_path), Err(ConfigError::InvalidValue { key: "scores.max", .. })));
+        assert!(matches!(Config::parse("[scores]\nallowed = [0, \"5\"]", file_path), Err(ConfigError::InvalidValue { key: "scores.allowed", .. })));
+        assert!(matches!(Config::parse("[scores]\nallowed = [-1, 1]\naliases = { meh = 0 }", file_path),
+            Err(ConfigError::InvalidValue { key: "scores.aliases", .. })));
+        assert!(matches!(Config::parse("[scores.aliases]\n\"1\" = -1", file_path), Err(ConfigError::InvalidValue { key: "scores.aliases", .. })));
+        assert!(matches!(Config::parse("journal = ", file_path), Err(ConfigError::CannotParseFile { .. })));
+    }
+
+    #[test]
+    fn errors_display() {
+        let file_path = String::from("path/to/file");
+
+        assert_eq!(ConfigError::CannotReadFile { file_path: file_path.clone(), read_error: io::Error::other("error text") }.to_string(),
+            "cannot read config file 'path/to/file'");
+        assert_eq!(ConfigError::InvalidValue { file_path, key: "timezone" }.to_string(),
+            "config file 'path/to/file' has invalid 'timezone' value");
+    }
+}
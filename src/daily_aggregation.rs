@@ -0,0 +1,168 @@
+use chrono::NaiveDate;
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::daily_score::DailyScore;
+
+const COMMENTS_SEPARATOR: &str = "; ";
+
+// Collapses records made on the same day into one before reports, so rating a day
+// in the morning and in the evening does not double its weight.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DailyAggregation {
+    Sum,
+    Mean,
+    First,
+    Last,
+    Min,
+    Max,
+}
+
+impl DailyAggregation {
+    pub fn parse(aggregation_str: &str) -> Option<Self> {
+        match aggregation_str {
+            "sum" => Some(Self::Sum),
+            "mean" => Some(Self::Mean),
+            "first" => Some(Self::First),
+            "last" => Some(Self::Last),
+            "min" => Some(Self::Min),
+            "max" => Some(Self::Max),
+            _ => None,
+        }
+    }
+
+    // One record per local date, ordered by date.
+    pub fn apply(self, daily_scores: &[DailyScore]) -> Vec<DailyScore> {
+        let mut days: BTreeMap<NaiveDate, Vec<&DailyScore>> = BTreeMap::new();
+        for daily_score in daily_scores {
+            days.entry(daily_score.datetime.naive_local().date()).or_default().push(daily_score);
+        }
+
+        days.into_values()
+            .map(|mut day_scores| {
+                // records made at the same time keep their journal order
+                day_scores.sort_by_key(|daily_score| daily_score.datetime);
+                self.collapse(&day_scores)
+            })
+            .collect()
+    }
+
+    // `first` and `last` take the whole record, others aggregate the score and every metric separately,
+    // join tags and comments, and take the time of the last record.
+    fn collapse(self, day_scores: &[&DailyScore]) -> DailyScore {
+        let last_daily_score = day_scores[day_scores.len() - 1];
+        match self {
+            Self::First => return day_scores[0].clone(),
+            Self::Last => return last_daily_score.clone(),
+            _ => {},
+        }
+
+        let score = self.value(day_scores.iter().map(|daily_score| daily_score.score));
+        let metrics = day_scores.iter()
+            .flat_map(|daily_score| daily_score.metrics.keys())
+            .collect::<BTreeSet<&String>>()
+            .into_iter()
+            .map(|name| (name.clone(), self.value(day_scores.iter().filter_map(|daily_score| daily_score.metrics.get(name).copied()))))
+            .collect();
+        let tags = day_scores.iter()
+            .flat_map(|daily_score| daily_score.tags.iter().cloned())
+            .collect();
+        // attributes of later records win
+        let attributes = day_scores.iter()
+            .flat_map(|daily_score| daily_score.attributes.clone())
+            .collect();
+        let comments = day_scores.iter()
+            .filter_map(|daily_score| daily_score.comment.as_deref())
+            .filter(|comment| !comment.is_empty())
+            .collect::<Vec<&str>>();
+        let comment = if comments.is_empty() { None } else { Some(comments.join(COMMENTS_SEPARATOR)) };
+
+        DailyScore { score, metrics, tags, attributes, comment, datetime: last_daily_score.datetime }
+    }
+
+    // There is always at least one value, as values are taken from the day's records.
+    fn value<I: Iterator<Item = f64>>(self, values: I) -> f64 {
+        let values = values.collect::<Vec<f64>>();
+        match self {
+            Self::Sum => values.iter().sum(),
+            Self::Mean => values.iter().sum::<f64>() / values.len() as f64,
+            Self::First => values[0],
+            Self::Last => values[values.len() - 1],
+            Self::Min => values.iter().copied().fold(f64::INFINITY, f64::min),
+            Self::Max => values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+        }
+    }
+}
+
+// Records are reported as they are if no aggregation is configured.
+pub fn apply(aggregation: Option<DailyAggregation>, daily_scores: Vec<DailyScore>) -> Vec<DailyScore> {
+    match aggregation {
+        Some(aggregation) => aggregation.apply(&daily_scores),
+        None => daily_scores,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{FixedOffset, TimeZone};
+
+    use super::*;
+
+    fn daily_score(day: u32, hour: u32, score: f64, tags: &[&str], comment: &str) -> DailyScore {
+        DailyScore {
+            score,
+            metrics: Default::default(),
+            attributes: Default::default(),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            comment: Some(comment.to_string()),
+            datetime: FixedOffset::east(3600).ymd(2020, 1, day).and_hms(hour, 0, 0),
+        }
+    }
+
+    fn daily_scores() -> Vec<DailyScore> {
+        vec![
+            daily_score(1, 20, 2.0, &["run"], "evening"),
+            daily_score(1, 8, -1.0, &["work"], "morning"),
+            daily_score(2, 12, 1.0, &[], ""),
+            daily_score(1, 13, 0.5, &["work"], ""),
+        ]
+    }
+
+    fn scores(aggregation: DailyAggregation) -> Vec<f64> {
+        aggregation.apply(&daily_scores()).iter().map(|daily_score| daily_score.score).collect()
+    }
+
+    #[test]
+    fn aggregation_parsing() {
+        assert_eq!(DailyAggregation::parse("mean"), Some(DailyAggregation::Mean));
+        assert_eq!(DailyAggregation::parse("last"), Some(DailyAggregation::Last));
+        assert_eq!(DailyAggregation::parse("average"), None);
+    }
+
+    #[test]
+    fn scores_aggregation() {
+        assert_eq!(scores(DailyAggregation::Sum), vec![1.5, 1.0]);
+        assert_eq!(scores(DailyAggregation::Mean), vec![0.5, 1.0]);
+        assert_eq!(scores(DailyAggregation::First), vec![-1.0, 1.0]);
+        assert_eq!(scores(DailyAggregation::Last), vec![2.0, 1.0]);
+        assert_eq!(scores(DailyAggregation::Min), vec![-1.0, 1.0]);
+        assert_eq!(scores(DailyAggregation::Max), vec![2.0, 1.0]);
+        assert_eq!(apply(None, daily_scores()).len(), 4);
+    }
+
+    #[test]
+    fn records_merging() {
+        let mut daily_scores = daily_scores();
+        daily_scores[0].metrics.insert("sleep".to_string(), 7.0);
+        daily_scores[1].metrics.insert("energy".to_string(), 2.0);
+        daily_scores[3].metrics.insert("energy".to_string(), -1.0);
+
+        let day = &DailyAggregation::Mean.apply(&daily_scores)[0];
+        assert_eq!(day.metrics, vec![("energy".to_string(), 0.5), ("sleep".to_string(), 7.0)].into_iter().collect());
+        assert_eq!(day.tags_string(), "run,work");
+        assert_eq!(day.comment.as_deref(), Some("morning; evening"));
+        assert_eq!(day.datetime, FixedOffset::east(3600).ymd(2020, 1, 1).and_hms(20, 0, 0));
+
+        let first = &DailyAggregation::First.apply(&daily_scores)[0];
+        assert!(first.same_record(&daily_scores[1]));
+    }
+}
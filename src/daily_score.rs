@@ -1,15 +1,25 @@
 use chrono::prelude::{DateTime, FixedOffset};
 use std::fmt;
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
+
+use crate::attribute;
+use crate::metric;
+use crate::tag;
 
 #[cfg(test)]
 use chrono::prelude::Utc;
 
-const DATE_FORMAT: &str = "%Y-%m-%d %H:%M:%S %z";
+pub const DATE_FORMAT: &str = "%Y-%m-%d %H:%M:%S %z";
 
+#[derive(Clone)]
 pub struct DailyScore {
-    pub score: i8,
+    // integer in older journals, may be fractional or out of -128..127 now, e.g. `7.5`
+    pub score: f64,
+    // named metrics stored along with the score, e.g. `energy=-1 sleep=7.5`
+    pub metrics: BTreeMap<String, f64>,
     pub tags: HashSet<String>,
+    // typed `KEY=VALUE` attributes, written along with tags, e.g. `weather=rain`
+    pub attributes: BTreeMap<String, String>,
     pub comment: Option<String>,
     pub datetime: DateTime<FixedOffset>,
 }
@@ -20,6 +30,8 @@ pub enum ParseError {
     InvalidDateTime(String),
     MissingScore,
     InvalidScore(String),
+    InvalidMetric(String),
+    InvalidTag(String),
 }
 
 impl std::error::Error for ParseError {}
@@ -30,6 +42,8 @@ impl fmt::Display for ParseError {
             ParseError::InvalidDateTime(date_string) => format!("'{}' is not a valid datetime", date_string),
             ParseError::MissingScore => "missing score".to_string(),
             ParseError::InvalidScore(score_string) => format!("'{}' is not a valid score", score_string),
+            ParseError::InvalidMetric(metric_string) => format!("'{}' is not a valid metric, expected NAME=VALUE", metric_string),
+            ParseError::InvalidTag(tag) => format!("'{}' is not a valid tag", tag),
         };
 
         write!(f, "{}", message)
@@ -39,24 +53,24 @@ impl fmt::Display for ParseError {
 impl DailyScore {
     #[cfg(test)]
     pub fn new() -> Self {
-        Self { score: 0, comment: None, tags: HashSet::new(), datetime: Utc::now().into() }
+        Self { score: 0.0, metrics: BTreeMap::new(), attributes: BTreeMap::new(), comment: None, tags: HashSet::new(), datetime: Utc::now().into() }
     }
 
     #[cfg(test)]
-    pub fn with_score(score: i8) -> Self {
-        Self { score, ..Self::new() }
+    pub fn with_score<S: Into<f64>>(score: S) -> Self {
+        Self { score: score.into(), ..Self::new() }
     }
 
     pub fn to_s(&self) -> String {
-        let comment_string = match &self.comment {
+        let comment_string = match self.comment.as_deref() {
+            Some("") | None => "".to_string(),
             Some(comment_string) => format!(" {}", comment_string),
-            None => "".to_string(),
         };
 
         format!("{} {} {} {} {} {}{}",
                 self.datetime.format(DATE_FORMAT),
                 crate::JOURNAL_SEPARATOR,
-                self.score,
+                self.score_string(),
                 crate::JOURNAL_SEPARATOR,
                 self.tags_string(),
                 crate::JOURNAL_SEPARATOR,
@@ -76,25 +90,67 @@ impl DailyScore {
         let score_str = slice.next()
             .ok_or(ParseError::MissingScore)?;
 
-        let score = score_str.parse::<i8>()
-            .map_err(|_| ParseError::InvalidScore(score_str.to_string()))?;
+        // score may be followed by named metrics: `1 energy=-1 sleep=7.5`
+        let (score_str, metrics_str) = score_str.split_once(char::is_whitespace).unwrap_or((score_str, ""));
+        let score = metric::parse_value(score_str)
+            .ok_or_else(|| ParseError::InvalidScore(score_str.to_string()))?;
+        let metrics = metric::parse_metrics(metrics_str).map_err(ParseError::InvalidMetric)?;
 
         let tags_str = slice.next().unwrap_or("");
-        let tags = if tags_str.is_empty() {
-            HashSet::new()
+        let (tags, attributes) = if tags_str.is_empty() {
+            (HashSet::new(), BTreeMap::new())
         } else {
-            tags_str.split(',').map(str::to_string).collect()
+            attribute::split_tags(tags_str.split(',').map(str::to_string))
         };
 
         let comment = slice.next().map(str::to_string);
-        Ok(DailyScore { score, tags, comment, datetime })
+        Ok(DailyScore { score, metrics, tags, attributes, comment, datetime })
+    }
+
+    // Comments read from journal are always present (maybe empty), so missing
+    // and empty comments are considered to be the same.
+    pub fn same_record(&self, other: &Self) -> bool {
+        self.datetime == other.datetime &&
+            self.score == other.score &&
+            self.metrics == other.metrics &&
+            self.tags == other.tags &&
+            self.attributes == other.attributes &&
+            self.comment.as_deref().unwrap_or("") == other.comment.as_deref().unwrap_or("")
     }
 
+    // Same as `same_record`, but only compares local dates, which is useful
+    // for sources that do not store time, like xlsx export.
+    pub fn same_day_record(&self, other: &Self) -> bool {
+        self.datetime.naive_local().date() == other.datetime.naive_local().date() &&
+            self.score == other.score &&
+            self.metrics == other.metrics &&
+            self.tags == other.tags &&
+            self.attributes == other.attributes &&
+            self.comment.as_deref().unwrap_or("") == other.comment.as_deref().unwrap_or("")
+    }
+
+    // Score followed by metrics, as it is written to the journal.
+    pub fn score_string(&self) -> String {
+        if self.metrics.is_empty() {
+            self.score.to_string()
+        } else {
+            format!("{} {}", self.score, metric::format_metrics(&self.metrics))
+        }
+    }
+
+    // Tags followed by attributes, as they are written to the journal.
     pub fn tags_string(&self) -> String {
-        let mut tags_vec = self.tags.iter().map(String::as_str).collect::<Vec<&str>>();
+        let mut tags_vec = self.tags.iter().cloned().collect::<Vec<String>>();
         tags_vec.sort_unstable();
+        tags_vec.extend(self.attributes.iter().map(|(key, value)| attribute::format_attribute(key, value)));
         tags_vec.join(crate::TAGS_SEPARATOR)
     }
+
+    // Tag filters match child tags and attributes as well, e.g. `sport` matches `sport/running`.
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|own_tag| tag::matches(own_tag, tag)) ||
+            attribute::parse_attribute(tag).is_some_and(|(key, value)| self.attributes.get(&key) == Some(&value))
+    }
 }
 
 #[cfg(test)]
@@ -107,24 +163,30 @@ mod tests {
     fn tags_formatting() {
         let local_date = FixedOffset::east(4 * 3600).ymd(2020, 1, 1).and_hms(9, 10, 11);
         let score1 = DailyScore {
-            score: 1,
+            score: 1.0,
+            metrics: Default::default(),
+            attributes: Default::default(),
             comment: Some("foo || bar".to_string()),
             tags: vec!["run".to_string(), "games".to_string()].into_iter().collect(),
-            datetime: local_date.into()
+            datetime: local_date
         };
 
         let score2 = DailyScore {
-            score: 1,
+            score: 1.0,
+            metrics: Default::default(),
+            attributes: Default::default(),
             comment: None,
             tags: HashSet::new(),
-            datetime: local_date.into()
+            datetime: local_date
         };
 
         let score3 = DailyScore {
-            score: 1,
+            score: 1.0,
+            metrics: Default::default(),
+            attributes: Default::default(),
             comment: None,
             tags: vec!["run".to_string()].into_iter().collect(),
-            datetime: local_date.into()
+            datetime: local_date
         };
 
         assert_eq!(score1.tags_string(), "games,run");
@@ -136,10 +198,12 @@ mod tests {
     fn string_formatting() {
         let local_date = FixedOffset::east(4 * 3600).ymd(2020, 1, 1).and_hms(9, 10, 11);
         let score = DailyScore {
-            score: 1,
+            score: 1.0,
+            metrics: Default::default(),
+            attributes: Default::default(),
             comment: Some("foo || bar".to_string()),
             tags: vec!["run".to_string(), "games".to_string()].into_iter().collect(),
-            datetime: local_date.into()
+            datetime: local_date
         };
 
         assert_eq!(score.to_s(), "2020-01-01 09:10:11 +0400 | 1 | games,run | foo || bar")
@@ -153,7 +217,7 @@ mod tests {
         assert!(daily_score_parse_result.is_ok());
 
         let daily_score = daily_score_parse_result.unwrap();
-        assert_eq!(daily_score.score, 1);
+        assert_eq!(daily_score.score, 1.0);
         assert_eq!(daily_score.comment, Some("foo || bar".to_string()));
         assert_eq!(daily_score.tags, HashSet::new());
         assert_eq!(Utc.ymd(2020, 2, 1).and_hms(7, 10, 11), daily_score.datetime);
@@ -169,6 +233,77 @@ mod tests {
         assert!(daily_score.tags.contains("foo"));
     }
 
+    #[test]
+    fn fractional_scores() {
+        let daily_score = DailyScore::parse("2020-02-01 09:10:11 +0200 | 7.5 | run | ").unwrap();
+        assert_eq!(daily_score.score, 7.5);
+        assert_eq!(daily_score.to_s(), "2020-02-01 09:10:11 +0200 | 7.5 | run |");
+
+        // integer scores of older journals are written back the same way
+        assert_eq!(DailyScore::parse("2020-02-01 09:10:11 +0200 | -1 | run | ").unwrap().to_s(), "2020-02-01 09:10:11 +0200 | -1 | run |");
+        assert_eq!(DailyScore::parse("2020-02-01 09:10:11 +0200 | -0 | run | ").unwrap().score_string(), "0");
+        assert_eq!(DailyScore::parse("2020-02-01 09:10:11 +0200 | 250 | run | ").unwrap().score, 250.0);
+        assert_eq!(DailyScore::parse("2020-02-01 09:10:11 +0200 | NaN | run | ").err(), Some(ParseError::InvalidScore("NaN".to_string())));
+    }
+
+    #[test]
+    fn attributes_parsing_and_formatting() {
+        let daily_score = DailyScore::parse("2020-02-01 09:10:11 +0200 | 1 | weather=rain,run,location=home | ").unwrap();
+
+        assert_eq!(daily_score.tags, vec!["run".to_string()].into_iter().collect());
+        assert_eq!(daily_score.attributes.get("weather"), Some(&"rain".to_string()));
+        assert_eq!(daily_score.attributes.get("location"), Some(&"home".to_string()));
+        assert_eq!(daily_score.tags_string(), "run,location=home,weather=rain");
+        assert!(daily_score.has_tag("weather=rain"));
+        assert!(!daily_score.has_tag("weather=sun"));
+
+        let daily_score = DailyScore::parse("2020-02-01 09:10:11 +0200 | 1 | a=b=c | ").unwrap();
+        assert!(daily_score.tags.contains("a=b=c"));
+        assert!(daily_score.attributes.is_empty());
+    }
+
+    #[test]
+    fn repeated_attributes_round_trip() {
+        let line = "2020-02-01 09:10:11 +0200 | 1 | weather=sun,run,weather=rain | ";
+        let daily_score = DailyScore::parse(line).unwrap();
+
+        assert_eq!(daily_score.attributes.get("weather"), Some(&"rain".to_string()));
+        assert!(daily_score.tags.contains("weather=sun"));
+        assert_eq!(daily_score.to_s(), "2020-02-01 09:10:11 +0200 | 1 | run,weather=sun,weather=rain |");
+
+        let reread_score = DailyScore::parse(&daily_score.to_s()).unwrap();
+        assert_eq!(reread_score.to_s(), daily_score.to_s());
+        assert_eq!(reread_score.attributes, daily_score.attributes);
+        assert_eq!(reread_score.tags, daily_score.tags);
+    }
+
+    #[test]
+    fn child_tags_matching() {
+        let daily_score = DailyScore::parse("2020-02-01 09:10:11 +0200 | 1 | sport/running,work | ").unwrap();
+
+        assert!(daily_score.has_tag("sport"));
+        assert!(daily_score.has_tag("sport/running"));
+        assert!(!daily_score.has_tag("sport/swimming"));
+        assert!(!daily_score.has_tag("sport/run"));
+        assert!(!daily_score.has_tag("sport/running/trail"));
+    }
+
+    #[test]
+    fn metrics_parsing_and_formatting() {
+        let daily_score_string = "2020-02-01 09:10:11 +0200 | -1 sleep=7.5  energy=2 | run | ";
+        let daily_score = DailyScore::parse(daily_score_string).unwrap();
+
+        assert_eq!(daily_score.score, -1.0);
+        assert_eq!(daily_score.metrics.get("sleep"), Some(&7.5));
+        assert_eq!(daily_score.metrics.get("energy"), Some(&2.0));
+        assert_eq!(daily_score.to_s(), "2020-02-01 09:10:11 +0200 | -1 energy=2 sleep=7.5 | run |");
+
+        assert_eq!(DailyScore::parse("2020-02-01 09:10:11 +0200 | 1 sleep | run | ").err(),
+            Some(ParseError::InvalidMetric("sleep".to_string())));
+        assert_eq!(DailyScore::parse("2020-02-01 09:10:11 +0200 | 1 score=2 | run | ").err(),
+            Some(ParseError::InvalidMetric("score=2".to_string())));
+    }
+
     #[test]
     fn invalid_string_parsing() {
         assert_eq!(DailyScore::parse("").err().unwrap(), ParseError::InvalidDateTime("".to_string()));
@@ -195,7 +330,7 @@ mod tests {
     fn new_defaults() {
         let daily_score = DailyScore::new();
 
-        assert_eq!(daily_score.score, 0);
+        assert_eq!(daily_score.score, 0.0);
         assert_eq!(daily_score.tags, HashSet::new());
         assert_eq!(daily_score.comment, None);
     }
@@ -204,7 +339,7 @@ mod tests {
     fn new_with_score() {
         let daily_score = DailyScore::with_score(5);
 
-        assert_eq!(daily_score.score, 5);
+        assert_eq!(daily_score.score, 5.0);
         assert_eq!(daily_score.tags, HashSet::new());
         assert_eq!(daily_score.comment, None);
     }
@@ -215,5 +350,7 @@ mod tests {
         assert_eq!(ParseError::InvalidDateTime("foo bar".to_string()).to_string(), "'foo bar' is not a valid datetime");
         assert_eq!(ParseError::MissingScore.to_string(), "missing score");
         assert_eq!(ParseError::InvalidScore("foo".to_string()).to_string(), "'foo' is not a valid score");
+        assert_eq!(ParseError::InvalidMetric("foo".to_string()).to_string(), "'foo' is not a valid metric, expected NAME=VALUE");
+        assert_eq!(ParseError::InvalidTag("a|b".to_string()).to_string(), "'a|b' is not a valid tag");
     }
 }
@@ -3,15 +3,46 @@ use std::fmt;
 
 use crate::GlobalConfig;
 use crate::journal;
+use crate::daily_score::DailyScore;
+use crate::mood_report::MoodReport;
+use crate::filter::Filter;
+use crate::metric::Metric;
+use crate::redaction::Redaction;
 
 pub struct ExportCommand {
     pub global_config: GlobalConfig,
     pub export_type: ExportType,
     pub file_path: String,
+    pub with_summary: bool,
+    pub filter: Filter,
+    pub metric: Metric,
+    pub redaction: Redaction,
 }
 
 pub enum ExportType {
     Xlsx,
+    Csv,
+    Json,
+    Ndjson,
+    Ics,
+}
+
+impl ExportType {
+    pub fn from_file_path(file_path: &str) -> Self {
+        let file_path = file_path.to_lowercase();
+
+        if file_path.ends_with(".csv") {
+            Self::Csv
+        } else if file_path.ends_with(".json") {
+            Self::Json
+        } else if file_path.ends_with(".ndjson") || file_path.ends_with(".jsonl") {
+            Self::Ndjson
+        } else if file_path.ends_with(".ics") {
+            Self::Ics
+        } else {
+            Self::Xlsx
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -40,16 +71,29 @@ impl fmt::Display for ExportCommandError {
 
 impl ExportCommand {
     pub fn run(self) -> Result<(), ExportCommandError> {
-        let daily_scores = journal::read(&self.global_config.journal_file_path)
+        let journal_daily_scores = journal::read(&self.global_config.journal_file_path, &self.global_config.tag_rules)
             .map_err(ExportCommandError::ReadError)?;
+        let redact = |daily_scores: Vec<DailyScore>| daily_scores.into_iter()
+            .map(|daily_score| self.redaction.apply(daily_score))
+            .collect::<Vec<DailyScore>>();
+        // summaries and calendar days are reports, so they are made of days the way reports see them
+        let report_daily_scores = || redact(self.global_config.report_daily_scores(journal_daily_scores.clone(), &self.filter));
+        let daily_scores = redact(journal_daily_scores.iter().filter(|daily_score| self.filter.matches(daily_score)).cloned().collect());
 
         match self.export_type {
-            ExportType::Xlsx =>  {
-                journal::write_xlsx(&self.file_path, &daily_scores)
-                    .map_err(ExportCommandError::WriteError)?;
-                println!("Export to '{}' done", self.file_path);
+            ExportType::Xlsx => {
+                let summary_daily_scores = if self.with_summary { report_daily_scores() } else { Vec::new() };
+                let filter = Filter::default();
+                let mood_report = MoodReport { daily_scores: &summary_daily_scores, filter: &filter, metric: &self.metric, timezone: self.global_config.timezone };
+                let summary = if self.with_summary { Some(&mood_report) } else { None };
+                journal::write_xlsx(&self.file_path, &daily_scores, summary)
             },
-        }
+            ExportType::Csv => journal::write_csv(&self.file_path, &daily_scores),
+            ExportType::Json => journal::write_json(&self.file_path, &daily_scores),
+            ExportType::Ndjson => journal::write_ndjson(&self.file_path, &daily_scores),
+            ExportType::Ics => journal::write_ics(&self.file_path, &report_daily_scores(), &self.global_config.journal_file_path),
+        }.map_err(ExportCommandError::WriteError)?;
+        println!("Export to '{}' done", self.file_path);
 
         Ok(())
     }
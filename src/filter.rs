@@ -0,0 +1,66 @@
+use chrono::NaiveDate;
+use std::collections::HashSet;
+
+use crate::daily_score::DailyScore;
+
+// Selects daily scores which make input into reports and exports.
+// All conditions should be met for a record to pass, unset conditions are ignored.
+#[derive(Default, Clone)]
+pub struct Filter {
+    pub tags: HashSet<String>,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub min_score: Option<f64>,
+    pub max_score: Option<f64>,
+}
+
+impl Filter {
+    #[cfg(test)]
+    pub fn with_tags(tags: HashSet<String>) -> Self {
+        Self { tags, ..Self::default() }
+    }
+
+    pub fn matches(&self, daily_score: &DailyScore) -> bool {
+        let date = daily_score.datetime.naive_local().date();
+
+        self.tags.iter().all(|tag| daily_score.has_tag(tag)) &&
+            self.from.is_none_or(|from| date >= from) &&
+            self.to.is_none_or(|to| date <= to) &&
+            self.min_score.is_none_or(|min_score| daily_score.score >= min_score) &&
+            self.max_score.is_none_or(|max_score| daily_score.score <= max_score)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{FixedOffset, TimeZone};
+
+    use super::*;
+
+    #[test]
+    fn matching() {
+        let daily_score = DailyScore {
+            score: 1.0,
+            metrics: Default::default(),
+            attributes: vec![("weather".to_string(), "rain".to_string())].into_iter().collect(),
+            tags: vec!["run".to_string(), "work".to_string()].into_iter().collect(),
+            comment: None,
+            datetime: FixedOffset::east(4 * 3600).ymd(2020, 1, 1).and_hms(1, 0, 0),
+        };
+        let date = NaiveDate::from_ymd(2020, 1, 1);
+
+        assert!(Filter::default().matches(&daily_score));
+        assert!(Filter::with_tags(vec!["run".to_string()].into_iter().collect()).matches(&daily_score));
+        assert!(!Filter::with_tags(vec!["run".to_string(), "games".to_string()].into_iter().collect()).matches(&daily_score));
+        assert!(Filter::with_tags(vec!["run".to_string(), "weather=rain".to_string()].into_iter().collect()).matches(&daily_score));
+        assert!(!Filter::with_tags(vec!["weather=sun".to_string()].into_iter().collect()).matches(&daily_score));
+
+        assert!(Filter { from: Some(date), to: Some(date), ..Filter::default() }.matches(&daily_score));
+        assert!(!Filter { from: date.succ_opt(), ..Filter::default() }.matches(&daily_score));
+        assert!(!Filter { to: date.pred_opt(), ..Filter::default() }.matches(&daily_score));
+
+        assert!(Filter { min_score: Some(1.0), max_score: Some(1.0), ..Filter::default() }.matches(&daily_score));
+        assert!(!Filter { min_score: Some(1.5), ..Filter::default() }.matches(&daily_score));
+        assert!(!Filter { max_score: Some(0.5), ..Filter::default() }.matches(&daily_score));
+    }
+}
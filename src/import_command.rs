@@ -0,0 +1,177 @@
+use std::error::Error;
+use std::fmt;
+use std::io::{self, BufRead, IsTerminal, Write};
+
+use crate::GlobalConfig;
+use crate::journal::{self, CsvFormat, RecordMatch};
+
+pub struct ImportCommand {
+    pub global_config: GlobalConfig,
+    pub import_type: ImportType,
+    pub import_mode: ImportMode,
+    pub file_path: String,
+    pub skip_confirmation: bool,
+}
+
+pub enum ImportType {
+    Csv(CsvFormat),
+    Json,
+    Ndjson,
+    Xlsx,
+}
+
+impl ImportType {
+    pub fn from_file_path(file_path: &str, csv_format: CsvFormat) -> Self {
+        let file_path = file_path.to_lowercase();
+
+        if file_path.ends_with(".json") {
+            Self::Json
+        } else if file_path.ends_with(".ndjson") || file_path.ends_with(".jsonl") {
+            Self::Ndjson
+        } else if file_path.ends_with(".xlsx") {
+            Self::Xlsx
+        } else {
+            Self::Csv(csv_format)
+        }
+    }
+
+    // xlsx export stores only dates, so records can't be matched by exact time
+    fn record_match(&self) -> RecordMatch {
+        match self {
+            Self::Xlsx => RecordMatch::SameDay,
+            _ => RecordMatch::Exact,
+        }
+    }
+}
+
+pub enum ImportMode {
+    Merge,
+    Replace,
+}
+
+#[derive(Debug)]
+pub enum ImportCommandError {
+    ReadError(journal::JournalError),
+    InvalidImportFile(journal::JournalError),
+    WriteError(journal::JournalError),
+    CannotReadConfirmation(io::Error),
+    ConfirmationRequired,
+}
+
+impl std::error::Error for ImportCommandError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::ReadError(journal_error) => Some(journal_error),
+            Self::InvalidImportFile(journal_error) => Some(journal_error),
+            Self::WriteError(journal_error) => Some(journal_error),
+            Self::CannotReadConfirmation(io_error) => Some(io_error),
+            Self::ConfirmationRequired => None,
+        }
+    }
+}
+
+impl fmt::Display for ImportCommandError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::ReadError(_journal_error) => write!(f, "cannot parse journal"),
+            Self::InvalidImportFile(_journal_error) => write!(f, "cannot read imported file"),
+            Self::WriteError(_journal_error) => write!(f, "cannot write to journal"),
+            Self::CannotReadConfirmation(_io_error) => write!(f, "cannot read import confirmation"),
+            Self::ConfirmationRequired => write!(f, "journal records would be removed, use --yes to confirm the import"),
+        }
+    }
+}
+
+impl ImportCommand {
+    pub fn run(self) -> Result<(), ImportCommandError> {
+        let journal_file_path = &self.global_config.journal_file_path;
+        let daily_scores = journal::read_if_exists(journal_file_path, &self.global_config.tag_rules)
+            .map_err(ImportCommandError::ReadError)?;
+
+        let mut imported_daily_scores = match &self.import_type {
+            ImportType::Csv(csv_format) => journal::read_csv(&self.file_path, csv_format),
+            ImportType::Json => journal::read_json(&self.file_path),
+            ImportType::Ndjson => journal::read_ndjson(&self.file_path),
+            ImportType::Xlsx => journal::read_xlsx(&self.file_path),
+        }.map_err(ImportCommandError::InvalidImportFile)?;
+        for daily_score in imported_daily_scores.iter_mut() {
+            daily_score.tags = self.global_config.tag_rules.apply_all(&daily_score.tags);
+        }
+
+        let record_match = self.import_type.record_match();
+        let new_daily_scores = match self.import_mode {
+            ImportMode::Merge => journal::merge(&daily_scores, imported_daily_scores, record_match),
+            ImportMode::Replace => journal::replace(&daily_scores, imported_daily_scores, record_match),
+        };
+
+        let (removed_daily_scores, added_daily_scores) = journal::diff(&daily_scores, &new_daily_scores);
+        if removed_daily_scores.is_empty() && added_daily_scores.is_empty() {
+            println!("Nothing to import from '{}'", self.file_path);
+            return Ok(())
+        }
+
+        for daily_score in removed_daily_scores.iter() {
+            println!("- {}", daily_score.to_s());
+        }
+        for daily_score in added_daily_scores.iter() {
+            println!("+ {}", daily_score.to_s());
+        }
+        println!("{} records to add, {} records to remove", added_daily_scores.len(), removed_daily_scores.len());
+
+        // there is nobody to ask when the input is piped or redirected, e.g. in scripts,
+        // so only added records are applied, and removing ones needs `--yes`
+        let stdin = io::stdin();
+        if !self.skip_confirmation {
+            if !stdin.is_terminal() {
+                if !removed_daily_scores.is_empty() {
+                    return Err(ImportCommandError::ConfirmationRequired);
+                }
+            } else if !confirm(stdin.lock())? {
+                println!("Import cancelled");
+                return Ok(())
+            }
+        }
+
+        journal::write(journal_file_path, &new_daily_scores)
+            .map_err(ImportCommandError::WriteError)?;
+        println!("Import from '{}' done", self.file_path);
+
+        Ok(())
+    }
+}
+
+fn confirm<R: BufRead>(mut input: R) -> Result<bool, ImportCommandError> {
+    print!("Apply changes to the journal? [y/N] ");
+    io::stdout().flush().map_err(ImportCommandError::CannotReadConfirmation)?;
+
+    let mut answer = String::new();
+    input.read_line(&mut answer).map_err(ImportCommandError::CannotReadConfirmation)?;
+
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn confirmation() {
+        assert!(confirm("y\n".as_bytes()).unwrap());
+        assert!(confirm(" Yes \n".as_bytes()).unwrap());
+        assert!(!confirm("n\n".as_bytes()).unwrap());
+        assert!(!confirm("".as_bytes()).unwrap());
+    }
+
+    #[test]
+    fn errors_display() {
+        let journal_error = || journal::JournalError::XlsxWriteError(io::Error::other("error text"));
+
+        assert_eq!(ImportCommandError::ReadError(journal_error()).to_string(), "cannot parse journal");
+        assert_eq!(ImportCommandError::InvalidImportFile(journal_error()).to_string(), "cannot read imported file");
+        assert_eq!(ImportCommandError::WriteError(journal_error()).to_string(), "cannot write to journal");
+        assert_eq!(ImportCommandError::CannotReadConfirmation(io::Error::other("error text")).to_string(),
+            "cannot read import confirmation");
+        assert_eq!(ImportCommandError::ConfirmationRequired.to_string(),
+            "journal records would be removed, use --yes to confirm the import");
+    }
+}
@@ -1,19 +1,95 @@
 use simple_excel_writer as excel;
 use excel::{row, Row, Workbook, Column};
+use calamine::{open_workbook, Data, DataType, Reader, Xlsx, XlsxError};
+use chrono::{DateTime, Duration, FixedOffset, Local, NaiveDate, NaiveDateTime, TimeZone};
 
-use std::fs::OpenOptions;
+use std::fs::{self, File, OpenOptions};
+use std::path::Path;
 use crate::daily_score;
 use crate::daily_score::DailyScore;
+use crate::attribute;
+use crate::metric;
+use crate::mood_report::{MoodReport, StreakKind};
+use crate::tag::{self, TagRules};
 use std::{io, fmt};
-use std::io::{BufRead, BufReader};
+use std::collections::{BTreeMap, HashSet};
+use std::io::{BufRead, BufReader, Write};
 use std::error::Error;
 
+mod csv;
+mod ics;
+pub mod json;
+
+const CSV_HEADER: [&str; 5] = ["Date", "Score", "Tags", "Comment", "Metrics"];
+const XLSX_HEADER: [&str; 5] = CSV_HEADER;
+// files exported before metrics were added have no metrics column
+const XLSX_REQUIRED_COLUMNS: usize = 4;
+const XLSX_SHEET_NAME: &str = "Daily Scores";
+const CSV_FALLBACK_DATE_FORMAT: &str = "%Y-%m-%d";
+const DAY_SECONDS: i64 = 24 * 3600;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CsvColumn {
+    Date,
+    Score,
+    Tags,
+    Comment,
+    Metrics,
+    Skip,
+}
+
+impl CsvColumn {
+    pub fn parse(column_name: &str) -> Option<Self> {
+        match column_name.trim().to_lowercase().as_str() {
+            "date" | "datetime" => Some(Self::Date),
+            "score" => Some(Self::Score),
+            "tags" => Some(Self::Tags),
+            "comment" => Some(Self::Comment),
+            "metrics" => Some(Self::Metrics),
+            "_" | "-" => Some(Self::Skip),
+            _ => None,
+        }
+    }
+}
+
+// Defines how imported records are matched to the journal ones.
+#[derive(Clone, Copy)]
+pub enum RecordMatch {
+    Exact,
+    SameDay,
+}
+
+impl RecordMatch {
+    fn matches(self, daily_score: &DailyScore, other: &DailyScore) -> bool {
+        match self {
+            Self::Exact => daily_score.same_record(other),
+            Self::SameDay => daily_score.same_day_record(other),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct CsvFormat {
+    pub date_format: Option<String>,
+    pub columns: Option<Vec<CsvColumn>>,
+}
+
 #[derive(Debug)]
 pub enum JournalError {
     CannotOpenFile { file_path: String, open_error: io::Error },
     CannotReadLine { file_path: String, read_error: io::Error },
     DailyScoreParseError { line: String, daily_score_parse_error: daily_score::ParseError },
+    CannotWriteFile { file_path: String, write_error: io::Error },
     XlsxWriteError(io::Error),
+    CsvUnterminatedQuote { file_path: String },
+    CsvUnknownColumn(String),
+    CsvMissingColumn(&'static str),
+    JsonParseError { file_path: String, json_error: serde_json::Error },
+    JsonInvalidRecord { record: String, field: &'static str },
+    JsonNotRecord(String),
+    XlsxReadError { file_path: String, xlsx_error: XlsxError },
+    XlsxInvalidHeader,
+    XlsxInvalidRow { row: usize, field: &'static str },
 }
 
 impl fmt::Display for JournalError {
@@ -22,7 +98,17 @@ impl fmt::Display for JournalError {
             Self::CannotOpenFile { file_path, open_error: _ } => write!(f, "cannot open journal file '{}'", file_path),
             Self::CannotReadLine { file_path, read_error: _ } => write!(f, "cannot read line from journal file '{}'", file_path),
             Self::DailyScoreParseError { line, daily_score_parse_error: _ } => write!(f, "cannot parse daily score data '{}'", line),
+            Self::CannotWriteFile { file_path, write_error: _ } => write!(f, "cannot write to file '{}'", file_path),
             Self::XlsxWriteError(_) => write!(f, "cannot write to xlsx file"),
+            Self::CsvUnterminatedQuote { file_path } => write!(f, "csv file '{}' has unterminated quoted field", file_path),
+            Self::CsvUnknownColumn(column_name) => write!(f, "csv column '{}' is not recognized", column_name),
+            Self::CsvMissingColumn(column_name) => write!(f, "csv file has no '{}' column", column_name),
+            Self::JsonParseError { file_path, json_error: _ } => write!(f, "cannot parse json file '{}'", file_path),
+            Self::JsonInvalidRecord { record, field } => write!(f, "json record '{}' has invalid '{}' field", record, field),
+            Self::JsonNotRecord(value) => write!(f, "json value '{}' is neither record nor array of records", value),
+            Self::XlsxReadError { file_path, xlsx_error: _ } => write!(f, "cannot read '{}' sheet from xlsx file '{}'", XLSX_SHEET_NAME, file_path),
+            Self::XlsxInvalidHeader => write!(f, "xlsx sheet header is not '{}'", XLSX_HEADER[..XLSX_REQUIRED_COLUMNS].join(", ")),
+            Self::XlsxInvalidRow { row, field } => write!(f, "xlsx row {} has invalid '{}' field", row, field),
         }
     }
 }
@@ -33,12 +119,17 @@ impl std::error::Error for JournalError {
             Self::CannotOpenFile { file_path: _, open_error } => Some(open_error),
             Self::CannotReadLine { file_path: _, read_error } => Some(read_error),
             Self::DailyScoreParseError { line: _, daily_score_parse_error } => Some(daily_score_parse_error),
+            Self::CannotWriteFile { file_path: _, write_error } => Some(write_error),
             Self::XlsxWriteError(error) => Some(error),
+            Self::JsonParseError { file_path: _, json_error } => Some(json_error),
+            Self::XlsxReadError { file_path: _, xlsx_error } => Some(xlsx_error),
+            _ => None,
         }
     }
 }
 
-pub fn read(file_path: &str) -> Result<Vec<DailyScore>, JournalError> {
+// Tags are read with configured aliases and normalization applied.
+pub fn read(file_path: &str, tag_rules: &TagRules) -> Result<Vec<DailyScore>, JournalError> {
     let mut records = Vec::<DailyScore>::new();
 
     let file = OpenOptions::new()
@@ -56,42 +147,424 @@ pub fn read(file_path: &str) -> Result<Vec<DailyScore>, JournalError> {
             read_error,
         })?;
 
-        let daily_score =
+        let mut daily_score =
             DailyScore::parse(line_string.as_str())
             .map_err(|daily_score_parse_error|
                      JournalError::DailyScoreParseError {
                          line: line_string.clone(),
                          daily_score_parse_error,
                      })?;
+        daily_score.tags = tag_rules.apply_all(&daily_score.tags);
 
         records.push(daily_score);
     }
     Ok(records)
 }
 
-pub fn write_xlsx(file_path: &str, daily_scores: &[DailyScore]) -> Result<(), JournalError> {
+pub fn read_if_exists(file_path: &str, tag_rules: &TagRules) -> Result<Vec<DailyScore>, JournalError> {
+    if Path::new(file_path).exists() {
+        read(file_path, tag_rules)
+    } else {
+        Ok(Vec::new())
+    }
+}
+
+// Writes the whole journal into a temporary file first and then moves it in place,
+// so the journal is never left half-written.
+pub fn write(file_path: &str, daily_scores: &[DailyScore]) -> Result<(), JournalError> {
+    let tmp_file_path = format!("{}.tmp", file_path);
+    let write_error = |write_error| JournalError::CannotWriteFile { file_path: file_path.to_string(), write_error };
+
+    if let Some(dir) = Path::new(file_path).parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir).map_err(write_error)?;
+    }
+    let written = write_tmp_file(&tmp_file_path, daily_scores)
+        .and_then(|_| fs::rename(&tmp_file_path, file_path));
+    if written.is_err() {
+        // the journal is left as it was, so the partially written copy is of no use
+        let _ = fs::remove_file(&tmp_file_path);
+    }
+
+    written.map_err(write_error)
+}
+
+fn write_tmp_file(tmp_file_path: &str, daily_scores: &[DailyScore]) -> io::Result<()> {
+    let mut file = File::create(tmp_file_path)?;
+    for daily_score in daily_scores.iter() {
+        writeln!(file, "{}", daily_score.to_s())?;
+    }
+    file.sync_all()
+}
+
+// Adds scores which are not in the journal yet, keeping records ordered by datetime.
+// Repeated imported records are added once.
+pub fn merge(daily_scores: &[DailyScore], imported_daily_scores: Vec<DailyScore>, record_match: RecordMatch) -> Vec<DailyScore> {
+    let mut merged_daily_scores = daily_scores.to_vec();
+
+    for imported_daily_score in imported_daily_scores {
+        if !merged_daily_scores.iter().any(|daily_score| record_match.matches(daily_score, &imported_daily_score)) {
+            merged_daily_scores.push(imported_daily_score);
+        }
+    }
+    merged_daily_scores.sort_by_key(|daily_score| daily_score.datetime);
+
+    merged_daily_scores
+}
+
+// Replaces journal with imported scores. Journal records matching imported ones are kept as is,
+// so less precise sources (i.e. without time) won't change existing records.
+pub fn replace(daily_scores: &[DailyScore], imported_daily_scores: Vec<DailyScore>, record_match: RecordMatch) -> Vec<DailyScore> {
+    let mut used = vec![false; daily_scores.len()];
+    let mut replaced_daily_scores = imported_daily_scores.into_iter()
+        .map(|imported_daily_score| {
+            let matching_index = daily_scores.iter().enumerate()
+                .position(|(i, daily_score)| !used[i] && record_match.matches(daily_score, &imported_daily_score));
+
+            match matching_index {
+                Some(i) => { used[i] = true; daily_scores[i].clone() },
+                None => imported_daily_score,
+            }
+        })
+        .collect::<Vec<DailyScore>>();
+    replaced_daily_scores.sort_by_key(|daily_score| daily_score.datetime);
+
+    replaced_daily_scores
+}
+
+// Returns records removed from and added to the journal.
+pub fn diff<'a>(daily_scores: &'a [DailyScore], new_daily_scores: &'a [DailyScore]) -> (Vec<&'a DailyScore>, Vec<&'a DailyScore>) {
+    let missing_in = |daily_scores: &'a [DailyScore], other_daily_scores: &'a [DailyScore]| {
+        daily_scores.iter()
+            .filter(|daily_score| !other_daily_scores.iter().any(|other| other.same_record(daily_score)))
+            .collect::<Vec<&DailyScore>>()
+    };
+
+    (missing_in(daily_scores, new_daily_scores), missing_in(new_daily_scores, daily_scores))
+}
+
+pub fn write_csv(file_path: &str, daily_scores: &[DailyScore]) -> Result<(), JournalError> {
+    let write_error = |write_error| JournalError::CannotWriteFile { file_path: file_path.to_string(), write_error };
+    let mut file = File::create(file_path).map_err(write_error)?;
+
+    writeln!(file, "{}", csv::format_record(&CSV_HEADER)).map_err(write_error)?;
+    for daily_score in daily_scores.iter() {
+        let datetime = daily_score.datetime.format(daily_score::DATE_FORMAT).to_string();
+        let score = daily_score.score.to_string();
+        let tags = daily_score.tags_string();
+        let comment = daily_score.comment.as_deref().unwrap_or("");
+        let metrics = metric::format_metrics(&daily_score.metrics);
+
+        writeln!(file, "{}", csv::format_record(&[&datetime, &score, &tags, comment, &metrics])).map_err(write_error)?;
+    }
+
+    Ok(())
+}
+
+pub fn read_csv(file_path: &str, csv_format: &CsvFormat) -> Result<Vec<DailyScore>, JournalError> {
+    let text = fs::read_to_string(file_path)
+        .map_err(|open_error| JournalError::CannotOpenFile { file_path: file_path.to_string(), open_error })?;
+
+    let mut records = csv::parse_records(&text)
+        .map_err(|_| JournalError::CsvUnterminatedQuote { file_path: file_path.to_string() })?
+        .into_iter();
+
+    // the first row is a header only if columns are detected by it
+    let columns = match &csv_format.columns {
+        Some(columns) => columns.clone(),
+        None => records.next().unwrap_or_default().iter()
+            .map(|column_name| CsvColumn::parse(column_name).ok_or_else(|| JournalError::CsvUnknownColumn(column_name.clone())))
+            .collect::<Result<Vec<CsvColumn>, JournalError>>()?,
+    };
+
+    let column_index = |column| columns.iter().position(|c| *c == column);
+    let date_index = column_index(CsvColumn::Date).ok_or(JournalError::CsvMissingColumn("date"))?;
+    let score_index = column_index(CsvColumn::Score).ok_or(JournalError::CsvMissingColumn("score"))?;
+    let tags_index = column_index(CsvColumn::Tags);
+    let comment_index = column_index(CsvColumn::Comment);
+    let metrics_index = column_index(CsvColumn::Metrics);
+
+    let mut daily_scores = Vec::new();
+    for record in records {
+        let field = |index: usize| record.get(index).map(|field| field.trim()).unwrap_or("");
+        let parse_error = |daily_score_parse_error| JournalError::DailyScoreParseError {
+            line: csv::format_record(&record.iter().map(String::as_str).collect::<Vec<&str>>()),
+            daily_score_parse_error,
+        };
+
+        let datetime_str = field(date_index);
+        let datetime = parse_datetime(datetime_str, csv_format.date_format.as_deref())
+            .ok_or_else(|| parse_error(daily_score::ParseError::InvalidDateTime(datetime_str.to_string())))?;
+
+        let score_str = field(score_index);
+        let score = metric::parse_value(score_str)
+            .ok_or_else(|| parse_error(daily_score::ParseError::InvalidScore(score_str.to_string())))?;
+
+        let (tags, attributes) = split_imported_tags(tags_index.map(field).unwrap_or(""))
+            .map_err(|tag| parse_error(daily_score::ParseError::InvalidTag(tag)))?;
+
+        let comment = comment_index
+            .map(field)
+            .filter(|comment| !comment.is_empty())
+            .map(|comment| comment.replace(&['\n', '\r'][..], " "));
+
+        let metrics = metric::parse_metrics(metrics_index.map(field).unwrap_or(""))
+            .map_err(|metric_str| parse_error(daily_score::ParseError::InvalidMetric(metric_str)))?;
+
+        daily_scores.push(DailyScore { score, metrics, tags, attributes, comment, datetime });
+    }
+
+    Ok(daily_scores)
+}
+
+// Imported tags are written to the journal as is, so a tag which would not be read back the same is returned as an error.
+// Items of repeated attribute keys are kept as tags, the way the journal reads them.
+fn split_imported_tags(tags_str: &str) -> Result<(HashSet<String>, BTreeMap<String, String>), String> {
+    let (tags, attributes) = attribute::split_tags(
+        tags_str.split(crate::TAGS_SEPARATOR).map(str::trim).filter(|tag| !tag.is_empty()).map(str::to_string)
+    );
+
+    match tags.iter().find(|tag| !tag::is_valid(tag) && attribute::parse_attribute(tag).is_none()) {
+        Some(invalid_tag) => Err(invalid_tag.clone()),
+        None => Ok((tags, attributes)),
+    }
+}
+
+pub fn write_json(file_path: &str, daily_scores: &[DailyScore]) -> Result<(), JournalError> {
+    let write_error = |write_error| JournalError::CannotWriteFile { file_path: file_path.to_string(), write_error };
+    let values = daily_scores.iter().map(json::to_value).collect::<Vec<serde_json::Value>>();
+    let mut file = File::create(file_path).map_err(write_error)?;
+
+    serde_json::to_writer_pretty(&mut file, &values).map_err(|json_error| write_error(json_error.into()))?;
+    writeln!(file).map_err(write_error)?;
+
+    Ok(())
+}
+
+pub fn write_ndjson(file_path: &str, daily_scores: &[DailyScore]) -> Result<(), JournalError> {
+    let write_error = |write_error| JournalError::CannotWriteFile { file_path: file_path.to_string(), write_error };
+    let mut file = File::create(file_path).map_err(write_error)?;
+
+    for daily_score in daily_scores.iter() {
+        writeln!(file, "{}", json::to_value(daily_score)).map_err(write_error)?;
+    }
+
+    Ok(())
+}
+
+pub fn write_ics(file_path: &str, daily_scores: &[DailyScore], journal_file_path: &str) -> Result<(), JournalError> {
+    fs::write(file_path, ics::format_calendar(daily_scores, &ics::journal_id(journal_file_path)))
+        .map_err(|write_error| JournalError::CannotWriteFile { file_path: file_path.to_string(), write_error })
+}
+
+pub fn read_json(file_path: &str) -> Result<Vec<DailyScore>, JournalError> {
+    let text = fs::read_to_string(file_path)
+        .map_err(|open_error| JournalError::CannotOpenFile { file_path: file_path.to_string(), open_error })?;
+    let value: serde_json::Value = serde_json::from_str(&text)
+        .map_err(|json_error| JournalError::JsonParseError { file_path: file_path.to_string(), json_error })?;
+
+    match value {
+        serde_json::Value::Array(values) => values.iter().map(daily_score_from_json).collect(),
+        value => daily_score_from_json(&value).map(|daily_score| vec![daily_score]),
+    }
+}
+
+pub fn read_ndjson(file_path: &str) -> Result<Vec<DailyScore>, JournalError> {
+    let text = fs::read_to_string(file_path)
+        .map_err(|open_error| JournalError::CannotOpenFile { file_path: file_path.to_string(), open_error })?;
+
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let value = serde_json::from_str(line)
+                .map_err(|json_error| JournalError::JsonParseError { file_path: file_path.to_string(), json_error })?;
+            daily_score_from_json(&value)
+        })
+        .collect()
+}
+
+fn daily_score_from_json(value: &serde_json::Value) -> Result<DailyScore, JournalError> {
+    if !value.is_object() {
+        return Err(JournalError::JsonNotRecord(value.to_string()));
+    }
+
+    json::from_value(value)
+        .map_err(|json::InvalidFieldError(field)| JournalError::JsonInvalidRecord { record: value.to_string(), field })
+}
+
+// Tries to parse datetime with or without time and offset; missing parts are taken
+// from the beginning of the day and local timezone.
+fn parse_datetime(datetime_str: &str, date_format: Option<&str>) -> Option<DateTime<FixedOffset>> {
+    let date_formats = match date_format {
+        Some(date_format) => vec![date_format],
+        None => vec![daily_score::DATE_FORMAT, CSV_FALLBACK_DATE_FORMAT],
+    };
+
+    date_formats.into_iter().find_map(|date_format| {
+        if let Ok(datetime) = DateTime::parse_from_str(datetime_str, date_format) {
+            return Some(datetime);
+        }
+
+        let naive_datetime = NaiveDateTime::parse_from_str(datetime_str, date_format)
+            .or_else(|_| NaiveDate::parse_from_str(datetime_str, date_format).map(|date| date.and_hms(0, 0, 0)))
+            .ok()?;
+        Local.from_local_datetime(&naive_datetime).earliest()
+            .map(|local_datetime| local_datetime.with_timezone(local_datetime.offset()))
+    })
+}
+
+pub fn read_xlsx(file_path: &str) -> Result<Vec<DailyScore>, JournalError> {
+    let read_error = |xlsx_error| JournalError::XlsxReadError { file_path: file_path.to_string(), xlsx_error };
+    let mut workbook: Xlsx<_> = open_workbook(file_path).map_err(read_error)?;
+    let range = workbook.worksheet_range(XLSX_SHEET_NAME).map_err(read_error)?;
+    let mut rows = range.rows();
+
+    let header = rows.next().unwrap_or_default();
+    let header_matches = header.len() >= XLSX_REQUIRED_COLUMNS &&
+        header.iter().zip(XLSX_HEADER.iter()).all(|(cell, name)| cell.get_string() == Some(name));
+    if !header_matches {
+        return Err(JournalError::XlsxInvalidHeader);
+    }
+
+    let mut daily_scores = Vec::new();
+    // spreadsheet rows are numbered from 1, and first row is a header
+    for (row, cells) in (2..).zip(rows) {
+        let cell = |index: usize| cells.get(index).unwrap_or(&Data::Empty);
+        if cells.iter().all(DataType::is_empty) { continue }
+
+        let datetime = xlsx_datetime(cell(0)).ok_or(JournalError::XlsxInvalidRow { row, field: "date" })?;
+
+        let score = match cell(1) {
+            Data::Int(score) => Some(*score as f64),
+            Data::Float(score) if score.is_finite() => Some(*score),
+            Data::String(score) => metric::parse_value(score),
+            _ => None,
+        }.ok_or(JournalError::XlsxInvalidRow { row, field: "score" })?;
+
+        let (tags, attributes) = split_imported_tags(&cell(2).as_string().unwrap_or_default())
+            .map_err(|_| JournalError::XlsxInvalidRow { row, field: "tags" })?;
+
+        let comment = cell(3).as_string()
+            .map(|comment| comment.trim().replace(&['\n', '\r'][..], " "))
+            .filter(|comment| !comment.is_empty());
+
+        let metrics = metric::parse_metrics(&cell(4).as_string().unwrap_or_default())
+            .map_err(|_| JournalError::XlsxInvalidRow { row, field: "metrics" })?;
+
+        daily_scores.push(DailyScore { score, metrics, tags, attributes, comment, datetime });
+    }
+
+    Ok(daily_scores)
+}
+
+// Date cells are stored as a number of days since 1899-12-30, fractional part is a time of the day.
+fn xlsx_datetime(cell: &Data) -> Option<DateTime<FixedOffset>> {
+    let serial = match cell {
+        Data::DateTime(excel_datetime) => excel_datetime.as_f64(),
+        Data::Float(serial) => *serial,
+        Data::Int(serial) => *serial as f64,
+        Data::String(datetime_str) => return parse_datetime(datetime_str.trim(), None),
+        _ => return None,
+    };
+
+    let seconds = (serial.fract() * DAY_SECONDS as f64).round() as i64;
+    let naive_datetime = NaiveDate::from_ymd(1899, 12, 30).and_hms(0, 0, 0) +
+        Duration::days(serial.trunc() as i64) + Duration::seconds(seconds);
+
+    Local.from_local_datetime(&naive_datetime).earliest()
+        .map(|local_datetime| local_datetime.with_timezone(local_datetime.offset()))
+}
+
+pub fn write_xlsx(file_path: &str, daily_scores: &[DailyScore], summary: Option<&MoodReport>) -> Result<(), JournalError> {
     let mut wb = Workbook::create(file_path);
-    let mut sheet = wb.create_sheet("Daily Scores");
+    let mut sheet = wb.create_sheet(XLSX_SHEET_NAME);
     sheet.add_column(Column { width: 10.0 });
     sheet.add_column(Column { width: 5.0 });
     sheet.add_column(Column { width: 40.0 });
     sheet.add_column(Column { width: 50.0 });
+    sheet.add_column(Column { width: 30.0 });
 
     wb.write_sheet(&mut sheet, |sheet_writer| {
-        sheet_writer.append_row(row!["Date", "Score", "Tags", "Comment"])?;
+        sheet_writer.append_row(row![XLSX_HEADER[0], XLSX_HEADER[1], XLSX_HEADER[2], XLSX_HEADER[3], XLSX_HEADER[4]])?;
 
         for daily_score in daily_scores.iter() {
             sheet_writer
                 .append_row(row![
                             daily_score.datetime.date().naive_local(),
-                            daily_score.score as f64,
+                            daily_score.score,
                             daily_score.tags_string(),
-                            daily_score.comment.as_deref().unwrap_or("")
+                            daily_score.comment.as_deref().unwrap_or(""),
+                            metric::format_metrics(&daily_score.metrics)
                 ])?
         };
         Ok(())
     }).map_err(JournalError::XlsxWriteError)?;
 
+    if let Some(mood_report) = summary {
+        write_xlsx_summary(&mut wb, mood_report).map_err(JournalError::XlsxWriteError)?;
+    }
+
+    wb.close().map_err(JournalError::XlsxWriteError)?;
+
+    Ok(())
+}
+
+fn write_xlsx_summary(wb: &mut Workbook, mood_report: &MoodReport) -> io::Result<()> {
+    let mut monthly_sheet = wb.create_sheet("Monthly");
+    monthly_sheet.add_column(Column { width: 10.0 });
+    monthly_sheet.add_column(Column { width: 8.0 });
+    monthly_sheet.add_column(Column { width: 8.0 });
+    monthly_sheet.add_column(Column { width: 8.0 });
+    wb.write_sheet(&mut monthly_sheet, |sheet_writer| {
+        sheet_writer.append_row(row!["Month", "Total", "Average", "Records"])?;
+        for (beginning_of_month, stats) in mood_report.monthly_stats() {
+            sheet_writer.append_row(row![beginning_of_month.format("%Y-%m").to_string(), stats.sum, stats.mean(), stats.count as f64])?;
+        }
+        Ok(())
+    })?;
+
+    let mut weekdays_sheet = wb.create_sheet("Weekdays");
+    weekdays_sheet.add_column(Column { width: 10.0 });
+    weekdays_sheet.add_column(Column { width: 8.0 });
+    weekdays_sheet.add_column(Column { width: 8.0 });
+    weekdays_sheet.add_column(Column { width: 8.0 });
+    wb.write_sheet(&mut weekdays_sheet, |sheet_writer| {
+        sheet_writer.append_row(row!["Weekday", "Total", "Average", "Records"])?;
+        for (weekday, stats) in mood_report.weekday_stats() {
+            sheet_writer.append_row(row![weekday.to_string(), stats.sum, stats.mean(), stats.count as f64])?;
+        }
+        Ok(())
+    })?;
+
+    let mut tags_sheet = wb.create_sheet("Tags");
+    tags_sheet.add_column(Column { width: 20.0 });
+    tags_sheet.add_column(Column { width: 8.0 });
+    tags_sheet.add_column(Column { width: 8.0 });
+    tags_sheet.add_column(Column { width: 8.0 });
+    wb.write_sheet(&mut tags_sheet, |sheet_writer| {
+        sheet_writer.append_row(row!["Tag", "Records", "Total", "Average"])?;
+        for (tag, stats) in mood_report.tag_stats() {
+            sheet_writer.append_row(row![tag, stats.count as f64, stats.sum, stats.mean()])?;
+        }
+        Ok(())
+    })?;
+
+    let mut streaks_sheet = wb.create_sheet("Streaks");
+    streaks_sheet.add_column(Column { width: 10.0 });
+    streaks_sheet.add_column(Column { width: 10.0 });
+    streaks_sheet.add_column(Column { width: 10.0 });
+    streaks_sheet.add_column(Column { width: 8.0 });
+    wb.write_sheet(&mut streaks_sheet, |sheet_writer| {
+        sheet_writer.append_row(row!["Streak", "Start", "End", "Days"])?;
+        for streak in mood_report.streaks() {
+            let kind = match streak.kind {
+                StreakKind::Positive => "positive",
+                StreakKind::Negative => "negative",
+            };
+            sheet_writer.append_row(row![kind, streak.start, streak.end, streak.days() as f64])?;
+        }
+        Ok(())
+    })?;
+
     Ok(())
 }
 
@@ -99,12 +572,13 @@ pub fn write_xlsx(file_path: &str, daily_scores: &[DailyScore]) -> Result<(), Jo
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::metric::Metric;
 
     #[test]
     fn errors_display() {
         let file_path = String::from("path/to/file");
-        let io_error = io::Error::new(io::ErrorKind::Other, "error text");
-        let another_io_error = io::Error::new(io::ErrorKind::Other, "error text");
+        let io_error = io::Error::other("error text");
+        let another_io_error = io::Error::other("error text");
         let line = String::from("foo bar baz");
         let daily_score_parse_error = daily_score::ParseError::MissingDateTime;
 
@@ -114,5 +588,167 @@ mod tests {
             "cannot read line from journal file 'path/to/file'");
         assert_eq!(JournalError::DailyScoreParseError { line, daily_score_parse_error }.to_string(),
             "cannot parse daily score data 'foo bar baz'");
+        assert_eq!(JournalError::CsvUnterminatedQuote { file_path: "path/to/file".to_string() }.to_string(),
+            "csv file 'path/to/file' has unterminated quoted field");
+        assert_eq!(JournalError::CsvUnknownColumn("foo".to_string()).to_string(), "csv column 'foo' is not recognized");
+        assert_eq!(JournalError::CsvMissingColumn("date").to_string(), "csv file has no 'date' column");
+        assert_eq!(JournalError::JsonInvalidRecord { record: "[]".to_string(), field: "score" }.to_string(),
+            "json record '[]' has invalid 'score' field");
+        assert_eq!(JournalError::JsonNotRecord("1".to_string()).to_string(), "json value '1' is neither record nor array of records");
+        assert_eq!(JournalError::XlsxInvalidHeader.to_string(), "xlsx sheet header is not 'Date, Score, Tags, Comment'");
+        assert_eq!(JournalError::XlsxInvalidRow { row: 3, field: "score" }.to_string(), "xlsx row 3 has invalid 'score' field");
+    }
+
+    #[test]
+    fn datetime_parsing() {
+        let datetime = FixedOffset::east(4 * 3600).ymd(2020, 1, 1).and_hms(9, 10, 11);
+        let midnight = Local.ymd(2020, 1, 2).and_hms(0, 0, 0);
+
+        assert_eq!(parse_datetime("2020-01-01 09:10:11 +0400", None), Some(datetime));
+        assert_eq!(parse_datetime("2020-01-02", None), Some(midnight.with_timezone(midnight.offset())));
+        assert_eq!(parse_datetime("02.01.2020", Some("%d.%m.%Y")), Some(midnight.with_timezone(midnight.offset())));
+        assert_eq!(parse_datetime("2020-01-02", Some("%d.%m.%Y")), None);
+    }
+
+    #[test]
+    fn merging() {
+        let daily_scores = vec![DailyScore::with_score(1)];
+        let mut existing_copy = DailyScore::with_score(1);
+        existing_copy.datetime = daily_scores[0].datetime;
+        existing_copy.comment = Some("".to_string());
+        let mut older = DailyScore::with_score(2);
+        older.datetime = daily_scores[0].datetime - Duration::days(1);
+
+        let merged_daily_scores = merge(&daily_scores, vec![existing_copy, older.clone(), older], RecordMatch::Exact);
+        assert_eq!(merged_daily_scores.iter().map(|daily_score| daily_score.score).collect::<Vec<f64>>(), vec![2.0, 1.0]);
+    }
+
+    #[test]
+    fn replacing() {
+        let mut existing = DailyScore::with_score(1);
+        existing.datetime = FixedOffset::east(0).ymd(2020, 1, 1).and_hms(9, 10, 11);
+        let mut removed = DailyScore::with_score(3);
+        removed.datetime = FixedOffset::east(0).ymd(2020, 1, 2).and_hms(9, 10, 11);
+        let daily_scores = vec![existing, removed];
+
+        let mut imported_existing = DailyScore::with_score(1);
+        imported_existing.datetime = FixedOffset::east(0).ymd(2020, 1, 1).and_hms(0, 0, 0);
+        let mut imported_new = DailyScore::with_score(2);
+        imported_new.datetime = FixedOffset::east(0).ymd(2020, 1, 3).and_hms(0, 0, 0);
+
+        let replaced_daily_scores = replace(&daily_scores, vec![imported_new, imported_existing], RecordMatch::SameDay);
+        assert_eq!(replaced_daily_scores.iter().map(|daily_score| daily_score.score).collect::<Vec<f64>>(), vec![1.0, 2.0]);
+        assert_eq!(replaced_daily_scores[0].datetime, daily_scores[0].datetime);
+
+        let (removed_daily_scores, added_daily_scores) = diff(&daily_scores, &replaced_daily_scores);
+        assert_eq!(removed_daily_scores.iter().map(|daily_score| daily_score.score).collect::<Vec<f64>>(), vec![3.0]);
+        assert_eq!(added_daily_scores.iter().map(|daily_score| daily_score.score).collect::<Vec<f64>>(), vec![2.0]);
+    }
+
+    #[test]
+    fn xlsx_summary_sheets() {
+        let xlsx_file = assert_fs::NamedTempFile::new("summary.xlsx").unwrap();
+        let file_path = xlsx_file.path().to_str().unwrap();
+        let mut daily_score = DailyScore::with_score(2);
+        daily_score.tags.insert("run".to_string());
+        daily_score.datetime = FixedOffset::east(0).ymd(2020, 1, 6).and_hms(9, 10, 11);
+        let daily_scores = vec![daily_score];
+        let filter = crate::filter::Filter::default();
+        let mood_report = MoodReport { daily_scores: &daily_scores, filter: &filter, metric: &Metric::Score, timezone: None };
+
+        write_xlsx(file_path, &daily_scores, Some(&mood_report)).unwrap();
+
+        let mut workbook: Xlsx<_> = open_workbook(file_path).unwrap();
+        assert_eq!(workbook.sheet_names(), vec!["Daily Scores", "Monthly", "Weekdays", "Tags", "Streaks"]);
+
+        let monthly_range = workbook.worksheet_range("Monthly").unwrap();
+        assert_eq!(monthly_range.get((1, 0)), Some(&Data::String("2020-01".to_string())));
+        assert_eq!(monthly_range.get((1, 1)).and_then(DataType::as_f64), Some(2.0));
+
+        let weekdays_range = workbook.worksheet_range("Weekdays").unwrap();
+        assert_eq!(weekdays_range.get((1, 0)), Some(&Data::String("Mon".to_string())));
+        assert_eq!(weekdays_range.get((1, 3)).and_then(DataType::as_f64), Some(1.0));
+
+        let tags_range = workbook.worksheet_range("Tags").unwrap();
+        assert_eq!(tags_range.get((1, 0)), Some(&Data::String("run".to_string())));
+
+        let streaks_range = workbook.worksheet_range("Streaks").unwrap();
+        assert_eq!(streaks_range.get((1, 0)), Some(&Data::String("positive".to_string())));
+        assert_eq!(streaks_range.get((1, 3)).and_then(DataType::as_f64), Some(1.0));
+
+        assert_eq!(read_xlsx(file_path).unwrap().len(), 1);
+
+        let mut invalid_daily_score = DailyScore::with_score(1);
+        invalid_daily_score.tags.insert("a|b".to_string());
+        write_xlsx(file_path, &[invalid_daily_score], None).unwrap();
+        assert_eq!(read_xlsx(file_path).err().unwrap().to_string(), "xlsx row 2 has invalid 'tags' field");
+    }
+
+    #[test]
+    fn csv_header() {
+        let csv_file = assert_fs::NamedTempFile::new("import.csv").unwrap();
+        fs::write(csv_file.path(), "2020-01-01,1,run\n2020-01-02,-1,\n").unwrap();
+        let file_path = csv_file.path().to_str().unwrap();
+
+        let columns = Some(vec![CsvColumn::Date, CsvColumn::Score, CsvColumn::Tags]);
+        let daily_scores = read_csv(file_path, &CsvFormat { columns, date_format: None }).unwrap();
+        assert_eq!(daily_scores.iter().map(|daily_score| daily_score.score).collect::<Vec<f64>>(), vec![1.0, -1.0]);
+
+        fs::write(csv_file.path(), "Date,Score\n2020-01-01,1\n").unwrap();
+        assert_eq!(read_csv(file_path, &CsvFormat::default()).unwrap().len(), 1);
+
+        fs::write(csv_file.path(), "date,score,tags,comment\n2020-01-01,1,\"run,weather=rain,weather=sun\",\n").unwrap();
+        assert!(read_csv(file_path, &CsvFormat::default()).unwrap()[0].tags.contains("weather=rain"));
+
+        fs::write(csv_file.path(), "date,score,tags,comment\n2020-01-01,1,a|b,hello\n").unwrap();
+        assert_eq!(read_csv(file_path, &CsvFormat::default()).err().unwrap().to_string(),
+            "cannot parse daily score data '2020-01-01,1,a|b,hello'");
+        match read_csv(file_path, &CsvFormat::default()) {
+            Err(JournalError::DailyScoreParseError { daily_score_parse_error, .. }) =>
+                assert_eq!(daily_score_parse_error, daily_score::ParseError::InvalidTag("a|b".to_string())),
+            _ => panic!("tag 'a|b' is imported"),
+        }
+    }
+
+    #[test]
+    fn json_top_level_values() {
+        let json_file = assert_fs::NamedTempFile::new("import.json").unwrap();
+        let file_path = json_file.path().to_str().unwrap();
+
+        fs::write(json_file.path(), r#"{"datetime": "2020-01-01T09:10:11Z", "score": 1}"#).unwrap();
+        assert_eq!(read_json(file_path).unwrap().len(), 1);
+
+        fs::write(json_file.path(), "1").unwrap();
+        assert_eq!(read_json(file_path).err().unwrap().to_string(), "json value '1' is neither record nor array of records");
+
+        fs::write(json_file.path(), "[1]").unwrap();
+        assert_eq!(read_json(file_path).err().unwrap().to_string(), "json value '1' is neither record nor array of records");
+    }
+
+    #[test]
+    fn failed_write_cleanup() {
+        let journal_dir = assert_fs::TempDir::new().unwrap();
+        // a directory can not be replaced by the written file
+        let file_path = journal_dir.path().join("howdy.journal");
+        fs::create_dir(&file_path).unwrap();
+        fs::create_dir(file_path.join("entry")).unwrap();
+        let file_path = file_path.to_str().unwrap();
+
+        assert!(write(file_path, &[DailyScore::with_score(1)]).is_err());
+        assert!(!Path::new(&format!("{}.tmp", file_path)).exists());
+    }
+
+    #[test]
+    fn xlsx_datetime_parsing() {
+        let local_datetime = |datetime: NaiveDateTime| {
+            let local_datetime = Local.from_local_datetime(&datetime).unwrap();
+            local_datetime.with_timezone(local_datetime.offset())
+        };
+
+        assert_eq!(xlsx_datetime(&Data::Float(43831.0)), Some(local_datetime(NaiveDate::from_ymd(2020, 1, 1).and_hms(0, 0, 0))));
+        assert_eq!(xlsx_datetime(&Data::Float(43831.5)), Some(local_datetime(NaiveDate::from_ymd(2020, 1, 1).and_hms(12, 0, 0))));
+        assert_eq!(xlsx_datetime(&Data::String("2020-01-01".to_string())),
+            Some(local_datetime(NaiveDate::from_ymd(2020, 1, 1).and_hms(0, 0, 0))));
+        assert_eq!(xlsx_datetime(&Data::Bool(true)), None);
     }
 }
@@ -0,0 +1,104 @@
+const FIELD_SEPARATOR: char = ',';
+const QUOTE: char = '"';
+
+#[derive(Debug, PartialEq)]
+pub struct UnterminatedQuoteError;
+
+pub fn format_record(fields: &[&str]) -> String {
+    fields.iter()
+        .map(|field| quote_field(field))
+        .collect::<Vec<String>>()
+        .join(&FIELD_SEPARATOR.to_string())
+}
+
+fn quote_field(field: &str) -> String {
+    let needs_quotes = field.contains(&[FIELD_SEPARATOR, QUOTE, '\n', '\r'][..]) ||
+        field.starts_with(' ') || field.ends_with(' ');
+
+    if needs_quotes {
+        format!("{}{}{}", QUOTE, field.replace(QUOTE, "\"\""), QUOTE)
+    } else {
+        field.to_string()
+    }
+}
+
+// Splits csv text into records, each record into fields. Quoted fields may contain separators,
+// doubled quotes and line breaks, so we can't simply split input by lines.
+pub fn parse_records(text: &str) -> Result<Vec<Vec<String>>, UnterminatedQuoteError> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                QUOTE if chars.peek() == Some(&QUOTE) => { chars.next(); field.push(QUOTE) },
+                QUOTE => in_quotes = false,
+                _ => field.push(c),
+            }
+            continue;
+        }
+
+        match c {
+            QUOTE => in_quotes = true,
+            FIELD_SEPARATOR => record.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => {},
+            '\n' => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            },
+            _ => field.push(c),
+        }
+    }
+
+    if in_quotes {
+        return Err(UnterminatedQuoteError);
+    }
+
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+
+    Ok(records.into_iter().filter(|record| !(record.len() == 1 && record[0].is_empty())).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_formatting() {
+        assert_eq!(format_record(&["a", "b c", ""]), "a,b c,");
+        assert_eq!(format_record(&["games,run", "say \"hi\"", " padded"]),
+            "\"games,run\",\"say \"\"hi\"\"\",\" padded\"");
+        assert_eq!(format_record(&["multi\nline"]), "\"multi\nline\"");
+    }
+
+    #[test]
+    fn records_parsing() {
+        let text = "Date,Score\r\n\"2020-01-01\",\"1\"\n2020-01-02,\"a,b\",\"say \"\"hi\"\"\"\n\n\"multi\nline\",2";
+
+        assert_eq!(parse_records(text).unwrap(), vec![
+            vec!["Date".to_string(), "Score".to_string()],
+            vec!["2020-01-01".to_string(), "1".to_string()],
+            vec!["2020-01-02".to_string(), "a,b".to_string(), "say \"hi\"".to_string()],
+            vec!["multi\nline".to_string(), "2".to_string()],
+        ]);
+    }
+
+    #[test]
+    fn records_round_trip() {
+        let fields = ["2020-01-01 09:10:11 +0400", "1", "games,run", "foo | \"bar\", baz"];
+        let records = parse_records(&format_record(&fields)).unwrap();
+
+        assert_eq!(records, vec![fields.iter().map(|f| f.to_string()).collect::<Vec<String>>()]);
+    }
+
+    #[test]
+    fn unterminated_quote_parsing() {
+        assert_eq!(parse_records("a,\"b\nc").err(), Some(UnterminatedQuoteError));
+    }
+}
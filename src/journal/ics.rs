@@ -0,0 +1,161 @@
+use chrono::{NaiveDate, Utc};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+
+use crate::daily_score::DailyScore;
+use crate::metric::ReportValue;
+
+const LINE_BREAK: &str = "\r\n";
+const MAX_LINE_OCTETS: usize = 75;
+const DATE_FORMAT: &str = "%Y%m%d";
+const DATETIME_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+const UID_DOMAIN: &str = "howdy.journal";
+const JOURNAL_ID_LENGTH: usize = 16;
+
+// Identifies events of the journal among the ones of other journals in the same calendar,
+// it is kept across exports as long as the journal stays where it is.
+pub fn journal_id(journal_file_path: &str) -> String {
+    let journal_file_path = fs::canonicalize(journal_file_path)
+        .map(|path| path.to_string_lossy().into_owned())
+        .unwrap_or_else(|_| journal_file_path.to_string());
+    let digest = Sha256::digest(journal_file_path.as_bytes());
+
+    digest.iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<String>()[..JOURNAL_ID_LENGTH]
+        .to_string()
+}
+
+// All scores made in one day are collapsed into a single all-day event.
+pub fn format_calendar(daily_scores: &[DailyScore], journal_id: &str) -> String {
+    let mut days: BTreeMap<NaiveDate, Vec<&DailyScore>> = BTreeMap::new();
+    for daily_score in daily_scores.iter() {
+        days.entry(daily_score.datetime.naive_local().date()).or_default().push(daily_score);
+    }
+
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//howdy//mood journal//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+    ];
+
+    for (date, day_scores) in days {
+        let score = ReportValue(day_scores.iter().map(|daily_score| daily_score.score).sum());
+        let tags = day_scores.iter()
+            .flat_map(|daily_score| daily_score.tags.iter().map(String::as_str))
+            .collect::<BTreeSet<&str>>();
+        let comments = day_scores.iter()
+            .filter_map(|daily_score| daily_score.comment.as_deref())
+            .filter(|comment| !comment.is_empty())
+            .collect::<Vec<&str>>();
+        // timestamp of the latest record makes output stable across exports
+        let updated_at = day_scores.iter().map(|daily_score| daily_score.datetime).max().unwrap();
+
+        let summary = if tags.is_empty() {
+            format!("Mood: {}", score)
+        } else {
+            format!("Mood: {} ({})", score, tags.into_iter().collect::<Vec<&str>>().join(", "))
+        };
+
+        lines.push("BEGIN:VEVENT".to_string());
+        lines.push(format!("UID:{}-{}@{}", date.format(DATE_FORMAT), journal_id, UID_DOMAIN));
+        lines.push(format!("DTSTAMP:{}", updated_at.with_timezone(&Utc).format(DATETIME_FORMAT)));
+        lines.push(format!("DTSTART;VALUE=DATE:{}", date.format(DATE_FORMAT)));
+        lines.push(format!("DTEND;VALUE=DATE:{}", date.succ().format(DATE_FORMAT)));
+        lines.push(format!("SUMMARY:{}", escape_text(&summary)));
+        if !comments.is_empty() {
+            lines.push(format!("DESCRIPTION:{}", escape_text(&comments.join("\n"))));
+        }
+        lines.push("END:VEVENT".to_string());
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    lines.iter().map(|line| fold_line(line) + LINE_BREAK).collect()
+}
+
+fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+// Lines longer than 75 octets are split, continuation lines start with a space.
+fn fold_line(line: &str) -> String {
+    let mut folded = String::new();
+    let mut line_octets = 0;
+
+    for c in line.chars() {
+        if line_octets + c.len_utf8() > MAX_LINE_OCTETS {
+            folded.push_str(LINE_BREAK);
+            folded.push(' ');
+            line_octets = 1;
+        }
+        folded.push(c);
+        line_octets += c.len_utf8();
+    }
+
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{FixedOffset, TimeZone};
+
+    use super::*;
+
+    #[test]
+    fn calendar_formatting() {
+        let daily_score_at = |score, tags: &[&str], comment: Option<&str>, hour| DailyScore {
+            score,
+            metrics: Default::default(),
+            attributes: Default::default(),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            comment: comment.map(str::to_string),
+            datetime: FixedOffset::east(4 * 3600).ymd(2020, 1, 1).and_hms(hour, 0, 0),
+        };
+        let daily_scores = vec![
+            daily_score_at(1.0, &["run"], Some("morning; fine"), 9),
+            daily_score_at(-2.0, &["work", "run"], None, 21),
+        ];
+
+        assert_eq!(format_calendar(&daily_scores, "0123456789abcdef"),
+            "BEGIN:VCALENDAR\r\n\
+            VERSION:2.0\r\n\
+            PRODID:-//howdy//mood journal//EN\r\n\
+            CALSCALE:GREGORIAN\r\n\
+            BEGIN:VEVENT\r\n\
+            UID:20200101-0123456789abcdef@howdy.journal\r\n\
+            DTSTAMP:20200101T170000Z\r\n\
+            DTSTART;VALUE=DATE:20200101\r\n\
+            DTEND;VALUE=DATE:20200102\r\n\
+            SUMMARY:Mood: -1 (run\\, work)\r\n\
+            DESCRIPTION:morning\\; fine\r\n\
+            END:VEVENT\r\n\
+            END:VCALENDAR\r\n");
+    }
+
+    #[test]
+    fn journal_ids() {
+        assert_eq!(journal_id("path/to/journal").len(), JOURNAL_ID_LENGTH);
+        assert_eq!(journal_id("path/to/journal"), journal_id("path/to/journal"));
+        assert_ne!(journal_id("path/to/journal"), journal_id("path/to/another_journal"));
+    }
+
+    #[test]
+    fn text_escaping() {
+        assert_eq!(escape_text("a,b;c\\d\ne"), "a\\,b\\;c\\\\d\\ne");
+    }
+
+    #[test]
+    fn line_folding() {
+        let line = "x".repeat(80);
+        assert_eq!(fold_line(&line), format!("{}\r\n {}", "x".repeat(75), "x".repeat(5)));
+        assert_eq!(fold_line("short"), "short");
+
+        let multibyte_line = "ж".repeat(40);
+        assert!(fold_line(&multibyte_line).split(LINE_BREAK).all(|line| line.len() <= MAX_LINE_OCTETS));
+    }
+}
@@ -0,0 +1,149 @@
+use chrono::DateTime;
+use serde_json::{json, Map, Value};
+
+use crate::daily_score::DailyScore;
+use crate::attribute;
+use crate::metric;
+use crate::tag;
+
+#[derive(Debug, PartialEq)]
+pub struct InvalidFieldError(pub &'static str);
+
+pub fn to_value(daily_score: &DailyScore) -> Value {
+    let mut tags = daily_score.tags.iter().collect::<Vec<&String>>();
+    tags.sort_unstable();
+
+    json!({
+        "datetime": daily_score.datetime.to_rfc3339(),
+        "score": number(daily_score.score),
+        "metrics": daily_score.metrics,
+        "tags": tags,
+        "attributes": daily_score.attributes,
+        "comment": daily_score.comment.as_deref().filter(|comment| !comment.is_empty()),
+    })
+}
+
+// Whole numbers are kept integers, so `1` is not written as `1.0`.
+pub fn number(value: f64) -> Value {
+    if value.fract() == 0.0 && value.abs() < i64::MAX as f64 { json!(value as i64) } else { json!(value) }
+}
+
+pub fn from_value(value: &Value) -> Result<DailyScore, InvalidFieldError> {
+    let empty_object = Map::new();
+    let object = value.as_object().unwrap_or(&empty_object);
+
+    let datetime = object.get("datetime")
+        .and_then(Value::as_str)
+        .and_then(|datetime_str| DateTime::parse_from_rfc3339(datetime_str).ok())
+        .ok_or(InvalidFieldError("datetime"))?;
+
+    let score = object.get("score")
+        .and_then(Value::as_f64)
+        .filter(|score| score.is_finite())
+        .ok_or(InvalidFieldError("score"))?;
+
+    let tags = match object.get("tags") {
+        None | Some(Value::Null) => Default::default(),
+        Some(Value::Array(tags)) => tags.iter()
+            .map(|tag| tag.as_str().filter(|tag| tag::is_valid(tag)).map(str::to_string))
+            .collect::<Option<_>>()
+            .ok_or(InvalidFieldError("tags"))?,
+        Some(_) => return Err(InvalidFieldError("tags")),
+    };
+
+    let comment = match object.get("comment") {
+        None | Some(Value::Null) => None,
+        Some(Value::String(comment)) => Some(comment.replace(&['\n', '\r'][..], " ")),
+        Some(_) => return Err(InvalidFieldError("comment")),
+    };
+
+    let metrics = match object.get("metrics") {
+        None | Some(Value::Null) => Default::default(),
+        Some(Value::Object(metrics)) => metrics.iter()
+            .map(|(name, value)| value.as_f64().filter(|_| metric::is_name_valid(name)).map(|value| (name.clone(), value)))
+            .collect::<Option<_>>()
+            .ok_or(InvalidFieldError("metrics"))?,
+        Some(_) => return Err(InvalidFieldError("metrics")),
+    };
+
+    let attributes = match object.get("attributes") {
+        None | Some(Value::Null) => Default::default(),
+        Some(Value::Object(attributes)) => attributes.iter()
+            .map(|(key, value)| value.as_str()
+                 .and_then(|value| attribute::parse_attribute(&attribute::format_attribute(key, value))))
+            .collect::<Option<_>>()
+            .ok_or(InvalidFieldError("attributes"))?,
+        Some(_) => return Err(InvalidFieldError("attributes")),
+    };
+
+    Ok(DailyScore { score, metrics, tags, attributes, comment, datetime })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::prelude::{FixedOffset, TimeZone};
+
+    use super::*;
+
+    #[test]
+    fn value_round_trip() {
+        let daily_score = DailyScore {
+            score: -1.0,
+            metrics: vec![("sleep".to_string(), 7.5)].into_iter().collect(),
+            comment: Some("foo | bar, \"baz\"".to_string()),
+            tags: vec!["run".to_string(), "games".to_string()].into_iter().collect(),
+            attributes: vec![("weather".to_string(), "rain".to_string())].into_iter().collect(),
+            datetime: FixedOffset::east(4 * 3600).ymd(2020, 1, 1).and_hms(9, 10, 11),
+        };
+        let value = to_value(&daily_score);
+
+        assert_eq!(value.to_string(),
+            r#"{"attributes":{"weather":"rain"},"comment":"foo | bar, \"baz\"","datetime":"2020-01-01T09:10:11+04:00","metrics":{"sleep":7.5},"score":-1,"tags":["games","run"]}"#);
+        assert!(from_value(&value).unwrap().same_record(&daily_score));
+    }
+
+    #[test]
+    fn fractional_scores() {
+        let daily_score = from_value(&json!({ "datetime": "2020-01-01T09:10:11Z", "score": 7.5 })).unwrap();
+
+        assert_eq!(daily_score.score, 7.5);
+        assert_eq!(to_value(&daily_score)["score"].to_string(), "7.5");
+        assert_eq!(number(300.0).to_string(), "300");
+    }
+
+    #[test]
+    fn optional_fields() {
+        let daily_score = from_value(&json!({ "datetime": "2020-01-01T09:10:11Z", "score": 1 })).unwrap();
+
+        assert_eq!(daily_score.tags.len(), 0);
+        assert_eq!(daily_score.comment, None);
+        assert!(daily_score.metrics.is_empty());
+        assert_eq!(to_value(&daily_score)["comment"], Value::Null);
+    }
+
+    #[test]
+    fn invalid_fields() {
+        assert_eq!(from_value(&json!([])).err(), Some(InvalidFieldError("datetime")));
+        assert_eq!(from_value(&json!({ "datetime": "2020-01-01", "score": 1 })).err(), Some(InvalidFieldError("datetime")));
+        assert_eq!(from_value(&json!({ "datetime": "2020-01-01T09:10:11Z", "score": "1" })).err(),
+            Some(InvalidFieldError("score")));
+        assert_eq!(from_value(&json!({ "datetime": "2020-01-01T09:10:11Z", "score": 1, "tags": "run" })).err(),
+            Some(InvalidFieldError("tags")));
+        assert_eq!(from_value(&json!({ "datetime": "2020-01-01T09:10:11Z", "score": 1, "tags": ["run,swim"] })).err(),
+            Some(InvalidFieldError("tags")));
+        assert_eq!(from_value(&json!({ "datetime": "2020-01-01T09:10:11Z", "score": 1, "tags": ["run|swim"] })).err(),
+            Some(InvalidFieldError("tags")));
+        assert_eq!(from_value(&json!({ "datetime": "2020-01-01T09:10:11Z", "score": 1, "tags": ["run\nswim"] })).err(),
+            Some(InvalidFieldError("tags")));
+        assert_eq!(from_value(&json!({ "datetime": "2020-01-01T09:10:11Z", "score": 1, "comment": 1 })).err(),
+            Some(InvalidFieldError("comment")));
+        assert_eq!(from_value(&json!({ "datetime": "2020-01-01T09:10:11Z", "score": 1, "metrics": { "sleep": "long" } })).err(),
+            Some(InvalidFieldError("metrics")));
+        assert_eq!(from_value(&json!({ "datetime": "2020-01-01T09:10:11Z", "score": 1, "metrics": { "score": 1 } })).err(),
+            Some(InvalidFieldError("metrics")));
+        assert_eq!(from_value(&json!({ "datetime": "2020-01-01T09:10:11Z", "score": 1, "attributes": { "weather": 1 } })).err(),
+            Some(InvalidFieldError("attributes")));
+        assert_eq!(from_value(&json!({ "datetime": "2020-01-01T09:10:11Z", "score": 1, "attributes": { "weather": "a,b" } })).err(),
+            Some(InvalidFieldError("attributes")));
+    }
+}
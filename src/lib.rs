@@ -1,33 +1,94 @@
-use std::{fmt, num};
+use std::fmt;
 use std::error::Error;
 use std::ops::Deref;
-use std::collections::HashSet;
-
-use crate::add_command::{AddCommand, AddCommandError};
-use crate::mood_command::{MoodCommand, MoodReportType, MoodCommandError};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use chrono::{NaiveDate, Weekday};
+
+use crate::add_command::{AddCommand, AddCommandError, SameDayPolicy};
+use crate::ask_command::{AskCommand, AskCommandError};
+use crate::mood_command::{MoodCommand, MoodGrouping, MoodReportType, PlotBackend, MoodCommandError};
 use crate::export_command::{ExportCommand, ExportType, ExportCommandError};
+use crate::report_command::{ReportCommand, ReportCommandError};
+use crate::review_command::{ReviewCommand, ReviewPeriod, ReviewCommandError};
+use crate::tags_command::{TagsCommand, TagsAction, TagsCommandError};
+use crate::import_command::{ImportCommand, ImportType, ImportMode, ImportCommandError};
+use crate::completions_command::{CompletionsCommand, CompleteTagsCommand, Shell, CompletionsCommandError};
+use crate::tui_command::{TuiCommand, TuiCommandError};
+use crate::serve_command::{ServeCommand, ServeCommandError};
+use crate::journal::{CsvColumn, CsvFormat};
+use crate::filter::Filter;
+use crate::metric::Metric;
+use crate::tag::TagRules;
+use crate::score_scale::ScoreScale;
+use crate::daily_aggregation::DailyAggregation;
+use crate::daily_score::DailyScore;
+use crate::timezone::{ReportDays, Timezone};
+use crate::redaction::Redaction;
+use crate::config::{Config, ConfigError};
+use crate::cli::{CommandSpec, ParsedArgs, ParsedOption, ParseError};
 
 const JOURNAL_FILE_PATH: &str = "./howdy.journal";
+const JOURNAL_FILE_PATH_ENV: &str = "HOWDY_JOURNAL";
 const XLSX_FILE_PATH: &str = "./howdy_journal.xlsx";
 const JOURNAL_SEPARATOR: char = '|';
 const TAGS_SEPARATOR: &str = ",";
+const FILTER_DATE_FORMAT: &str = "%Y-%m-%d";
+const PLOT_DATE_FORMAT: &str = "%d/%m/%Y";
+const BIND_ADDRESS: &str = "127.0.0.1:8080";
 
+mod cli;
+mod config;
 mod daily_score;
+mod daily_aggregation;
 mod add_command;
+mod ask_command;
 mod mood_command;
 mod export_command;
+mod import_command;
+mod report_command;
+mod review_command;
+mod tags_command;
+mod completions_command;
+mod tui_command;
+mod serve_command;
 mod mood_report;
+mod filter;
+mod metric;
+mod score_scale;
+mod timezone;
+mod attribute;
+mod tag;
+mod redaction;
 mod journal;
 mod test_helpers;
 
 #[derive(Debug)]
 pub enum CliError {
-    CommandNotProvided,
     FilenameNotProvided,
     CommandNotRecognized(String),
     AddCommandArgsMissingDailyScore,
-    AddCommandArgsInvalidDailyScore { score_string: String, parse_error: num::ParseIntError },
+    AddCommandArgsInvalidDailyScore(String),
+    AddCommandArgsScoreNotAllowed { score: f64, scale: String },
+    AddCommandArgsInvalidMetric(String),
+    ConfigInvalid(ConfigError),
     MoodReportTypeInvalid(String),
+    MetricInvalid(String),
+    AttributeKeyInvalid(String),
+    TagInvalid(String),
+    TagsRenameArgsMissing,
+    PlotBackendInvalid(String),
+    CommandOptionInvalid { command: String, option: String },
+    UnexpectedArgument { command: String, argument: String },
+    ImportCommandArgsMissingFilePath,
+    ReportCommandArgsMissingFormat,
+    ReviewPeriodInvalid(String),
+    CompletionsShellInvalid(String),
+    OptionValueMissing(String),
+    CsvColumnInvalid(String),
+    FilterDateInvalid(String),
+    FilterScoreInvalid(String),
     CommandExecutionError(Box<dyn Error>),
 }
 
@@ -35,7 +96,7 @@ impl std::error::Error for CliError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
         match self {
             Self::CommandExecutionError(error) => Some(error.deref()),
-            Self::AddCommandArgsInvalidDailyScore { score_string: _, parse_error } => Some(parse_error),
+            Self::ConfigInvalid(config_error) => Some(config_error),
             _ => None
         }
     }
@@ -44,26 +105,65 @@ impl std::error::Error for CliError {
 impl fmt::Display for CliError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let message = match self {
-            Self::CommandNotProvided => "command is not provided".to_string(),
             Self::FilenameNotProvided => "'-f' option requires file path which is not provided".to_string(),
             Self::CommandNotRecognized(command) => format!("command '{}' is not recognized", command),
             Self::AddCommandArgsMissingDailyScore => "daily score is not provided for add command".to_string(),
-            Self::AddCommandArgsInvalidDailyScore { score_string, parse_error: _ } => {
-                format!("cannot parse daily score '{}' as int for add command", score_string)
+            Self::AddCommandArgsInvalidDailyScore(score_string) => {
+                format!("cannot parse daily score '{}' as number for add command", score_string)
             },
+            Self::AddCommandArgsScoreNotAllowed { score, scale } => format!("score {} is not allowed, expected {}", score, scale),
+            Self::AddCommandArgsInvalidMetric(metric) => format!("'{}' is not a valid metric for add command, expected NAME=VALUE", metric),
+            Self::ConfigInvalid(_) => "cannot load config".to_string(),
             Self::MoodReportTypeInvalid(report_type) => format!("'{}' is not a valid mood report type", report_type),
+            Self::MetricInvalid(metric) => format!("'{}' is not a valid metric name", metric),
+            Self::AttributeKeyInvalid(key) => format!("'{}' is not a valid attribute key", key),
+            Self::TagInvalid(tag) => format!("'{}' is not a valid tag", tag),
+            Self::TagsRenameArgsMissing => "tags to rename are not provided, use 'tags rename OLD NEW'".to_string(),
+            Self::PlotBackendInvalid(plot_backend) => format!("'{}' is not a valid plot backend, expected 'gnuplot' or 'none'", plot_backend),
+            Self::CommandOptionInvalid { command, option } if command.is_empty() => format!("'{}' is not a valid option", option),
+            Self::CommandOptionInvalid { command, option } => format!("'{}' is not a valid {} command option", option, command),
+            Self::UnexpectedArgument { command, argument } => format!("unexpected argument '{}' for {} command", argument, command),
+            Self::CompletionsShellInvalid(shell) => format!("'{}' is not a supported shell, expected bash, zsh or fish", shell),
+            Self::ImportCommandArgsMissingFilePath => "file path is not provided for import command".to_string(),
+            Self::ReportCommandArgsMissingFormat => "report format is not provided, use '--html DIR'".to_string(),
+            Self::ReviewPeriodInvalid(period) => format!("'{}' is not a valid review period, expected YYYY-MM or YYYY", period),
+            Self::OptionValueMissing(option) => format!("'{}' option requires a value which is not provided", option),
+            Self::CsvColumnInvalid(column_name) => format!("'{}' is not a valid csv column", column_name),
+            Self::FilterDateInvalid(date) => format!("'{}' is not a valid date, expected YYYY-MM-DD", date),
+            Self::FilterScoreInvalid(score) => format!("'{}' is not a valid score filter", score),
             Self::CommandExecutionError(_) => "failed to execute command".to_string(),
         };
         write!(f, "{}", message)
     }
 }
 
+impl CliError {
+    fn from_parse_error(parse_error: ParseError, command_spec: &CommandSpec) -> Self {
+        match parse_error {
+            ParseError::OptionValueMissing(option) if option == "-f" || option == "--file" => Self::FilenameNotProvided,
+            ParseError::OptionValueMissing(option) => Self::OptionValueMissing(option),
+            ParseError::OptionInvalid(option) => Self::CommandOptionInvalid { command: command_spec.name.to_string(), option },
+        }
+    }
+
+    // Errors caused by wrong command line arguments, as opposed to errors of command execution.
+    pub fn is_usage_error(&self) -> bool {
+        !matches!(self, Self::ConfigInvalid(_) | Self::CommandExecutionError(_))
+    }
+}
+
 impl From<AddCommandError> for CliError {
     fn from(error: add_command::AddCommandError) -> Self {
         Self::CommandExecutionError(Box::new(error))
     }
 }
 
+impl From<AskCommandError> for CliError {
+    fn from(error: ask_command::AskCommandError) -> Self {
+        Self::CommandExecutionError(Box::new(error))
+    }
+}
+
 impl From<MoodCommandError> for CliError {
     fn from(error: mood_command::MoodCommandError) -> Self {
         Self::CommandExecutionError(Box::new(error))
@@ -76,100 +176,459 @@ impl From<ExportCommandError> for CliError {
     }
 }
 
+impl From<ImportCommandError> for CliError {
+    fn from(error: import_command::ImportCommandError) -> Self {
+        Self::CommandExecutionError(Box::new(error))
+    }
+}
+
+impl From<ReportCommandError> for CliError {
+    fn from(error: report_command::ReportCommandError) -> Self {
+        Self::CommandExecutionError(Box::new(error))
+    }
+}
+
+impl From<CompletionsCommandError> for CliError {
+    fn from(error: completions_command::CompletionsCommandError) -> Self {
+        Self::CommandExecutionError(Box::new(error))
+    }
+}
+
+impl From<TuiCommandError> for CliError {
+    fn from(error: tui_command::TuiCommandError) -> Self {
+        Self::CommandExecutionError(Box::new(error))
+    }
+}
+
+impl From<ServeCommandError> for CliError {
+    fn from(error: serve_command::ServeCommandError) -> Self {
+        Self::CommandExecutionError(Box::new(error))
+    }
+}
+
+impl From<TagsCommandError> for CliError {
+    fn from(error: tags_command::TagsCommandError) -> Self {
+        Self::CommandExecutionError(Box::new(error))
+    }
+}
+
+impl From<ReviewCommandError> for CliError {
+    fn from(error: review_command::ReviewCommandError) -> Self {
+        Self::CommandExecutionError(Box::new(error))
+    }
+}
+
+#[derive(Clone)]
 pub struct GlobalConfig {
     pub journal_file_path: String,
+    pub report_type: MoodReportType,
+    pub week_start: Weekday,
+    pub timezone: Option<Timezone>,
+    pub report_days: ReportDays,
+    pub plot_backend: PlotBackend,
+    pub plot_date_format: String,
+    pub import_date_format: Option<String>,
+    pub tag_rules: TagRules,
+    pub score_scale: ScoreScale,
+    // same-day records are collapsed with it before reports, if set
+    pub daily_aggregation: Option<DailyAggregation>,
+    pub same_day_policy: SameDayPolicy,
 }
 
-fn build_add_command<I>(mut args: I, global_config: GlobalConfig) -> Result<AddCommand, CliError>
-    where
-    I: Iterator<Item = String>,
-{
-    let mut tag_or_comment_sign;
-    let mut tags = HashSet::new();
+impl From<Config> for GlobalConfig {
+    fn from(config: Config) -> Self {
+        Self {
+            journal_file_path: env::var(JOURNAL_FILE_PATH_ENV).ok()
+                .filter(|journal_file_path| !journal_file_path.is_empty())
+                .or(config.journal_file_path)
+                .or_else(config::user_journal_file_path)
+                .unwrap_or_else(|| JOURNAL_FILE_PATH.to_string()),
+            report_type: config.report_type.unwrap_or(MoodReportType::Monthly),
+            week_start: config.week_start.unwrap_or(Weekday::Mon),
+            timezone: config.timezone,
+            report_days: config.report_days.unwrap_or_default(),
+            plot_backend: config.plot_backend.unwrap_or(PlotBackend::Gnuplot),
+            plot_date_format: config.plot_date_format.unwrap_or_else(|| PLOT_DATE_FORMAT.to_string()),
+            import_date_format: config.import_date_format,
+            tag_rules: TagRules::new(config.normalize_tags.unwrap_or(false), config.tag_aliases.unwrap_or_default()),
+            score_scale: config.score_scale.unwrap_or_default(),
+            daily_aggregation: config.daily_aggregation,
+            same_day_policy: config.same_day_policy.unwrap_or_default(),
+        }
+    }
+}
 
-    let score_string = args.next()
-        .ok_or(CliError::AddCommandArgsMissingDailyScore)?;
+impl GlobalConfig {
+    // Records as reports see them: on days of the reporting zone if configured, filtered and collapsed per day.
+    // Only matching records are aggregated, so an untagged record does not get into a tagged report by its day.
+    pub fn report_daily_scores(&self, daily_scores: Vec<DailyScore>, filter: &Filter) -> Vec<DailyScore> {
+        let daily_scores = timezone::apply(self.report_days, self.timezone, daily_scores).into_iter()
+            .filter(|daily_score| filter.matches(daily_score))
+            .collect();
+        daily_aggregation::apply(self.daily_aggregation, daily_scores)
+    }
+}
 
-    let score = score_string.parse::<i8>()
-        .map_err(|parse_error| CliError::AddCommandArgsInvalidDailyScore { score_string, parse_error })?;
+fn build_add_command(args: ParsedArgs, mut global_config: GlobalConfig) -> Result<AddCommand, CliError> {
+    let mut positionals = args.positionals.into_iter();
 
-    loop {
-        tag_or_comment_sign = args.next();
-        match tag_or_comment_sign.as_deref() {
-            Some("--comment") | Some("-c") | None => break,
-            Some(tag) => tags.insert(tag.to_string())
-        };
-    };
+    let score_string = positionals.next()
+        .ok_or(CliError::AddCommandArgsMissingDailyScore)?;
+
+    let score = global_config.score_scale.parse(&score_string)
+        .ok_or(CliError::AddCommandArgsInvalidDailyScore(score_string))?;
+    if !global_config.score_scale.is_allowed(score) {
+        return Err(CliError::AddCommandArgsScoreNotAllowed { score, scale: global_config.score_scale.description() });
+    }
 
-    let comment_string: String = args.collect::<Vec<String>>().join(" ");
+    // bare `KEY=VALUE` arguments are attributes, the rest are tags
+    let (tags, attributes) = attribute::split_tags(positionals);
+
+    let metrics = args.options.iter()
+        .filter(|option| option.name == "--metric")
+        .map(|option| {
+            let metric_str = option.value.clone().unwrap_or_default();
+            metric::parse_metric(&metric_str).ok_or(CliError::AddCommandArgsInvalidMetric(metric_str))
+        })
+        .collect::<Result<BTreeMap<String, f64>, CliError>>()?;
+
+    // repeated comment options are joined, as the comment used to take all the rest of arguments
+    let comment_string = args.options.iter()
+        .filter(|option| option.name == "--comment")
+        .filter_map(|option| option.value.as_deref())
+        .filter(|comment| !comment.is_empty())
+        .collect::<Vec<&str>>()
+        .join(" ");
     let comment = if comment_string.is_empty() {
         None
     } else {
         Some(comment_string)
     };
 
-    Ok(AddCommand { score, tags, comment, datetime: None, global_config })
+    // forced records are added whatever the day already has
+    if args.options.iter().any(|option| option.name == "--force") {
+        global_config.same_day_policy = SameDayPolicy::Allow;
+    }
+
+    Ok(AddCommand { score, metrics, tags, attributes, comment, datetime: None, global_config })
 }
 
-fn build_mood_command<I>(mut args: I, global_config: GlobalConfig) -> Result<MoodCommand, CliError>
-    where
-    I: Iterator<Item = String>,
-{
-    let mut tag_or_type_sign;
-    let mut tags = HashSet::new();
-    loop {
-        tag_or_type_sign = args.next();
-        match tag_or_type_sign.as_deref() {
-            Some("--type") | Some("-t") | None => break,
-            Some(tag) => tags.insert(tag.to_string())
-        };
+// Applies options shared by commands which filter journal records.
+// Returns `false` if option is not a filter option.
+fn parse_filter_option(option: &ParsedOption, filter: &mut Filter, tag_rules: &TagRules) -> Result<bool, CliError> {
+    let option_value = || option.value.clone().unwrap_or_default();
+    let parse_date = |date: String| NaiveDate::parse_from_str(&date, FILTER_DATE_FORMAT)
+        .map_err(|_| CliError::FilterDateInvalid(date));
+    let parse_score = |score: String| metric::parse_value(&score)
+        .ok_or(CliError::FilterScoreInvalid(score));
+
+    match option.name {
+        "--tag" => { filter.tags.insert(tag_rules.filter_tag(&option_value())); },
+        "--from" => filter.from = Some(parse_date(option_value())?),
+        "--to" => filter.to = Some(parse_date(option_value())?),
+        "--min-score" => filter.min_score = Some(parse_score(option_value())?),
+        "--max-score" => filter.max_score = Some(parse_score(option_value())?),
+        _ => return Ok(false),
+    }
+
+    Ok(true)
+}
+
+// Metric reports are made of, the primary score by default.
+fn parse_metric_option(args: &ParsedArgs) -> Result<Metric, CliError> {
+    match args.value("--metric") {
+        Some(metric_str) => Metric::parse(metric_str).ok_or_else(|| CliError::MetricInvalid(metric_str.to_string())),
+        None => Ok(Metric::Score),
+    }
+}
+
+fn build_mood_command(args: ParsedArgs, mut global_config: GlobalConfig) -> Result<MoodCommand, CliError> {
+    let mut filter = Filter::default();
+    let mut report_type = global_config.report_type;
+    let mut group_by = None;
+    let metric = parse_metric_option(&args)?;
+
+    for option in args.options.iter() {
+        let option_value = || option.value.clone().unwrap_or_default();
+
+        match option.name {
+            "--type" => {
+                report_type = MoodReportType::parse(&option_value())
+                    .ok_or_else(|| CliError::MoodReportTypeInvalid(option_value()))?;
+            },
+            "--group-by" => {
+                let key = option_value();
+                if !attribute::is_key_valid(&key) {
+                    return Err(CliError::AttributeKeyInvalid(key));
+                }
+                group_by = Some(MoodGrouping::Attribute(key));
+            },
+            "--by-hour" => group_by = Some(MoodGrouping::HourOfDay),
+            "--plot" => {
+                global_config.plot_backend = PlotBackend::parse(&option_value())
+                    .ok_or_else(|| CliError::PlotBackendInvalid(option_value()))?;
+            },
+            _ => { parse_filter_option(option, &mut filter, &global_config.tag_rules)?; },
+        }
+    }
+    filter.tags.extend(args.positionals.iter().map(|tag| global_config.tag_rules.filter_tag(tag)));
+
+    Ok(MoodCommand { report_type, group_by, global_config, filter, metric })
+}
+
+fn build_export_command(args: ParsedArgs, global_config: GlobalConfig) -> Result<ExportCommand, CliError> {
+    let mut with_summary = false;
+    let mut filter = Filter::default();
+    let mut redaction = Redaction::default();
+    let metric = parse_metric_option(&args)?;
+
+    for option in args.options.iter() {
+        let option_value = || option.value.clone().unwrap_or_default();
+
+        match option.name {
+            "--summary" => with_summary = true,
+            "--redact-comments" => redaction.drop_comments = true,
+            "--date-only" => redaction.truncate_time = true,
+            "--hash-tags" => redaction.tag_salt = Some(option_value()),
+            "--allow-tags" => {
+                let tag_allowlist = option_value().split(TAGS_SEPARATOR).map(str::to_string).collect();
+                redaction.tag_allowlist = Some(tag_allowlist);
+            },
+            _ => { parse_filter_option(option, &mut filter, &global_config.tag_rules)?; },
+        }
+    }
+
+    let file_path = single_positional(args.positionals, "export")?.unwrap_or_else(|| XLSX_FILE_PATH.to_string());
+    let export_type = ExportType::from_file_path(&file_path);
+
+    Ok(ExportCommand { global_config, export_type, file_path, with_summary, filter, metric, redaction })
+}
+
+fn build_report_command(args: ParsedArgs, global_config: GlobalConfig) -> Result<ReportCommand, CliError> {
+    let mut filter = Filter::default();
+    let metric = parse_metric_option(&args)?;
+
+    for option in args.options.iter() {
+        parse_filter_option(option, &mut filter, &global_config.tag_rules)?;
+    }
+
+    if let Some(argument) = args.positionals.first() {
+        return Err(CliError::UnexpectedArgument { command: "report".to_string(), argument: argument.clone() });
+    }
+    let output_dir = args.value("--html").ok_or(CliError::ReportCommandArgsMissingFormat)?.to_string();
+
+    Ok(ReportCommand { global_config, output_dir, filter, metric })
+}
+
+fn build_review_command(args: ParsedArgs, global_config: GlobalConfig) -> Result<ReviewCommand, CliError> {
+    let mut filter = Filter::default();
+    let metric = parse_metric_option(&args)?;
+
+    for option in args.options.iter() {
+        parse_filter_option(option, &mut filter, &global_config.tag_rules)?;
+    }
+
+    let output_file_path = args.value("--output").map(str::to_string);
+    let period = match single_positional(args.positionals, "review")? {
+        Some(period_str) => ReviewPeriod::parse(&period_str).ok_or(CliError::ReviewPeriodInvalid(period_str))?,
+        None => ReviewPeriod::current_month(global_config.timezone),
+    };
+
+    Ok(ReviewCommand { global_config, period, filter, metric, output_file_path })
+}
+
+fn build_tags_command(args: ParsedArgs, global_config: GlobalConfig) -> Result<TagsCommand, CliError> {
+    let mut filter = Filter::default();
+    let metric = parse_metric_option(&args)?;
+
+    for option in args.options.iter() {
+        parse_filter_option(option, &mut filter, &global_config.tag_rules)?;
+    }
+
+    let mut positionals = args.positionals.into_iter();
+    let action = match positionals.next() {
+        None => TagsAction::List,
+        Some(subcommand) if subcommand == "rename" => {
+            let (from, to) = match (positionals.next(), positionals.next()) {
+                (Some(from), Some(to)) => (from, to),
+                _ => return Err(CliError::TagsRenameArgsMissing),
+            };
+            if let Some(invalid_tag) = [&from, &to].iter().find(|tag| !tag::is_valid(tag)) {
+                return Err(CliError::TagInvalid(invalid_tag.to_string()));
+            }
+            TagsAction::Rename { from, to }
+        },
+        Some(argument) => return Err(CliError::UnexpectedArgument { command: "tags".to_string(), argument }),
     };
+    if let Some(argument) = positionals.next() {
+        return Err(CliError::UnexpectedArgument { command: "tags".to_string(), argument });
+    }
+
+    Ok(TagsCommand { global_config, action, filter, metric })
+}
+
+fn build_import_command(args: ParsedArgs, global_config: GlobalConfig) -> Result<ImportCommand, CliError> {
+    let mut csv_format = CsvFormat { date_format: global_config.import_date_format.clone(), ..CsvFormat::default() };
+    let mut import_mode = ImportMode::Merge;
+
+    for option in args.options.iter() {
+        let option_value = || option.value.clone().unwrap_or_default();
+
+        match option.name {
+            "--date-format" => csv_format.date_format = Some(option_value()),
+            "--columns" => {
+                let columns = option_value()
+                    .split(',')
+                    .map(|column_name| CsvColumn::parse(column_name).ok_or_else(|| CliError::CsvColumnInvalid(column_name.to_string())))
+                    .collect::<Result<Vec<CsvColumn>, CliError>>()?;
+                csv_format.columns = Some(columns);
+            },
+            "--merge" => import_mode = ImportMode::Merge,
+            "--replace" => import_mode = ImportMode::Replace,
+            _ => {},
+        }
+    }
+
+    let skip_confirmation = args.has("--yes");
+    let file_path = single_positional(args.positionals, "import")?.ok_or(CliError::ImportCommandArgsMissingFilePath)?;
+    let import_type = ImportType::from_file_path(&file_path, csv_format);
+
+    Ok(ImportCommand { global_config, import_type, import_mode, file_path, skip_confirmation })
+}
+
+fn build_tui_command(args: ParsedArgs, global_config: GlobalConfig) -> Result<TuiCommand, CliError> {
+    if let Some(argument) = args.positionals.first() {
+        return Err(CliError::UnexpectedArgument { command: "tui".to_string(), argument: argument.clone() });
+    }
+
+    Ok(TuiCommand { global_config })
+}
+
+fn build_serve_command(args: ParsedArgs, global_config: GlobalConfig) -> Result<ServeCommand, CliError> {
+    if let Some(argument) = args.positionals.first() {
+        return Err(CliError::UnexpectedArgument { command: "serve".to_string(), argument: argument.clone() });
+    }
+    let bind_address = args.value("--bind").unwrap_or(BIND_ADDRESS).to_string();
+
+    let token = args.value("--token").map(str::to_string);
 
-    let report_type_str = args.next();
-    let report_type = match report_type_str.as_deref() {
-        Some("m") | Some("monthly") => MoodReportType::MonthlyIterative,
-        Some("lm") | Some("last month") => MoodReportType::Monthly,
-        Some("ly") | Some("last year") => MoodReportType::Yearly,
-        Some("mm") | Some("moving") => MoodReportType::MovingMonthly,
-        Some("w") | Some("weekly") => MoodReportType::WeeklyIterative,
-        Some("7d") | Some("7 days") => MoodReportType::SevenDaysIterative,
-        Some("30d") | Some("30 days") => MoodReportType::ThirtyDaysIterative,
-        None => MoodReportType::Monthly,
-        Some(unrecognized_option) => return Err(CliError::MoodReportTypeInvalid(unrecognized_option.to_string())),
+    Ok(ServeCommand { global_config, bind_address, token })
+}
+
+fn build_completions_command(args: ParsedArgs) -> Result<CompletionsCommand, CliError> {
+    let shell_str = single_positional(args.positionals, "completions")?.unwrap_or_default();
+    let shell = Shell::parse(&shell_str).ok_or(CliError::CompletionsShellInvalid(shell_str))?;
+
+    Ok(CompletionsCommand { shell })
+}
+
+fn single_positional(positionals: Vec<String>, command: &str) -> Result<Option<String>, CliError> {
+    let mut positionals = positionals.into_iter();
+    let positional = positionals.next();
+
+    match positionals.next() {
+        Some(argument) => Err(CliError::UnexpectedArgument { command: command.to_string(), argument }),
+        None => Ok(positional),
+    }
+}
+
+// Journal in the current directory used to be the default one, so it's likely
+// that user expects it to be used.
+fn warn_if_journal_shadowed(journal_file_path: &str) {
+    let local_journal_file_path = match fs::canonicalize(JOURNAL_FILE_PATH) {
+        Ok(local_journal_file_path) => local_journal_file_path,
+        Err(_) => return,
     };
 
-    Ok(MoodCommand { report_type, global_config, tags })
+    if fs::canonicalize(journal_file_path).ok().as_ref() != Some(&local_journal_file_path) {
+        eprintln!("Warning: '{}' is ignored, journal '{}' is used instead (use '-f {}' to use the local one)",
+                  JOURNAL_FILE_PATH, journal_file_path, JOURNAL_FILE_PATH);
+    }
 }
 
-fn build_export_command<I>(mut args: I, global_config: GlobalConfig) -> Result<ExportCommand, CliError>
-    where
+pub fn run<I>(cli_args: I) -> Result<(), CliError>
+where
     I: Iterator<Item = String>,
 {
-    let file_path = args.next().unwrap_or_else(|| XLSX_FILE_PATH.to_string());
-    let export_type = ExportType::Xlsx;
+    run_with_config(cli_args, Config::load)
+}
 
-    Ok(ExportCommand { global_config, export_type, file_path })
+// `ask` is run if no command is given.
+fn command_name(top_level_args: &ParsedArgs) -> &str {
+    match top_level_args.positionals.first() {
+        Some(command_name) => command_name.as_str(),
+        None if top_level_args.has("--help") => "help",
+        None => "ask",
+    }
 }
 
-pub fn run<I>(mut cli_args: I) -> Result<(), CliError>
+// Config is loaded only when a command is going to be run, so help works with invalid config files.
+fn run_with_config<I, L>(mut cli_args: I, load_config: L) -> Result<(), CliError>
 where
     I: Iterator<Item = String>,
+    L: FnOnce() -> Result<Config, ConfigError>,
 {
     // skip exec filename
     cli_args.next();
 
-    let mut global_config = GlobalConfig { journal_file_path: JOURNAL_FILE_PATH.to_string() };
+    let top_level_args = cli::TOP_LEVEL.parse(&mut cli_args, true)
+        .map_err(|parse_error| CliError::from_parse_error(parse_error, &cli::TOP_LEVEL))?;
+    if top_level_args.has("--version") {
+        println!("howdy {}", env!("CARGO_PKG_VERSION"));
+        return Ok(());
+    }
+
+    let command_name = command_name(&top_level_args);
+    if command_name == "help" {
+        let help = match cli_args.next() {
+            Some(command_name) => CommandSpec::find(&command_name).ok_or(CliError::CommandNotRecognized(command_name))?.help(),
+            None => cli::TOP_LEVEL.help(),
+        };
+        println!("{}", help);
+        return Ok(());
+    }
 
-    let mut argument = cli_args.next().ok_or(CliError::CommandNotProvided)?;
-    if argument.as_str() == "-f" {
-        global_config.journal_file_path = cli_args.next().ok_or(CliError::FilenameNotProvided)?;
-        argument = cli_args.next().ok_or(CliError::CommandNotProvided)?
+    let command_spec = CommandSpec::find(command_name)
+        .ok_or_else(|| CliError::CommandNotRecognized(command_name.to_string()))?;
+    let args = command_spec.parse(&mut cli_args, false)
+        .map_err(|parse_error| CliError::from_parse_error(parse_error, command_spec))?;
+    if top_level_args.has("--help") || args.has("--help") {
+        println!("{}", command_spec.help());
+        return Ok(());
+    }
+
+    // shell scripts do not depend on the config, and tag completion output goes right into the shell,
+    // so neither of them warns or fails because of the config
+    let completing = command_spec.name == "__complete-tags";
+    if command_spec.name == "completions" {
+        build_completions_command(args)?.run()?;
+        return Ok(());
+    }
+
+    let config = match load_config() {
+        Ok(config) => config,
+        Err(_) if completing => return Ok(()),
+        Err(config_error) => return Err(CliError::ConfigInvalid(config_error)),
     };
+    let mut global_config = GlobalConfig::from(config);
+    match args.value("--file").or_else(|| top_level_args.value("--file")) {
+        Some(journal_file_path) => global_config.journal_file_path = journal_file_path.to_string(),
+        None if completing => {},
+        None => warn_if_journal_shadowed(&global_config.journal_file_path),
+    }
 
-    match argument.as_str() {
-        "add" => build_add_command(cli_args, global_config)?.run()?,
-        "mood" => build_mood_command(cli_args, global_config)?.run()?,
-        "export" => build_export_command(cli_args, global_config)?.run()?,
+    match command_spec.name {
+        "ask" => AskCommand { global_config }.run()?,
+        "add" => build_add_command(args, global_config)?.run()?,
+        "mood" => build_mood_command(args, global_config)?.run()?,
+        "tags" => build_tags_command(args, global_config)?.run()?,
+        "export" => build_export_command(args, global_config)?.run()?,
+        "import" => build_import_command(args, global_config)?.run()?,
+        "report" => build_report_command(args, global_config)?.run()?,
+        "review" => build_review_command(args, global_config)?.run()?,
+        "tui" => build_tui_command(args, global_config)?.run()?,
+        "serve" => build_serve_command(args, global_config)?.run()?,
+        "__complete-tags" => CompleteTagsCommand { global_config }.run()?,
         unrecognized_command => return Err(CliError::CommandNotRecognized(unrecognized_command.to_string())),
     }
 
@@ -178,24 +637,20 @@ where
 
 #[cfg(test)]
 mod tests {
-    use crate::test_helpers::build_cli_args;
+    use crate::test_helpers::{build_cli_args, run_without_config};
     use super::*;
 
     #[test]
     fn no_command_error() {
-        let args = Vec::new();
-        let result_err = run(args.into_iter()).err().unwrap();
+        let top_level_args = cli::TOP_LEVEL.parse(&mut build_cli_args("-f path"), true).unwrap();
 
-        assert!(
-            matches!(result_err, CliError::CommandNotProvided)
-        );
-        assert_eq!(format!("{}", result_err), "command is not provided".to_string());
+        assert_eq!(command_name(&top_level_args), "ask");
     }
 
     #[test]
     fn no_file_path_error() {
         let args = build_cli_args("exec/path -f");
-        let result_err = run(args.into_iter()).err().unwrap();
+        let result_err = run_without_config(args.into_iter()).err().unwrap();
 
         assert!(
             matches!(result_err, CliError::FilenameNotProvided)
@@ -206,7 +661,7 @@ mod tests {
     #[test]
     fn wrong_command_error() {
         let args = build_cli_args("exec/path foo");
-        let result_err = run(args.into_iter()).err().unwrap();
+        let result_err = run_without_config(args.into_iter()).err().unwrap();
 
         assert!(
             matches!(result_err, CliError::CommandNotRecognized(_))
@@ -217,7 +672,7 @@ mod tests {
     #[test]
     fn no_add_args_error() {
         let args = build_cli_args("exec/path add");
-        let result_err = run(args.into_iter()).err().unwrap();
+        let result_err = run_without_config(args.into_iter()).err().unwrap();
 
         assert!(
             matches!(result_err, CliError::AddCommandArgsMissingDailyScore)
@@ -226,22 +681,35 @@ mod tests {
             "daily score is not provided for add command")
     }
 
+    #[test]
+    fn invalid_add_score_error() {
+        let args = build_cli_args("exec/path add 7,5");
+        let result_err = run_without_config(args.into_iter()).err().unwrap();
+
+        assert!(
+            matches!(result_err, CliError::AddCommandArgsInvalidDailyScore(_))
+        );
+        assert_eq!(format!("{}", result_err),
+            "cannot parse daily score '7,5' as number for add command".to_string());
+    }
+
     #[test]
     fn small_add_score_error() {
         let args = build_cli_args("exec/path add -250");
-        let result_err = run(args.into_iter()).err().unwrap();
+        let score_scale = ScoreScale { min: Some(-2.0), max: Some(2.0), ..ScoreScale::default() };
+        let result_err = run_with_config(args, || Ok(Config { score_scale: Some(score_scale), ..Config::default() })).err().unwrap();
 
         assert!(
-            matches!(result_err, CliError::AddCommandArgsInvalidDailyScore { .. })
+            matches!(result_err, CliError::AddCommandArgsScoreNotAllowed { .. })
         );
         assert_eq!(format!("{}", result_err),
-            "cannot parse daily score '-250' as int for add command".to_string());
+            "score -250 is not allowed, expected from -2 to 2".to_string());
     }
 
     #[test]
     fn wrong_mood_report_type_error() {
         let args = build_cli_args("exec/path mood -t mmm");
-        let result_err = run(args.into_iter()).err().unwrap();
+        let result_err = run_without_config(args.into_iter()).err().unwrap();
 
         assert!(
             matches!(result_err, CliError::MoodReportTypeInvalid(_))
@@ -250,4 +718,120 @@ mod tests {
         assert_eq!(format!("{}", result_err),
             "'mmm' is not a valid mood report type".to_string());
     }
+
+    #[test]
+    fn wrong_plot_backend_error() {
+        let result_err = run_without_config(build_cli_args("exec/path mood --plot svg")).err().unwrap();
+
+        assert!(matches!(result_err, CliError::PlotBackendInvalid(_)));
+        assert_eq!(result_err.to_string(), "'svg' is not a valid plot backend, expected 'gnuplot' or 'none'");
+    }
+
+    #[test]
+    fn metric_args_errors() {
+        let add_err = run_without_config(build_cli_args("exec/path add 1 -m sleep=long")).err().unwrap();
+        assert!(matches!(add_err, CliError::AddCommandArgsInvalidMetric(_)));
+        assert_eq!(add_err.to_string(), "'sleep=long' is not a valid metric for add command, expected NAME=VALUE");
+
+        let mood_err = run_without_config(build_cli_args("exec/path mood --metric 1st")).err().unwrap();
+        assert!(matches!(mood_err, CliError::MetricInvalid(_)));
+        assert_eq!(mood_err.to_string(), "'1st' is not a valid metric name");
+
+        let group_by_err = run_without_config(build_cli_args("exec/path mood --group-by weather=rain")).err().unwrap();
+        assert!(matches!(group_by_err, CliError::AttributeKeyInvalid(_)));
+        assert_eq!(group_by_err.to_string(), "'weather=rain' is not a valid attribute key");
+    }
+
+    #[test]
+    fn filter_args_errors() {
+        let invalid_date_err = run_without_config(build_cli_args("exec/path export --from 01.01.2020")).err().unwrap();
+        assert!(matches!(invalid_date_err, CliError::FilterDateInvalid(_)));
+        assert_eq!(invalid_date_err.to_string(), "'01.01.2020' is not a valid date, expected YYYY-MM-DD");
+
+        let invalid_score_err = run_without_config(build_cli_args("exec/path mood --min-score foo")).err().unwrap();
+        assert!(matches!(invalid_score_err, CliError::FilterScoreInvalid(_)));
+        assert_eq!(invalid_score_err.to_string(), "'foo' is not a valid score filter");
+
+        let missing_value_err = run_without_config(build_cli_args("exec/path mood --tag")).err().unwrap();
+        assert!(matches!(missing_value_err, CliError::OptionValueMissing(_)));
+    }
+
+    #[test]
+    fn export_args_errors() {
+        let result_err = run_without_config(build_cli_args("exec/path export --foo")).err().unwrap();
+
+        assert!(matches!(result_err, CliError::CommandOptionInvalid { .. }));
+        assert_eq!(result_err.to_string(), "'--foo' is not a valid export command option");
+    }
+
+    #[test]
+    fn report_args_errors() {
+        let missing_format_err = run_without_config(build_cli_args("exec/path report")).err().unwrap();
+        assert!(matches!(missing_format_err, CliError::ReportCommandArgsMissingFormat));
+        assert_eq!(missing_format_err.to_string(), "report format is not provided, use '--html DIR'");
+
+        let invalid_option_err = run_without_config(build_cli_args("exec/path report --pdf out")).err().unwrap();
+        assert!(matches!(invalid_option_err, CliError::CommandOptionInvalid { .. }));
+        assert_eq!(invalid_option_err.to_string(), "'--pdf' is not a valid report command option");
+    }
+
+    #[test]
+    fn unexpected_args_errors() {
+        let result_err = run_without_config(build_cli_args("exec/path review 2020 2021")).err().unwrap();
+        assert!(matches!(result_err, CliError::UnexpectedArgument { .. }));
+        assert_eq!(result_err.to_string(), "unexpected argument '2021' for review command");
+
+        let top_level_err = run_without_config(build_cli_args("exec/path --foo add 1")).err().unwrap();
+        assert!(matches!(top_level_err, CliError::CommandOptionInvalid { .. }));
+        assert_eq!(top_level_err.to_string(), "'--foo' is not a valid option");
+    }
+
+    #[test]
+    fn tags_args_errors() {
+        let missing_tags_err = run_without_config(build_cli_args("exec/path tags rename sport")).err().unwrap();
+        assert!(matches!(missing_tags_err, CliError::TagsRenameArgsMissing));
+        assert_eq!(missing_tags_err.to_string(), "tags to rename are not provided, use 'tags rename OLD NEW'");
+
+        let invalid_tag_err = run_without_config(build_cli_args("exec/path tags rename sport weather=sun")).err().unwrap();
+        assert!(matches!(invalid_tag_err, CliError::TagInvalid(_)));
+        assert_eq!(invalid_tag_err.to_string(), "'weather=sun' is not a valid tag");
+
+        let unexpected_err = run_without_config(build_cli_args("exec/path tags sport")).err().unwrap();
+        assert_eq!(unexpected_err.to_string(), "unexpected argument 'sport' for tags command");
+    }
+
+    #[test]
+    fn completions_args_errors() {
+        let result_err = run_without_config(build_cli_args("exec/path completions powershell")).err().unwrap();
+
+        assert!(matches!(result_err, CliError::CompletionsShellInvalid(_)));
+        assert_eq!(result_err.to_string(), "'powershell' is not a supported shell, expected bash, zsh or fish");
+    }
+
+    #[test]
+    fn review_args_errors() {
+        let result_err = run_without_config(build_cli_args("exec/path review 2020-13")).err().unwrap();
+
+        assert!(matches!(result_err, CliError::ReviewPeriodInvalid(_)));
+        assert_eq!(result_err.to_string(), "'2020-13' is not a valid review period, expected YYYY-MM or YYYY");
+    }
+
+    #[test]
+    fn import_args_errors() {
+        let missing_file_err = run_without_config(build_cli_args("exec/path import")).err().unwrap();
+        assert!(matches!(missing_file_err, CliError::ImportCommandArgsMissingFilePath));
+        assert_eq!(missing_file_err.to_string(), "file path is not provided for import command");
+
+        let invalid_option_err = run_without_config(build_cli_args("exec/path import file.csv --foo")).err().unwrap();
+        assert!(matches!(invalid_option_err, CliError::CommandOptionInvalid { .. }));
+        assert_eq!(invalid_option_err.to_string(), "'--foo' is not a valid import command option");
+
+        let missing_value_err = run_without_config(build_cli_args("exec/path import file.csv --date-format")).err().unwrap();
+        assert!(matches!(missing_value_err, CliError::OptionValueMissing(_)));
+        assert_eq!(missing_value_err.to_string(), "'--date-format' option requires a value which is not provided");
+
+        let invalid_column_err = run_without_config(build_cli_args("exec/path import file.csv --columns date,mood")).err().unwrap();
+        assert!(matches!(invalid_column_err, CliError::CsvColumnInvalid(_)));
+        assert_eq!(invalid_column_err.to_string(), "'mood' is not a valid csv column");
+    }
 }
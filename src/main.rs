@@ -7,6 +7,9 @@ fn main() {
 
     if let Err(error) = howdy::run(args) {
         report_error(&error);
+        if error.is_usage_error() {
+            eprintln!("run 'howdy help' for usage");
+        }
         process::exit(1)
     }
 }
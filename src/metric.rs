@@ -0,0 +1,137 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::daily_score::DailyScore;
+
+pub const SCORE_METRIC_NAME: &str = "score";
+const METRIC_VALUE_SEPARATOR: char = '=';
+
+// Selects the value reports are made of: the primary score, or one of the named metrics
+// stored along with it, e.g. `energy=-1 sleep=7.5`.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub enum Metric {
+    #[default]
+    Score,
+    Named(String),
+}
+
+impl Metric {
+    pub fn parse(metric_str: &str) -> Option<Self> {
+        match metric_str {
+            SCORE_METRIC_NAME => Some(Self::Score),
+            name if is_name_valid(name) => Some(Self::Named(name.to_string())),
+            _ => None,
+        }
+    }
+
+    // Reports of the score are called moods.
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Score => "mood",
+            Self::Named(name) => name,
+        }
+    }
+
+    // Records without the metric have no value and are left out of reports.
+    pub fn value(&self, daily_score: &DailyScore) -> Option<f64> {
+        match self {
+            Self::Score => Some(daily_score.score),
+            Self::Named(name) => daily_score.metrics.get(name).copied(),
+        }
+    }
+}
+
+// Displays reported values with up to 2 decimals and without trailing zeros, so score sums stay integers.
+// Sign and width flags work the same way as for integers.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReportValue(pub f64);
+
+impl fmt::Display for ReportValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let rounded = format!("{:.2}", self.0.abs());
+        let digits = rounded.trim_end_matches('0').trim_end_matches('.');
+        f.pad_integral(self.0 >= 0.0 || digits == "0", "", digits)
+    }
+}
+
+// Names start with a letter and contain letters, digits, `_` and `-`, `score` is reserved for the primary score.
+pub fn is_name_valid(name: &str) -> bool {
+    name != SCORE_METRIC_NAME &&
+        name.chars().next().is_some_and(char::is_alphabetic) &&
+        name.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+}
+
+// Parses `NAME=VALUE` pair.
+pub fn parse_metric(metric_str: &str) -> Option<(String, f64)> {
+    let (name, value) = metric_str.split_once(METRIC_VALUE_SEPARATOR)?;
+    let value = parse_value(value)?;
+
+    if is_name_valid(name) { Some((name.to_string(), value)) } else { None }
+}
+
+// Any finite number, `-0` is read as `0`, so it's not written back with a sign.
+pub fn parse_value(value_str: &str) -> Option<f64> {
+    value_str.trim().parse::<f64>().ok().filter(|value| value.is_finite()).map(|value| value + 0.0)
+}
+
+// Formats metrics as space separated `NAME=VALUE` pairs, ordered by name.
+pub fn format_metrics(metrics: &BTreeMap<String, f64>) -> String {
+    metrics.iter()
+        .map(|(name, value)| format!("{}{}{}", name, METRIC_VALUE_SEPARATOR, value))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+// Parses space separated `NAME=VALUE` pairs, returns the first invalid pair on error.
+pub fn parse_metrics(metrics_str: &str) -> Result<BTreeMap<String, f64>, String> {
+    metrics_str.split_whitespace()
+        .map(|metric_str| parse_metric(metric_str).ok_or_else(|| metric_str.to_string()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn metric_parsing() {
+        assert_eq!(Metric::parse("score"), Some(Metric::Score));
+        assert_eq!(Metric::parse("sleep"), Some(Metric::Named("sleep".to_string())));
+        assert_eq!(Metric::parse("heart-rate_2"), Some(Metric::Named("heart-rate_2".to_string())));
+        assert_eq!(Metric::parse("2sleep"), None);
+        assert_eq!(Metric::parse("sleep=1"), None);
+        assert_eq!(Metric::parse(""), None);
+    }
+
+    #[test]
+    fn metric_values() {
+        let mut daily_score = DailyScore::with_score(-1);
+        daily_score.metrics.insert("sleep".to_string(), 7.5);
+
+        assert_eq!(Metric::Score.value(&daily_score), Some(-1.0));
+        assert_eq!(Metric::Named("sleep".to_string()).value(&daily_score), Some(7.5));
+        assert_eq!(Metric::Named("energy".to_string()).value(&daily_score), None);
+    }
+
+    #[test]
+    fn report_value_display() {
+        assert_eq!(ReportValue(2.0).to_string(), "2");
+        assert_eq!(ReportValue(-0.5).to_string(), "-0.5");
+        assert_eq!(ReportValue(1.0 / 3.0).to_string(), "0.33");
+        assert_eq!(ReportValue(-0.001).to_string(), "0");
+        assert_eq!(format!("{:+}", ReportValue(7.25)), "+7.25");
+        assert_eq!(format!("{:>4}", ReportValue(-1.0)), "  -1");
+    }
+
+    #[test]
+    fn metrics_formatting_and_parsing() {
+        let metrics = parse_metrics(" sleep=7.5  energy=-1 ").unwrap();
+
+        assert_eq!(format_metrics(&metrics), "energy=-1 sleep=7.5");
+        assert_eq!(parse_metrics(""), Ok(BTreeMap::new()));
+        assert_eq!(parse_metrics("energy=high"), Err("energy=high".to_string()));
+        assert_eq!(parse_metrics("score=1"), Err("score=1".to_string()));
+        assert_eq!(parse_metrics("energy=inf"), Err("energy=inf".to_string()));
+        assert_eq!(parse_metric("energy"), None);
+    }
+}
@@ -1,8 +1,11 @@
+use chrono::Weekday;
 use std::error::Error;
-use std::collections::HashSet;
 use std::fmt;
 
-use crate::mood_report::MoodReport;
+use crate::mood_report::{MoodReport, ScoreStats};
+use crate::timezone;
+use crate::filter::Filter;
+use crate::metric::{Metric, ReportValue};
 use crate::GlobalConfig;
 use crate::journal;
 
@@ -11,9 +14,13 @@ mod plot;
 pub struct MoodCommand {
     pub global_config: GlobalConfig,
     pub report_type: MoodReportType,
-    pub tags: HashSet<String>,
+    // sums up by attribute values or hours of day instead of the report type periods
+    pub group_by: Option<MoodGrouping>,
+    pub filter: Filter,
+    pub metric: Metric,
 }
 
+#[derive(Clone, Copy)]
 pub enum MoodReportType {
     WeeklyIterative,
     SevenDaysIterative,
@@ -25,12 +32,77 @@ pub enum MoodReportType {
 }
 
 impl MoodReportType {
+    pub fn parse(report_type_str: &str) -> Option<Self> {
+        match report_type_str {
+            "m" | "monthly" => Some(Self::MonthlyIterative),
+            "lm" | "last month" => Some(Self::Monthly),
+            "ly" | "last year" => Some(Self::Yearly),
+            "mm" | "moving" => Some(Self::MovingMonthly),
+            "w" | "weekly" => Some(Self::WeeklyIterative),
+            "7d" | "7 days" => Some(Self::SevenDaysIterative),
+            "30d" | "30 days" => Some(Self::ThirtyDaysIterative),
+            _ => None,
+        }
+    }
+
+    // Report data, timestamped with period ends.
+    pub fn series(self, mood_report: &MoodReport, week_start: Weekday) -> Vec<(i64, f64)> {
+        match self {
+            Self::Monthly => mood_report.thirty_days_mood(),
+            Self::Yearly => mood_report.yearly_mood(),
+            Self::MonthlyIterative => mood_report.iterative_monthly_mood(),
+            Self::WeeklyIterative => mood_report.iterative_weekly_mood(week_start),
+            Self::SevenDaysIterative => mood_report.iterative_seven_days_mood(),
+            Self::ThirtyDaysIterative => mood_report.iterative_thirty_days_mood(),
+            Self::MovingMonthly => mood_report.thirty_days_moving_mood(),
+        }
+    }
+
+    // Named metrics are reported in place of moods, e.g. `weekly sleep:`.
+    fn caption(&self, metric: &Metric) -> String {
+        let caption = match self {
+            Self::Monthly => "30-days mood:",
+            Self::Yearly => "365-days mood:",
+            Self::MonthlyIterative => "monthly moods:",
+            Self::WeeklyIterative | Self::SevenDaysIterative => "weekly moods:",
+            Self::ThirtyDaysIterative => "thirty day intervals moods:",
+            Self::MovingMonthly => "30-days moving mood:",
+        };
+
+        match metric {
+            Metric::Score => caption.to_string(),
+            Metric::Named(name) => caption.replace("moods", name).replace("mood", name),
+        }
+    }
+
     fn is_plottable(&self) -> bool {
         matches!(self, Self::WeeklyIterative | Self::SevenDaysIterative | Self::MonthlyIterative |
                  Self::ThirtyDaysIterative | Self::MovingMonthly)
     }
 }
 
+pub enum MoodGrouping {
+    Attribute(String),
+    // records are not collapsed by days for it, as its point is to compare check-ins made during a day
+    HourOfDay,
+}
+
+#[derive(Clone, Copy)]
+pub enum PlotBackend {
+    Gnuplot,
+    Disabled,
+}
+
+impl PlotBackend {
+    pub fn parse(plot_backend_str: &str) -> Option<Self> {
+        match plot_backend_str {
+            "gnuplot" => Some(Self::Gnuplot),
+            "none" => Some(Self::Disabled),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum MoodCommandError {
     JournalReadError(journal::JournalError),
@@ -54,25 +126,39 @@ impl fmt::Display for MoodCommandError {
 
 impl MoodCommand {
     pub fn run(self) -> Result<(), MoodCommandError> {
-        let daily_scores = journal::read(&self.global_config.journal_file_path)
+        let daily_scores = journal::read(&self.global_config.journal_file_path, &self.global_config.tag_rules)
             .map_err(MoodCommandError::JournalReadError)?;
 
-        let mood_report = MoodReport { daily_scores: &daily_scores, tags: &self.tags };
+        if let Some(MoodGrouping::HourOfDay) = &self.group_by {
+            // hours of separate records are reported, so they are not collapsed per day
+            let daily_scores = timezone::apply(self.global_config.report_days, self.global_config.timezone, daily_scores);
+            let mood_report = MoodReport { daily_scores: &daily_scores, filter: &self.filter, metric: &self.metric, timezone: self.global_config.timezone };
+            println!("{} by hour of day:", self.metric.name());
+            for (hour, stats) in mood_report.hourly_stats() {
+                println!("{:02}: {}", hour, stats_line(stats));
+            }
+            return Ok(());
+        }
 
-        let (caption, data) = match self.report_type {
-            MoodReportType::Monthly => ("30-days mood:", mood_report.thirty_days_mood()),
-            MoodReportType::Yearly => ("365-days mood:", mood_report.yearly_mood()),
-            MoodReportType::MonthlyIterative => ("monthly moods:", mood_report.iterative_monthly_mood()),
-            MoodReportType::WeeklyIterative => ("weekly moods:",  mood_report.iterative_weekly_mood()),
-            MoodReportType::SevenDaysIterative => ("weekly moods:", mood_report.iterative_seven_days_mood()),
-            MoodReportType::ThirtyDaysIterative => ("thirty day intervals moods:", mood_report.iterative_thirty_days_mood()),
-            MoodReportType::MovingMonthly => ("30-days moving mood:", mood_report.thirty_days_moving_mood()),
-        };
+        let daily_scores = self.global_config.report_daily_scores(daily_scores, &self.filter);
+        let mood_report = MoodReport { daily_scores: &daily_scores, filter: &Filter::default(), metric: &self.metric, timezone: self.global_config.timezone };
+
+        if let Some(MoodGrouping::Attribute(key)) = &self.group_by {
+            println!("{} by {}:", self.metric.name(), key);
+            for (value, stats) in mood_report.attribute_stats(key) {
+                println!("{}: {}", value, stats_line(stats));
+            }
+            return Ok(());
+        }
 
-        println!("{} {:?}", caption, data.iter().map(|ts| ts.1).collect::<Vec<i32>>());
+        let data = self.report_type.series(&mood_report, self.global_config.week_start);
 
-        if self.report_type.is_plottable() && !data.is_empty() {
-            if let Err(error) = plot::draw(&data) {
+        let values = data.iter().map(|ts| ReportValue(ts.1).to_string()).collect::<Vec<String>>();
+        println!("{} [{}]", self.report_type.caption(&self.metric), values.join(", "));
+
+        let plot_enabled = matches!(self.global_config.plot_backend, PlotBackend::Gnuplot);
+        if plot_enabled && self.report_type.is_plottable() && !data.is_empty() {
+            if let Err(error) = plot::draw(&data, &self.global_config.plot_date_format) {
                 println!("Warning: can't init gnuplot: {:?}", error);
             };
         }
@@ -80,3 +166,7 @@ impl MoodCommand {
         Ok(())
     }
 }
+
+fn stats_line(stats: ScoreStats) -> String {
+    format!("{} ({} records, average {})", ReportValue(stats.sum), stats.count, ReportValue(stats.mean()))
+}
@@ -1,8 +1,6 @@
 use gnuplot::{AxesCommon,Auto,CloseSentinel,Figure,Format,GnuplotInitError};
 
-const DATE_FORMAT: &str = "%d/%m/%Y"; // TODO: make it configurable
-
-pub fn draw<Tx, Ty>(data: &[(Tx, Ty)]) -> Result<CloseSentinel, GnuplotInitError>
+pub fn draw<Tx, Ty>(data: &[(Tx, Ty)], date_format: &str) -> Result<CloseSentinel, GnuplotInitError>
 where
     Tx: gnuplot::DataType + Copy,
     Ty: gnuplot::DataType + Copy,
@@ -14,7 +12,7 @@ where
     fg.axes2d()
         .set_title("30-days moving cumulative mood", &[])
         .lines(x, y, &[])
-        .set_x_ticks(Some((Auto, 0)), &[Format(DATE_FORMAT)], &[])
+        .set_x_ticks(Some((Auto, 0)), &[Format(date_format)], &[])
         .set_x_time(true);
     fg.show()
 }
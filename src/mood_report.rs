@@ -1,124 +1,258 @@
-use chrono::{Local, Duration, Datelike, DateTime, FixedOffset};
-use std::collections::{HashSet, HashMap};
-use std::convert::TryFrom;
+use chrono::{Duration, Datelike, NaiveDate, Timelike, Weekday};
+use std::collections::{BTreeMap, HashSet};
 
 use crate::daily_score::DailyScore;
+use crate::filter::Filter;
+use crate::metric::Metric;
+use crate::tag;
+use crate::timezone::{self, Timezone};
+
+const DAY_SECONDS: i64 = 3600 * 24;
+const WEEK_DAYS: i64 = 7;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct ScoreStats {
+    pub count: usize,
+    pub sum: f64,
+}
+
+impl ScoreStats {
+    fn add(&mut self, score: f64) {
+        self.count += 1;
+        self.sum += score;
+    }
+
+    pub fn mean(&self) -> f64 {
+        if self.count == 0 {
+            0.0
+        } else {
+            self.sum / self.count as f64
+        }
+    }
+}
 
-const HOUR_SECONDS: i64 = 3600;
-const DAY_SECONDS: i64 = HOUR_SECONDS * 24;
-const WEEK_SECONDS: i64 = DAY_SECONDS * 7;
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StreakKind {
+    Positive,
+    Negative,
+}
+
+// Consecutive days with summary daily scores of the same sign.
+#[derive(Debug, PartialEq)]
+pub struct Streak {
+    pub kind: StreakKind,
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+}
+
+impl Streak {
+    pub fn days(&self) -> i64 {
+        (self.end - self.start).num_days() + 1
+    }
+}
 
 pub struct MoodReport<'a> {
-    pub daily_scores: &'a Vec<DailyScore>,
-    pub tags: &'a HashSet<String>,
+    pub daily_scores: &'a [DailyScore],
+    pub filter: &'a Filter,
+    pub metric: &'a Metric,
+    // periods end today in the zone, while records are bucketed by their own local dates
+    pub timezone: Option<Timezone>,
 }
 
 impl<'a> MoodReport<'a> {
-    #[cfg(test)]
     pub fn len(&self) -> usize {
-        self.daily_scores.len()
+        self.filtered_daily_scores().count()
     }
 
-    pub fn thirty_days_mood(&self) -> Vec<(i64, i32)> {
-        let now = Local::now();
-        let thirty_days_ago = (now - Duration::days(29)).with_timezone(now.offset());
+    pub fn thirty_days_mood(&self) -> Vec<(i64, f64)> {
+        let now = timezone::now(self.timezone);
+        let thirty_days_ago = now.naive_local().date() - Duration::days(29);
 
-        vec![(now.timestamp(), self.filter_mood_sum(|daily_score| daily_score.datetime.date() >= thirty_days_ago.date()))]
+        vec![(now.timestamp(), self.filter_mood_sum(|daily_score| local_date(daily_score) >= thirty_days_ago))]
     }
 
-    pub fn iterative_weekly_mood(&self) -> Vec<(i64, i32)> {
-        let now = Local::now();
-        let today = now.with_timezone(now.offset()).date();
-        let last_monday = today.and_hms_nano(0, 0, 0, 0) - Duration::days(today.weekday().num_days_from_monday().into());
-        self.iterative_const_period_report(last_monday, WEEK_SECONDS)
+    pub fn iterative_weekly_mood(&self, week_start: Weekday) -> Vec<(i64, f64)> {
+        let today = timezone::today(self.timezone);
+        let days_since_week_start = (today.weekday().num_days_from_monday() + 7 - week_start.num_days_from_monday()) % 7;
+        let last_week_start = today - Duration::days(days_since_week_start.into());
+        self.iterative_const_period_report(last_week_start, WEEK_DAYS)
+    }
+
+    pub fn iterative_seven_days_mood(&self) -> Vec<(i64, f64)> {
+        let today = timezone::today(self.timezone);
+        self.iterative_const_period_report(today.succ(), WEEK_DAYS)
+    }
+
+    pub fn iterative_thirty_days_mood(&self) -> Vec<(i64, f64)> {
+        let today = timezone::today(self.timezone);
+        self.iterative_const_period_report(today.succ(), 30)
+    }
+
+    pub fn iterative_monthly_mood(&self) -> Vec<(i64, f64)> {
+        let today = timezone::today(self.timezone);
+        self.iterative_monthly_report(beginning_of_month(today))
+    }
+
+    pub fn yearly_mood(&self) -> Vec<(i64, f64)> {
+        let now = timezone::now(self.timezone);
+        let usual_year_ago = now.naive_local().date() - Duration::days(364);
+
+        vec![(now.timestamp(), self.filter_mood_sum(|daily_score| local_date(daily_score) >= usual_year_ago))]
+    }
+
+    pub fn thirty_days_moving_mood(&self) -> Vec<(i64, f64)> {
+        // from 29 days ago to now there are 30 calendar dates
+        // also we use dates to verify if daily score records fit into frame
+        // so 29-days frame covers 30 dates
+        self.timeframed_moving_mood_report(29, 0, 29)
     }
 
-    pub fn iterative_seven_days_mood(&self) -> Vec<(i64, i32)> {
-        let now = Local::now();
-        let today = now.with_timezone(now.offset()).date();
-        let beginning_of_next_day = today.succ().and_hms_nano(0, 0, 0, 0);
-        self.iterative_const_period_report(beginning_of_next_day, WEEK_SECONDS)
+    // Stats are keyed by the first day of the month.
+    pub fn monthly_stats(&self) -> BTreeMap<NaiveDate, ScoreStats> {
+        let mut monthly_stats = BTreeMap::new();
+        for (daily_score, value) in self.metric_values() {
+            monthly_stats.entry(beginning_of_month(local_date(daily_score))).or_insert_with(ScoreStats::default).add(value);
+        }
+        monthly_stats
     }
 
-    pub fn iterative_thirty_days_mood(&self) -> Vec<(i64, i32)> {
-        let now = Local::now();
-        let today = now.with_timezone(now.offset()).date();
-        let beginning_of_next_day = today.succ().and_hms_nano(0, 0, 0, 0);
-        self.iterative_const_period_report(beginning_of_next_day, DAY_SECONDS * 30)
+    pub fn weekday_stats(&self) -> Vec<(Weekday, ScoreStats)> {
+        let mut weekday_stats = [ScoreStats::default(); 7];
+        for (daily_score, value) in self.metric_values() {
+            let weekday = local_date(daily_score).weekday();
+            weekday_stats[weekday.num_days_from_monday() as usize].add(value);
+        }
+
+        let mut weekday = Weekday::Mon;
+        weekday_stats.iter().map(|stats| {
+            let weekday_stats = (weekday, *stats);
+            weekday = weekday.succ();
+            weekday_stats
+        }).collect()
     }
 
-    pub fn iterative_monthly_mood(&self) -> Vec<(i64, i32)> {
-        let now = Local::now();
-        let now_fixed_offset = now.with_timezone(now.offset());
-        let mut earliest_datetime = now_fixed_offset;
-        let beginning_of_current_month = Self::beginning_of_month(now_fixed_offset);
-        let mut monthly_scores: HashMap<i64, i32> = HashMap::new();
+    // Stats roll up to parent tags, a record counts once for a parent even if it has several of its children.
+    pub fn tag_stats(&self) -> BTreeMap<String, ScoreStats> {
+        let mut tag_stats = BTreeMap::new();
+        for (daily_score, value) in self.metric_values() {
+            let tags = daily_score.tags.iter()
+                .flat_map(|own_tag| tag::with_parents(own_tag))
+                .collect::<HashSet<&str>>();
+            for tag in tags {
+                tag_stats.entry(tag.to_string()).or_insert_with(ScoreStats::default).add(value);
+            }
+        }
+        tag_stats
+    }
 
-        for daily_score in self.daily_scores {
-            if daily_score.datetime >= beginning_of_current_month { continue }
-            if earliest_datetime > daily_score.datetime {
-                earliest_datetime = daily_score.datetime;
+    // Records without the attribute are left out.
+    pub fn attribute_stats(&self, key: &str) -> BTreeMap<String, ScoreStats> {
+        let mut attribute_stats = BTreeMap::new();
+        for (daily_score, value) in self.metric_values() {
+            if let Some(attribute_value) = daily_score.attributes.get(key) {
+                attribute_stats.entry(attribute_value.clone()).or_insert_with(ScoreStats::default).add(value);
             }
+        }
+        attribute_stats
+    }
 
-            // hash scores by 1st of corresponding score's month
-            let monthly_score_sum = monthly_scores.entry(Self::beginning_of_month(daily_score.datetime).timestamp()).or_insert(0);
-            *monthly_score_sum += daily_score.score as i32;
+    // Keyed by local hour, hours without records are left out.
+    pub fn hourly_stats(&self) -> BTreeMap<u32, ScoreStats> {
+        let mut hourly_stats = BTreeMap::new();
+        for (daily_score, value) in self.metric_values() {
+            hourly_stats.entry(daily_score.datetime.hour()).or_insert_with(ScoreStats::default).add(value);
         }
+        hourly_stats
+    }
 
-        let mut data: Vec<(i64, i32)> = Vec::new();
-        let mut beginning_of_month = beginning_of_current_month;
-        while beginning_of_month > Self::beginning_of_month(earliest_datetime) {
-            let beginning_of_previous_month = Self::beginning_of_previous_month(beginning_of_month);
-            let previous_month_timestamp = beginning_of_previous_month.timestamp();
-            // we need to get from hash by prev month 1st day timestamp (because it is easier to save it by
-            // score's month 1st day timestamp), and store data as this month's 1st day timestamp
-            data.push((beginning_of_month.timestamp(), *monthly_scores.get(&previous_month_timestamp).unwrap_or(&0)));
-            beginning_of_month = beginning_of_previous_month;
+    pub fn stats(&self) -> ScoreStats {
+        let mut stats = ScoreStats::default();
+        for (_, value) in self.metric_values() {
+            stats.add(value);
         }
-        data.reverse();
+        stats
+    }
 
-        data
+    // Impact is a difference between mean score of records with the tag and mean score of records without it.
+    pub fn tag_impacts(&self) -> Vec<(String, ScoreStats, f64)> {
+        let total_stats = self.stats();
+
+        let mut tag_impacts = self.tag_stats().into_iter()
+            .map(|(tag, stats)| {
+                let other_stats = ScoreStats { count: total_stats.count - stats.count, sum: total_stats.sum - stats.sum };
+                let impact = if other_stats.count == 0 { 0.0 } else { stats.mean() - other_stats.mean() };
+                (tag, stats, impact)
+            })
+            .collect::<Vec<(String, ScoreStats, f64)>>();
+        tag_impacts.sort_by(|a, b| b.2.abs().partial_cmp(&a.2.abs()).unwrap().then_with(|| a.0.cmp(&b.0)));
+
+        tag_impacts
     }
 
-    pub fn yearly_mood(&self) -> Vec<(i64, i32)> {
-        let now = Local::now();
-        let usual_year_ago = (now - Duration::days(364)).with_timezone(now.offset());
+    pub fn daily_sums(&self) -> BTreeMap<NaiveDate, f64> {
+        let mut daily_sums = BTreeMap::new();
+        for (daily_score, value) in self.metric_values() {
+            *daily_sums.entry(local_date(daily_score)).or_insert(0.0) += value;
+        }
+        daily_sums
+    }
 
-        vec![(now.timestamp(), self.filter_mood_sum(|daily_score| daily_score.datetime.date() >= usual_year_ago.date()))]
+    // Days without records and days with zero summary score break streaks.
+    pub fn streaks(&self) -> Vec<Streak> {
+        let mut streaks: Vec<Streak> = Vec::new();
+
+        for (date, sum) in self.daily_sums() {
+            let kind = match sum {
+                sum if sum > 0.0 => StreakKind::Positive,
+                sum if sum < 0.0 => StreakKind::Negative,
+                _ => continue,
+            };
+
+            match streaks.last_mut() {
+                Some(streak) if streak.kind == kind && streak.end.succ() == date => streak.end = date,
+                _ => streaks.push(Streak { kind, start: date, end: date }),
+            }
+        }
+
+        streaks
     }
 
-    pub fn thirty_days_moving_mood(&self) -> Vec<(i64, i32)> {
-        // from 29 days ago to now there are 30 calendar dates
-        // also we use dates to verify if daily score records fit into frame
-        // so 29-days frame covers 30 dates
-        self.timeframed_moving_mood_report(29, 0, 29)
+    // Records without the reported metric are left out along with the ones not matching the filter.
+    pub fn filtered_daily_scores(&self) -> impl Iterator<Item = &DailyScore> {
+        self.metric_values().map(|(daily_score, _)| daily_score)
+    }
+
+    fn metric_values(&self) -> impl Iterator<Item = (&DailyScore, f64)> {
+        self.daily_scores
+            .iter()
+            .filter(move |daily_score| self.filter.matches(daily_score))
+            .filter_map(move |daily_score| self.metric.value(daily_score).map(|value| (daily_score, value)))
     }
 
-    fn filter_mood_sum<F>(&self, filter_fn: F) -> i32
+    fn filter_mood_sum<F>(&self, filter_fn: F) -> f64
         where
             F: Fn(&&DailyScore) -> bool,
         {
-            self.daily_scores
-                .iter()
-                .filter(filter_fn)
-                .filter(|daily_score| self.tags.iter().all(|tag| daily_score.tags.contains(tag)))
-                .map(|daily_score| daily_score.score as i32)
+            self.metric_values()
+                .filter(|(daily_score, _)| filter_fn(daily_score))
+                .map(|(_, value)| value)
                 .sum()
         }
 
-    fn timeframed_moving_mood_report(&self, starts_at_days_ago: u32, ends_at_days_ago: u32, frame_size: u32) -> Vec<(i64, i32)> {
+    #[allow(clippy::reserve_after_initialization)]
+    fn timeframed_moving_mood_report(&self, starts_at_days_ago: u32, ends_at_days_ago: u32, frame_size: u32) -> Vec<(i64, f64)> {
         let mut hist = Vec::new();
         hist.reserve((starts_at_days_ago - ends_at_days_ago) as usize);
-        let now = Local::now();
-        let fixed_now = now.with_timezone(now.offset());
+        let now = timezone::now(self.timezone);
+        let today = now.naive_local().date();
 
         for frame_ends_at_days_ago in (ends_at_days_ago..=starts_at_days_ago).rev() {
-            let frame_ends_at_timestamp = Local::now().timestamp() - (frame_ends_at_days_ago as i64 * DAY_SECONDS);
-            let frame_end = fixed_now - Duration::days(frame_ends_at_days_ago as i64);
+            let frame_ends_at_timestamp = now.timestamp() - (frame_ends_at_days_ago as i64 * DAY_SECONDS);
+            let frame_end = today - Duration::days(frame_ends_at_days_ago as i64);
             let frame_start = frame_end - Duration::days(frame_size as i64);
             let sum = self
                 .filter_mood_sum(|daily_score| {
-                    daily_score.datetime.date() >= frame_start.date() && daily_score.datetime.date() <= frame_end.date()
+                    local_date(daily_score) >= frame_start && local_date(daily_score) <= frame_end
                 });
             hist.push((frame_ends_at_timestamp, sum));
         }
@@ -126,66 +260,102 @@ impl<'a> MoodReport<'a> {
         hist
     }
 
-    fn iterative_const_period_report(&self, report_ends_at: DateTime<FixedOffset>, period: i64) -> Vec<(i64, i32)> {
-        let mut data = Vec::new();
-        let filtered_daily_scores = self.daily_scores
-            .iter()
-            .filter(|daily_score| self.tags.iter().all(|tag| daily_score.tags.contains(tag)))
-            .filter(|daily_score| daily_score.datetime < report_ends_at);
-
-        for daily_score in filtered_daily_scores {
-            let seconds_before_report_end = report_ends_at.timestamp() - daily_score.datetime.timestamp();
-            // score at (report end - period duration) belongs to this period, so we have to subtract 1 second,
-            // otherwise it will fall into previous period's report.
-            let i_i64 = (seconds_before_report_end - 1) / period;
-
-            // drop anything in the future or beyod platform's max len periods ago
-            let i_convert = usize::try_from(i_i64);
-            if i_convert.is_err() { continue };
-
-            let i = i_convert.unwrap();
-            let seconds_to_next_period = seconds_before_report_end % period;
-
-            // potentially we can reserve data space before loop, but first record usually is the oldest,
-            // so resize will happen only once in most of the cases
-            if i >= data.len() {
-                let mut len = data.len() as i64;
-                data.resize_with(i + 1, || { len += 1; (report_ends_at.timestamp() - (len - 1) * period, 0) });
+    // Periods of `period_days` dates going back from the report end date, which is not included.
+    // Each period is timestamped with the start of the day it ends at, so periods of the same
+    // number of days can differ by an hour in length if DST changes within them.
+    fn iterative_const_period_report(&self, report_end: NaiveDate, period_days: i64) -> Vec<(i64, f64)> {
+        let mut sums: Vec<f64> = Vec::new();
+
+        for (daily_score, value) in self.metric_values() {
+            let days_before_report_end = (report_end - local_date(daily_score)).num_days();
+            // drop anything at the report end date or later
+            if days_before_report_end < 1 { continue };
+
+            let i = ((days_before_report_end - 1) / period_days) as usize;
+            // first record usually is the oldest, so resize will happen only once in most of the cases
+            if i >= sums.len() {
+                sums.resize(i + 1, 0.0);
+            }
+            sums[i] += value;
+        }
+
+        sums.into_iter()
+            .enumerate()
+            .rev()
+            .map(|(i, sum)| {
+                let period_end = report_end - Duration::days(i as i64 * period_days);
+                (timezone::start_of_day(self.timezone, period_end).timestamp(), sum)
+            })
+            .collect()
+    }
+
+    // Months from the one of the earliest record up to the current one, which is not included,
+    // each timestamped with the start of the next month.
+    fn iterative_monthly_report(&self, current_month: NaiveDate) -> Vec<(i64, f64)> {
+        let mut monthly_sums: BTreeMap<NaiveDate, f64> = BTreeMap::new();
+        for (daily_score, value) in self.metric_values() {
+            let month = beginning_of_month(local_date(daily_score));
+            if month < current_month {
+                *monthly_sums.entry(month).or_insert(0.0) += value;
             }
+        }
 
-            data[i] = (daily_score.datetime.timestamp() + seconds_to_next_period, data[i].1 + daily_score.score as i32);
+        let mut data = Vec::new();
+        let mut month = match monthly_sums.keys().next() {
+            Some(month) => *month,
+            None => return data,
+        };
+        while month < current_month {
+            let next_month = beginning_of_next_month(month);
+            data.push((timezone::start_of_day(self.timezone, next_month).timestamp(), monthly_sums.get(&month).copied().unwrap_or(0.0)));
+            month = next_month;
         }
-        data.reverse();
+
         data
     }
+}
 
-    fn beginning_of_month(datetime: DateTime<FixedOffset>) -> DateTime<FixedOffset> {
-        datetime.date().and_hms_nano(0, 0, 0, 0) - Duration::days(datetime.day0().into())
-    }
+fn local_date(daily_score: &DailyScore) -> NaiveDate {
+    daily_score.datetime.naive_local().date()
+}
+
+fn beginning_of_month(date: NaiveDate) -> NaiveDate {
+    NaiveDate::from_ymd(date.year(), date.month(), 1)
+}
 
-    fn beginning_of_previous_month(datetime: DateTime<FixedOffset>) -> DateTime<FixedOffset> {
-        Self::beginning_of_month(datetime.date().pred().and_hms(0, 0, 0))
+fn beginning_of_next_month(date: NaiveDate) -> NaiveDate {
+    if date.month() == 12 {
+        NaiveDate::from_ymd(date.year() + 1, 1, 1)
+    } else {
+        NaiveDate::from_ymd(date.year(), date.month() + 1, 1)
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use chrono::{DateTime, FixedOffset, TimeZone, Utc};
+    use chrono_tz::Europe;
+    use std::collections::HashSet;
+
     use super::*;
+    use crate::timezone::ReportDays;
 
     #[test]
     fn consumes_scores() {
         let scores = vec![DailyScore::new(), DailyScore::new()];
-        let mood_report = MoodReport { daily_scores: &scores, tags: &HashSet::new() };
+        let mood_report = MoodReport { daily_scores: &scores, filter: &Filter::default(), metric: &Metric::Score, timezone: None };
 
         assert_eq!(mood_report.len(), 2);
     }
 
     #[test]
     fn iterative_weekly_mood() {
-        let daily_score = DailyScore::with_score(-10);
+        let daily_score = DailyScore { datetime: now_with_fixed_offset(), ..DailyScore::with_score(-10) };
         let last_week_daily_score =
             DailyScore {
-                score: 2,
+                score: 2.0,
+                metrics: Default::default(),
+                attributes: Default::default(),
                 tags: HashSet::new(),
                 comment: None,
                 datetime: last_monday() - Duration::days(1)
@@ -193,7 +363,9 @@ mod tests {
 
         let another_last_week_daily_score =
             DailyScore {
-                score: 3,
+                score: 3.0,
+                metrics: Default::default(),
+                attributes: Default::default(),
                 tags: HashSet::new(),
                 comment: None,
                 datetime: last_monday() - Duration::days(2)
@@ -201,7 +373,9 @@ mod tests {
 
         let old_daily_score =
             DailyScore {
-                score: 4,
+                score: 4.0,
+                metrics: Default::default(),
+                attributes: Default::default(),
                 tags: HashSet::new(),
                 comment: None,
                 datetime: last_monday() - Duration::days(15)
@@ -209,29 +383,56 @@ mod tests {
 
         let mood_report =
             MoodReport {
-                daily_scores: &vec![
+                daily_scores: &[
                     another_last_week_daily_score,
                     daily_score,
                     old_daily_score,
                     last_week_daily_score,
                 ],
-                tags: &HashSet::new(),
+                filter: &Filter::default(),
+                metric: &Metric::Score,
+                timezone: None,
             };
 
-        let previous_monday = last_monday() - Duration::days(7);
-        let pre_previous_monday = last_monday() - Duration::days(14);
+        let monday_weeks_ago = |weeks| timezone::start_of_day(None, last_monday().naive_local().date() - Duration::weeks(weeks)).timestamp();
+
+        assert_eq!(mood_report.iterative_weekly_mood(Weekday::Mon),
+            vec![(monday_weeks_ago(2), 4.0), (monday_weeks_ago(1), 0.0), (monday_weeks_ago(0), 5.0)]
+        )
+    }
+
+    #[test]
+    fn iterative_weekly_mood_with_week_start() {
+        let today = timezone::today(None);
+        let days_since_sunday = today.weekday().num_days_from_sunday();
+        let last_sunday = timezone::start_of_day(None, today - Duration::days(days_since_sunday.into()));
+        let daily_score_at = |score, datetime| DailyScore { score, metrics: Default::default(), attributes: Default::default(), tags: HashSet::new(), comment: None, datetime };
+
+        let mood_report =
+            MoodReport {
+                daily_scores: &[
+                    daily_score_at(2.0, last_sunday - Duration::days(1)),
+                    daily_score_at(3.0, last_sunday - Duration::days(8)),
+                    daily_score_at(-10.0, last_sunday),
+                ],
+                filter: &Filter::default(),
+                metric: &Metric::Score,
+                timezone: None,
+            };
 
-        assert_eq!(mood_report.iterative_weekly_mood(),
-            vec![(pre_previous_monday.timestamp(), 4), (previous_monday.timestamp(), 0), (last_monday().timestamp(), 5)]
+        assert_eq!(mood_report.iterative_weekly_mood(Weekday::Sun),
+            vec![(timezone::start_of_day(None, last_sunday.date().naive_local() - Duration::days(7)).timestamp(), 3.0), (last_sunday.timestamp(), 2.0)]
         )
     }
 
     #[test]
     fn iterative_seven_days_mood() {
-        let daily_score = DailyScore::with_score(-10);
+        let daily_score = DailyScore { datetime: now_with_fixed_offset(), ..DailyScore::with_score(-10) };
         let last_week_daily_score =
             DailyScore {
-                score: 2,
+                score: 2.0,
+                metrics: Default::default(),
+                attributes: Default::default(),
                 tags: HashSet::new(),
                 comment: None,
                 datetime: daily_score.datetime - Duration::days(1)
@@ -239,7 +440,9 @@ mod tests {
 
         let another_last_week_daily_score =
             DailyScore {
-                score: 3,
+                score: 3.0,
+                metrics: Default::default(),
+                attributes: Default::default(),
                 tags: HashSet::new(),
                 comment: None,
                 datetime: daily_score.datetime - Duration::days(2)
@@ -247,7 +450,9 @@ mod tests {
 
         let old_daily_score =
             DailyScore {
-                score: 4,
+                score: 4.0,
+                metrics: Default::default(),
+                attributes: Default::default(),
                 tags: HashSet::new(),
                 comment: None,
                 datetime: daily_score.datetime - Duration::days(15)
@@ -255,32 +460,35 @@ mod tests {
 
         let mood_report =
             MoodReport {
-                daily_scores: &vec![
+                daily_scores: &[
                     another_last_week_daily_score,
                     old_daily_score,
                     last_week_daily_score,
                     daily_score,
                 ],
-                tags: &HashSet::new(),
+                filter: &Filter::default(),
+                metric: &Metric::Score,
+                timezone: None,
             };
 
         assert_eq!(mood_report.iterative_seven_days_mood().len(), 3);
 
-        let report_timestamp = mood_report.iterative_seven_days_mood()[2].0;
-        let previous_period_end = report_timestamp - WEEK_SECONDS;
-        let pre_previous_period_end = report_timestamp - 2 * WEEK_SECONDS;
+        let report_end = timezone::today(None).succ();
+        let period_end = |weeks_ago| timezone::start_of_day(None, report_end - Duration::weeks(weeks_ago)).timestamp();
 
         assert_eq!(mood_report.iterative_seven_days_mood(),
-            vec![(pre_previous_period_end, 4), (previous_period_end, 0), (report_timestamp, -5)]
+            vec![(period_end(2), 4.0), (period_end(1), 0.0), (period_end(0), -5.0)]
         )
     }
 
     #[test]
     fn iterative_thirty_days_mood() {
-        let daily_score = DailyScore::with_score(-10);
+        let daily_score = DailyScore { datetime: now_with_fixed_offset(), ..DailyScore::with_score(-10) };
         let last_month_daily_score =
             DailyScore {
-                score: 3,
+                score: 3.0,
+                metrics: Default::default(),
+                attributes: Default::default(),
                 tags: HashSet::new(),
                 comment: None,
                 datetime: daily_score.datetime - Duration::days(20)
@@ -288,7 +496,9 @@ mod tests {
 
         let old_daily_score =
             DailyScore {
-                score: 4,
+                score: 4.0,
+                metrics: Default::default(),
+                attributes: Default::default(),
                 tags: HashSet::new(),
                 comment: None,
                 datetime: daily_score.datetime - Duration::days(61)
@@ -296,31 +506,34 @@ mod tests {
 
         let mood_report =
             MoodReport {
-                daily_scores: &vec![
+                daily_scores: &[
                     old_daily_score,
                     last_month_daily_score,
                     daily_score,
                 ],
-                tags: &HashSet::new(),
+                filter: &Filter::default(),
+                metric: &Metric::Score,
+                timezone: None,
             };
 
         assert_eq!(mood_report.iterative_thirty_days_mood().len(), 3);
 
-        let report_timestamp = mood_report.iterative_thirty_days_mood()[2].0;
-        let previous_period_end = report_timestamp - DAY_SECONDS * 30;
-        let pre_previous_period_end = report_timestamp - 2 * DAY_SECONDS * 30;
+        let report_end = timezone::today(None).succ();
+        let period_end = |periods_ago: i64| timezone::start_of_day(None, report_end - Duration::days(30 * periods_ago)).timestamp();
 
         assert_eq!(mood_report.iterative_thirty_days_mood(),
-            vec![(pre_previous_period_end, 4), (previous_period_end, 0), (report_timestamp, -7)]
+            vec![(period_end(2), 4.0), (period_end(1), 0.0), (period_end(0), -7.0)]
         )
     }
 
     #[test]
     fn iterative_monthly_mood() {
-        let daily_score = DailyScore::with_score(-10);
+        let daily_score = DailyScore { datetime: now_with_fixed_offset(), ..DailyScore::with_score(-10) };
         let last_month_daily_score =
             DailyScore {
-                score: 2,
+                score: 2.0,
+                metrics: Default::default(),
+                attributes: Default::default(),
                 tags: HashSet::new(),
                 comment: None,
                 datetime: beginning_of_month() - Duration::days(1)
@@ -328,7 +541,9 @@ mod tests {
 
         let another_last_month_daily_score =
             DailyScore {
-                score: 3,
+                score: 3.0,
+                metrics: Default::default(),
+                attributes: Default::default(),
                 tags: HashSet::new(),
                 comment: None,
                 datetime: beginning_of_month() - Duration::days(20)
@@ -336,7 +551,9 @@ mod tests {
 
         let old_daily_score =
             DailyScore {
-                score: 4,
+                score: 4.0,
+                metrics: Default::default(),
+                attributes: Default::default(),
                 tags: HashSet::new(),
                 comment: None,
                 datetime: beginning_of_month() - Duration::days(35)
@@ -344,17 +561,19 @@ mod tests {
 
         let mood_report =
             MoodReport {
-                daily_scores: &vec![
+                daily_scores: &[
                     another_last_month_daily_score,
                     old_daily_score,
                     last_month_daily_score,
                     daily_score,
                 ],
-                tags: &HashSet::new(),
+                filter: &Filter::default(),
+                metric: &Metric::Score,
+                timezone: None,
             };
 
         assert_eq!(mood_report.iterative_monthly_mood(),
-            vec![(beginning_of_previous_month().timestamp(), 4), (beginning_of_month().timestamp(), 5)]
+            vec![(beginning_of_previous_month().timestamp(), 4.0), (beginning_of_month().timestamp(), 5.0)]
         )
     }
 
@@ -364,7 +583,9 @@ mod tests {
         let another_daily_score = DailyScore::with_score(2);
         let old_daily_score =
             DailyScore {
-                score: 5,
+                score: 5.0,
+                metrics: Default::default(),
+                attributes: Default::default(),
                 tags: HashSet::new(),
                 comment: None,
                 datetime: now_with_fixed_offset() - Duration::days(40)
@@ -372,14 +593,14 @@ mod tests {
 
         let mood_report =
             MoodReport {
-                daily_scores: &vec![daily_score, another_daily_score, old_daily_score],
-                tags: &HashSet::new(),
+                daily_scores: &[daily_score, another_daily_score, old_daily_score],
+                filter: &Filter::default(),
+                metric: &Metric::Score,
+                timezone: None,
             };
 
 
-        assert!(
-            matches!(mood_report.thirty_days_mood()[..], [(_, 3)])
-        );
+        assert_eq!(mood_report.thirty_days_mood().iter().map(|val| val.1).collect::<Vec<f64>>(), vec![3.0]);
     }
 
     #[test]
@@ -389,7 +610,9 @@ mod tests {
 
         let daily_score =
             DailyScore {
-                score: 1,
+                score: 1.0,
+                metrics: Default::default(),
+                attributes: Default::default(),
                 tags: tag2.clone(),
                 comment: None,
                 datetime: now_with_fixed_offset(),
@@ -397,7 +620,9 @@ mod tests {
 
         let another_daily_score =
             DailyScore {
-                score: 2,
+                score: 2.0,
+                metrics: Default::default(),
+                attributes: Default::default(),
                 tags: tag.clone(),
                 comment: None,
                 datetime: now_with_fixed_offset() - Duration::days(20)
@@ -405,7 +630,9 @@ mod tests {
 
         let old_daily_score =
             DailyScore {
-                score: 5,
+                score: 5.0,
+                metrics: Default::default(),
+                attributes: Default::default(),
                 tags: tag.clone(),
                 comment: None,
                 datetime: now_with_fixed_offset() - Duration::days(40)
@@ -416,63 +643,71 @@ mod tests {
         let tag_mood_report =
             MoodReport {
                 daily_scores: &daily_scores,
-                tags: &tag,
+                filter: &Filter::with_tags(tag.clone()),
+                metric: &Metric::Score,
+                timezone: None,
             };
 
         let multitag_mood_report =
             MoodReport {
                 daily_scores: &daily_scores,
-                tags: &vec!["tag".to_string(), "tag2".to_string()].into_iter().collect(),
+                filter: &Filter::with_tags(vec!["tag".to_string(), "tag2".to_string()].into_iter().collect()),
+                metric: &Metric::Score,
+                timezone: None,
             };
 
-        assert!(
-            matches!(tag_mood_report.thirty_days_mood()[..], [(_, 2)])
-        );
-        assert!(
-            matches!(multitag_mood_report.thirty_days_mood()[..], [(_, 0)])
-        );
+        assert_eq!(tag_mood_report.thirty_days_mood().iter().map(|val| val.1).collect::<Vec<f64>>(), vec![2.0]);
+        assert_eq!(multitag_mood_report.thirty_days_mood().iter().map(|val| val.1).collect::<Vec<f64>>(), vec![0.0]);
     }
 
     #[test]
     fn thirty_days_moving_mood() {
-        let today_daily_score = DailyScore::with_score(1);
+        let today_daily_score = DailyScore { datetime: now_with_fixed_offset(), ..DailyScore::with_score(1) };
         let beginning_of_month_daily_score =
             DailyScore {
-                score: -1,
+                score: -1.0,
+                metrics: Default::default(),
+                attributes: Default::default(),
                 tags: HashSet::new(),
                 comment: None,
                 datetime: now_with_fixed_offset() - Duration::days(25) - Duration::minutes(1)
             };
         let fifty_days_ago_daily_score =
             DailyScore {
-                score: 2,
+                score: 2.0,
+                metrics: Default::default(),
+                attributes: Default::default(),
                 tags: HashSet::new(),
                 comment: None,
                 datetime: now_with_fixed_offset() - Duration::days(50) + Duration::minutes(1)
             };
         let ninty_days_ago_daily_score =
             DailyScore {
-                score: 20,
+                score: 20.0,
+                metrics: Default::default(),
+                attributes: Default::default(),
                 tags: HashSet::new(),
                 comment: None,
                 datetime: now_with_fixed_offset() - Duration::days(90)
             };
 
         let mood_report = MoodReport {
-            daily_scores: &vec![
+            daily_scores: &[
                     beginning_of_month_daily_score,
                     fifty_days_ago_daily_score,
                     ninty_days_ago_daily_score,
                     today_daily_score
                 ],
-            tags: &HashSet::new(),
+            filter: &Filter::default(),
+            metric: &Metric::Score,
+            timezone: None,
         };
 
-        assert_eq!(mood_report.thirty_days_moving_mood().iter().map(|val| val.1).collect::<Vec<i32>>(),
+        assert_eq!(mood_report.thirty_days_moving_mood().iter().map(|val| val.1).collect::<Vec<f64>>(),
             vec![
-                2, 2, 2, 2, 1, 1, 1, 1, 1, -1,
-                -1, -1, -1, -1, -1, -1, -1, -1, -1, -1,
-                -1, -1, -1, -1, -1, -1, -1, -1, -1, 0,
+                2.0, 2.0, 2.0, 2.0, 1.0, 1.0, 1.0, 1.0, 1.0, -1.0,
+                -1.0, -1.0, -1.0, -1.0, -1.0, -1.0, -1.0, -1.0, -1.0, -1.0,
+                -1.0, -1.0, -1.0, -1.0, -1.0, -1.0, -1.0, -1.0, -1.0, 0.0,
             ]
         );
     }
@@ -486,7 +721,9 @@ mod tests {
         let another_daily_score = DailyScore::with_score(2);
         let forty_days_ago_score =
             DailyScore {
-                score: 5,
+                score: 5.0,
+                metrics: Default::default(),
+                attributes: Default::default(),
                 datetime: now_with_fixed_offset()  - Duration::days(40),
                 tags: no_tags.clone(),
                 comment: None,
@@ -494,7 +731,9 @@ mod tests {
 
         let old_score =
             DailyScore {
-                score: -4,
+                score: -4.0,
+                metrics: Default::default(),
+                attributes: Default::default(),
                 datetime: now_with_fixed_offset() - Duration::weeks(55),
                 tags: tag_tags.clone(),
                 comment: None,
@@ -502,34 +741,189 @@ mod tests {
 
         let daily_scores = vec![daily_score, another_daily_score, forty_days_ago_score, old_score];
 
-        let mood_report = MoodReport { daily_scores: &daily_scores, tags: &no_tags };
-        let tagged_mood_report = MoodReport { daily_scores: &daily_scores, tags: &tag_tags };
+        let mood_report = MoodReport { daily_scores: &daily_scores, filter: &Filter::default(), metric: &Metric::Score, timezone: None };
+        let tagged_mood_report = MoodReport { daily_scores: &daily_scores, filter: &Filter::with_tags(tag_tags.clone()), metric: &Metric::Score, timezone: None };
 
-        assert!(
-            matches!(mood_report.yearly_mood()[..], [(_, 8)])
-        );
-        assert!(
-            matches!(tagged_mood_report.yearly_mood()[..], [(_, 0)])
-        );
+        assert_eq!(mood_report.yearly_mood().iter().map(|val| val.1).collect::<Vec<f64>>(), vec![8.0]);
+        assert_eq!(tagged_mood_report.yearly_mood().iter().map(|val| val.1).collect::<Vec<f64>>(), vec![0.0]);
+    }
+
+    #[test]
+    fn summary_stats() {
+        let daily_score_at = |score, tags: &[&str], y, m, d| DailyScore {
+            score,
+            metrics: Default::default(),
+            attributes: Default::default(),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            comment: None,
+            datetime: FixedOffset::east(3 * 3600).ymd(y, m, d).and_hms(23, 0, 0),
+        };
+
+        let daily_scores = vec![
+            daily_score_at(1.0, &["run"], 2020, 1, 6),
+            daily_score_at(2.0, &["run", "work"], 2020, 1, 7),
+            daily_score_at(-3.0, &["work"], 2020, 1, 8),
+            daily_score_at(1.0, &[], 2020, 2, 3),
+        ];
+        let mood_report = MoodReport { daily_scores: &daily_scores, filter: &Filter::default(), metric: &Metric::Score, timezone: None };
+
+        assert_eq!(mood_report.monthly_stats().into_iter().collect::<Vec<(NaiveDate, ScoreStats)>>(), vec![
+            (NaiveDate::from_ymd(2020, 1, 1), ScoreStats { count: 3, sum: 0.0 }),
+            (NaiveDate::from_ymd(2020, 2, 1), ScoreStats { count: 1, sum: 1.0 }),
+        ]);
+
+        let weekday_stats = mood_report.weekday_stats();
+        assert_eq!(weekday_stats.len(), 7);
+        assert_eq!(weekday_stats[0], (Weekday::Mon, ScoreStats { count: 2, sum: 2.0 }));
+        assert_eq!(weekday_stats[2], (Weekday::Wed, ScoreStats { count: 1, sum: -3.0 }));
+        assert_eq!(weekday_stats[6], (Weekday::Sun, ScoreStats::default()));
+
+        assert_eq!(mood_report.stats(), ScoreStats { count: 4, sum: 1.0 });
+
+        let tag_stats = mood_report.tag_stats();
+        assert_eq!(tag_stats["run"], ScoreStats { count: 2, sum: 3.0 });
+        assert_eq!(tag_stats["work"].mean(), -0.5);
+
+        assert_eq!(mood_report.tag_impacts(), vec![
+            ("run".to_string(), ScoreStats { count: 2, sum: 3.0 }, 2.5),
+            ("work".to_string(), ScoreStats { count: 2, sum: -1.0 }, -1.5),
+        ]);
+
+        assert_eq!(mood_report.streaks(), vec![
+            Streak { kind: StreakKind::Positive, start: NaiveDate::from_ymd(2020, 1, 6), end: NaiveDate::from_ymd(2020, 1, 7) },
+            Streak { kind: StreakKind::Negative, start: NaiveDate::from_ymd(2020, 1, 8), end: NaiveDate::from_ymd(2020, 1, 8) },
+            Streak { kind: StreakKind::Positive, start: NaiveDate::from_ymd(2020, 2, 3), end: NaiveDate::from_ymd(2020, 2, 3) },
+        ]);
+        assert_eq!(mood_report.streaks()[0].days(), 2);
+
+        let mut attributed_daily_scores = daily_scores.clone();
+        attributed_daily_scores[0].attributes.insert("weather".to_string(), "sun".to_string());
+        attributed_daily_scores[1].attributes.insert("weather".to_string(), "rain".to_string());
+        attributed_daily_scores[2].attributes.insert("weather".to_string(), "rain".to_string());
+        let attributed_mood_report = MoodReport { daily_scores: &attributed_daily_scores, filter: &Filter::default(), metric: &Metric::Score, timezone: None };
+        assert_eq!(attributed_mood_report.attribute_stats("weather").into_iter().collect::<Vec<(String, ScoreStats)>>(), vec![
+            ("rain".to_string(), ScoreStats { count: 2, sum: -1.0 }),
+            ("sun".to_string(), ScoreStats { count: 1, sum: 1.0 }),
+        ]);
+        assert!(attributed_mood_report.attribute_stats("location").is_empty());
+
+        let filter = Filter::with_tags(vec!["work".to_string()].into_iter().collect());
+        let tagged_mood_report = MoodReport { daily_scores: &daily_scores, filter: &filter, metric: &Metric::Score, timezone: None };
+        assert_eq!(tagged_mood_report.tag_stats().keys().collect::<Vec<&String>>(), vec!["run", "work"]);
+        assert_eq!(tagged_mood_report.streaks().len(), 2);
+    }
+
+    #[test]
+    fn hierarchical_tags() {
+        let daily_score = |score, tags: &[&str]| {
+            let mut daily_score = DailyScore::with_score(score);
+            daily_score.tags = tags.iter().map(|tag| tag.to_string()).collect();
+            daily_score
+        };
+        let daily_scores = vec![
+            daily_score(2, &["sport/running", "sport/swimming"]),
+            daily_score(1, &["sport/running/trail"]),
+            daily_score(-1, &["work"]),
+        ];
+        let mood_report = MoodReport { daily_scores: &daily_scores, filter: &Filter::default(), metric: &Metric::Score, timezone: None };
+
+        let tag_stats = mood_report.tag_stats();
+        assert_eq!(tag_stats.keys().collect::<Vec<&String>>(),
+            vec!["sport", "sport/running", "sport/running/trail", "sport/swimming", "work"]);
+        assert_eq!(tag_stats["sport"], ScoreStats { count: 2, sum: 3.0 });
+        assert_eq!(tag_stats["sport/running"], ScoreStats { count: 2, sum: 3.0 });
+        assert_eq!(tag_stats["sport/swimming"], ScoreStats { count: 1, sum: 2.0 });
+
+        let filter = Filter::with_tags(vec!["sport".to_string()].into_iter().collect());
+        let sport_mood_report = MoodReport { daily_scores: &daily_scores, filter: &filter, metric: &Metric::Score, timezone: None };
+        assert_eq!(sport_mood_report.stats(), ScoreStats { count: 2, sum: 3.0 });
+    }
+
+    #[test]
+    fn hourly_stats() {
+        let daily_score_at = |hour, score| {
+            let mut daily_score = DailyScore::with_score(score);
+            daily_score.datetime = FixedOffset::east(3600).ymd(2020, 1, 1).and_hms(hour, 30, 0);
+            daily_score
+        };
+        let daily_scores = vec![daily_score_at(8, -1.0), daily_score_at(20, 1.5), daily_score_at(8, 0.5)];
+        let mood_report = MoodReport { daily_scores: &daily_scores, filter: &Filter::default(), metric: &Metric::Score, timezone: None };
+
+        assert_eq!(mood_report.hourly_stats().into_iter().collect::<Vec<(u32, ScoreStats)>>(), vec![
+            (8, ScoreStats { count: 2, sum: -0.5 }),
+            (20, ScoreStats { count: 1, sum: 1.5 }),
+        ]);
+    }
+
+    #[test]
+    fn periods_across_dst() {
+        let berlin = Some(Timezone::Zone(Europe::Berlin));
+        let record_at = |score, datetime| DailyScore { score, datetime, ..DailyScore::with_score(0) };
+        let daily_scores = vec![
+            // the last hour before clocks are moved forward, and the first one after
+            record_at(1.0, FixedOffset::east(3600).ymd(2021, 3, 28).and_hms(1, 30, 0)),
+            record_at(2.0, FixedOffset::east(2 * 3600).ymd(2021, 3, 28).and_hms(3, 30, 0)),
+            // a late evening record of the previous day
+            record_at(4.0, FixedOffset::east(3600).ymd(2021, 3, 27).and_hms(23, 30, 0)),
+            // made on another continent, it is already the next day in Berlin
+            record_at(8.0, FixedOffset::west(5 * 3600).ymd(2021, 3, 28).and_hms(22, 0, 0)),
+        ];
+        let filter = Filter::default();
+        let mood_report = MoodReport { daily_scores: &daily_scores, filter: &filter, metric: &Metric::Score, timezone: berlin };
+        let start_of_day = |day| timezone::start_of_day(berlin, NaiveDate::from_ymd(2021, 3, day)).timestamp();
+
+        // records are bucketed by their own dates, and the 23 hours long day is reported as one
+        assert_eq!(mood_report.iterative_const_period_report(NaiveDate::from_ymd(2021, 3, 30), 1),
+            vec![(start_of_day(28), 4.0), (start_of_day(29), 11.0), (start_of_day(30), 0.0)]);
+        assert_eq!(start_of_day(29) - start_of_day(28), 23 * 3600);
+
+        let zone_daily_scores = timezone::apply(ReportDays::Timezone, berlin, daily_scores.clone());
+        let zone_mood_report = MoodReport { daily_scores: &zone_daily_scores, ..mood_report };
+        assert_eq!(zone_mood_report.iterative_const_period_report(NaiveDate::from_ymd(2021, 3, 30), 1),
+            vec![(start_of_day(28), 4.0), (start_of_day(29), 3.0), (start_of_day(30), 8.0)]);
+    }
+
+    #[test]
+    fn months_across_dst() {
+        let berlin = Some(Timezone::Zone(Europe::Berlin));
+        let daily_scores = vec![
+            DailyScore { datetime: FixedOffset::east(2 * 3600).ymd(2021, 8, 31).and_hms(23, 30, 0), ..DailyScore::with_score(1) },
+            DailyScore { datetime: FixedOffset::east(3600).ymd(2021, 10, 31).and_hms(23, 30, 0), ..DailyScore::with_score(2) },
+            DailyScore { datetime: FixedOffset::east(3600).ymd(2021, 11, 1).and_hms(0, 30, 0), ..DailyScore::with_score(4) },
+        ];
+        let filter = Filter::default();
+        let mood_report = MoodReport { daily_scores: &daily_scores, filter: &filter, metric: &Metric::Score, timezone: berlin };
+        let start_of_month = |year, month| timezone::start_of_day(berlin, NaiveDate::from_ymd(year, month, 1)).to_rfc3339();
+
+        let months = mood_report.iterative_monthly_report(NaiveDate::from_ymd(2022, 1, 1)).into_iter()
+            .map(|(timestamp, value)| (timezone::convert(berlin, &Utc.timestamp(timestamp, 0)).to_rfc3339(), value))
+            .collect::<Vec<(String, f64)>>();
+        assert_eq!(months, vec![
+            (start_of_month(2021, 9), 1.0),
+            (start_of_month(2021, 10), 0.0),
+            (start_of_month(2021, 11), 2.0),
+            (start_of_month(2021, 12), 4.0),
+            (start_of_month(2022, 1), 0.0),
+        ]);
+        assert_eq!(start_of_month(2021, 11), "2021-11-01T00:00:00+01:00");
+        assert_eq!(start_of_month(2021, 10), "2021-10-01T00:00:00+02:00");
     }
 
     fn now_with_fixed_offset() -> DateTime<FixedOffset> {
-        let now = Local::now();
-        now.with_timezone(now.offset())
+        timezone::now(None)
     }
 
     fn last_monday() -> DateTime<FixedOffset> {
-        let today = now_with_fixed_offset().date();
-        today.and_hms_nano(0, 0, 0, 0) - Duration::days(today.weekday().num_days_from_monday().into())
+        let today = timezone::today(None);
+        timezone::start_of_day(None, today - Duration::days(today.weekday().num_days_from_monday().into()))
     }
 
     fn beginning_of_month() -> DateTime<FixedOffset> {
-        let today = now_with_fixed_offset().date();
-        today.and_hms_nano(0, 0, 0, 0) - Duration::days(today.day0().into())
+        timezone::start_of_day(None, super::beginning_of_month(timezone::today(None)))
     }
 
     fn beginning_of_previous_month() -> DateTime<FixedOffset> {
-        let last_day_of_previos_month = beginning_of_month() - Duration::days(1);
-        last_day_of_previos_month - Duration::days(last_day_of_previos_month.day0().into())
+        let last_day_of_previous_month = timezone::today(None).with_day(1).unwrap().pred();
+        timezone::start_of_day(None, super::beginning_of_month(last_day_of_previous_month))
     }
 }
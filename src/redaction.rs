@@ -0,0 +1,116 @@
+use chrono::{FixedOffset, TimeZone};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+
+use crate::daily_score::DailyScore;
+
+const TAG_HASH_LENGTH: usize = 16;
+
+// Strips personal details from daily scores before they leave the journal.
+#[derive(Default)]
+pub struct Redaction {
+    pub drop_comments: bool,
+    pub tag_allowlist: Option<HashSet<String>>,
+    pub tag_salt: Option<String>,
+    pub truncate_time: bool,
+}
+
+impl Redaction {
+    pub fn apply(&self, mut daily_score: DailyScore) -> DailyScore {
+        if self.drop_comments {
+            daily_score.comment = None;
+        }
+
+        if let Some(tag_allowlist) = &self.tag_allowlist {
+            daily_score.tags.retain(|tag| tag_allowlist.contains(tag));
+            // attributes are allowed by key
+            daily_score.attributes.retain(|key, _| tag_allowlist.contains(key));
+        }
+
+        if let Some(tag_salt) = &self.tag_salt {
+            daily_score.tags = daily_score.tags.iter().map(|tag| Self::hash_tag(tag_salt, tag)).collect();
+            // keys are kept to group by, only values are hashed
+            for value in daily_score.attributes.values_mut() {
+                *value = Self::hash_tag(tag_salt, value);
+            }
+        }
+
+        // the offset tells where the record was made, so the record date is kept at UTC midnight
+        if self.truncate_time {
+            let date = daily_score.datetime.naive_local().date();
+            daily_score.datetime = FixedOffset::east(0).from_utc_datetime(&date.and_hms(0, 0, 0));
+        }
+
+        daily_score
+    }
+
+    // The same tag with the same salt is always hashed into the same string, so tag stats
+    // are preserved, but tag names can't be recovered without knowing the salt.
+    // The salt is prefixed by its length, so `ab` + `c` and `a` + `bc` are hashed differently.
+    fn hash_tag(salt: &str, tag: &str) -> String {
+        let digest = Sha256::new()
+            .chain_update((salt.len() as u64).to_be_bytes())
+            .chain_update(salt.as_bytes())
+            .chain_update(tag.as_bytes())
+            .finalize();
+
+        digest.iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<String>()[..TAG_HASH_LENGTH]
+            .to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn daily_score() -> DailyScore {
+        DailyScore {
+            score: 1.0,
+            metrics: Default::default(),
+            attributes: vec![("location".to_string(), "home".to_string()), ("weather".to_string(), "rain".to_string())]
+                .into_iter().collect(),
+            tags: vec!["run".to_string(), "work".to_string()].into_iter().collect(),
+            comment: Some("secret".to_string()),
+            datetime: FixedOffset::east(4 * 3600).ymd(2020, 1, 1).and_hms(9, 10, 11),
+        }
+    }
+
+    #[test]
+    fn no_redaction() {
+        assert!(Redaction::default().apply(daily_score()).same_record(&daily_score()));
+    }
+
+    #[test]
+    fn comments_and_time_redaction() {
+        let redaction = Redaction { drop_comments: true, truncate_time: true, ..Redaction::default() };
+        let redacted_daily_score = redaction.apply(daily_score());
+
+        assert_eq!(redacted_daily_score.comment, None);
+        assert_eq!(redacted_daily_score.datetime.to_rfc3339(), "2020-01-01T00:00:00+00:00");
+
+        let late_evening = DailyScore { datetime: FixedOffset::west(5 * 3600).ymd(2020, 1, 1).and_hms(23, 10, 11), ..daily_score() };
+        assert_eq!(redaction.apply(late_evening).datetime.to_rfc3339(), "2020-01-01T00:00:00+00:00");
+        assert_eq!(redacted_daily_score.tags, daily_score().tags);
+    }
+
+    #[test]
+    fn tags_redaction() {
+        let allowlist = Redaction {
+            tag_allowlist: Some(vec!["run".to_string(), "weather".to_string()].into_iter().collect()),
+            ..Redaction::default()
+        };
+        assert_eq!(allowlist.apply(daily_score()).tags_string(), "run,weather=rain");
+
+        let hashed = Redaction { tag_salt: Some("salt".to_string()), ..Redaction::default() };
+        let hashed_tags = hashed.apply(daily_score()).tags;
+        assert_eq!(hashed_tags.len(), 2);
+        assert!(hashed_tags.contains(&Redaction::hash_tag("salt", "run")));
+        assert!(hashed_tags.iter().all(|tag| tag.len() == TAG_HASH_LENGTH));
+        assert_ne!(Redaction::hash_tag("salt", "run"), Redaction::hash_tag("pepper", "run"));
+        assert_eq!(Redaction::hash_tag("salt", "run"), Redaction::hash_tag("salt", "run"));
+        assert_ne!(Redaction::hash_tag("sal", "trun"), Redaction::hash_tag("salt", "run"));
+        assert_eq!(hashed.apply(daily_score()).attributes["weather"], Redaction::hash_tag("salt", "rain"));
+    }
+}
@@ -0,0 +1,82 @@
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::timezone;
+use crate::mood_report::MoodReport;
+use crate::filter::Filter;
+use crate::metric::Metric;
+use crate::GlobalConfig;
+use crate::journal;
+
+mod html;
+
+const HTML_REPORT_FILE_NAME: &str = "index.html";
+
+pub struct ReportCommand {
+    pub global_config: GlobalConfig,
+    pub output_dir: String,
+    pub filter: Filter,
+    pub metric: Metric,
+}
+
+#[derive(Debug)]
+pub enum ReportCommandError {
+    JournalReadError(journal::JournalError),
+    CannotWriteReport { file_path: String, write_error: io::Error },
+}
+
+impl std::error::Error for ReportCommandError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::JournalReadError(journal_error) => Some(journal_error),
+            Self::CannotWriteReport { file_path: _, write_error } => Some(write_error),
+        }
+    }
+}
+
+impl fmt::Display for ReportCommandError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::JournalReadError(_journal_error) => write!(f, "cannot parse journal"),
+            Self::CannotWriteReport { file_path, write_error: _ } => write!(f, "cannot write report to '{}'", file_path),
+        }
+    }
+}
+
+impl ReportCommand {
+    pub fn run(self) -> Result<(), ReportCommandError> {
+        let daily_scores = journal::read(&self.global_config.journal_file_path, &self.global_config.tag_rules)
+            .map_err(ReportCommandError::JournalReadError)?;
+        let daily_scores = self.global_config.report_daily_scores(daily_scores, &self.filter);
+        let mood_report = MoodReport { daily_scores: &daily_scores, filter: &Filter::default(), metric: &self.metric, timezone: self.global_config.timezone };
+
+        let file_path = Path::new(&self.output_dir).join(HTML_REPORT_FILE_NAME);
+        let write_error = |write_error| ReportCommandError::CannotWriteReport {
+            file_path: file_path.display().to_string(),
+            write_error,
+        };
+
+        fs::create_dir_all(&self.output_dir).map_err(write_error)?;
+        fs::write(&file_path, html::render(&mood_report, timezone::today(self.global_config.timezone), self.global_config.week_start)).map_err(write_error)?;
+        println!("Report is written to '{}'", file_path.display());
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn errors_display() {
+        let file_path = String::from("path/to/file");
+        let write_error = io::Error::other("error text");
+
+        assert_eq!(ReportCommandError::CannotWriteReport { file_path, write_error }.to_string(),
+            "cannot write report to 'path/to/file'");
+    }
+}
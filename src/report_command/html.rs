@@ -0,0 +1,219 @@
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+
+use crate::metric::{Metric, ReportValue};
+use crate::mood_report::{MoodReport, StreakKind};
+use crate::timezone::{self, Timezone};
+
+const CHART_HEIGHT: i64 = 200;
+const CHART_BAR_WIDTH: i64 = 14;
+const CHART_MIN_WIDTH: i64 = 600;
+const HEATMAP_CELL_SIZE: i64 = 12;
+const HEATMAP_WEEKS: i64 = 53;
+const STREAKS_LIMIT: usize = 10;
+const POSITIVE_COLOR: &str = "#2e9e44";
+const NEGATIVE_COLOR: &str = "#d1453b";
+const EMPTY_COLOR: &str = "#ebedf0";
+
+const STYLE: &str = "body { font-family: sans-serif; margin: 2em auto; max-width: 60em; color: #222; }\
+    table { border-collapse: collapse; }\
+    th, td { padding: 0.2em 0.8em; border-bottom: 1px solid #ddd; text-align: left; }\
+    td.number { text-align: right; }\
+    svg { display: block; margin: 1em 0; }\
+    svg text { font-size: 10px; fill: #555; }";
+
+pub fn render(mood_report: &MoodReport, today: NaiveDate, week_start: Weekday) -> String {
+    let monthly_data = mood_report.iterative_monthly_mood();
+    let weekly_data = mood_report.iterative_weekly_mood(week_start);
+
+    let metric_note = match mood_report.metric {
+        Metric::Score => String::new(),
+        Metric::Named(name) => format!(" of '{}' metric", escape(name)),
+    };
+
+    let sections = [
+        format!("<h1>howdy mood report</h1><p>Generated on {}, {} records{}.</p>", today, mood_report.len(), metric_note),
+        format!("<h2>Monthly moods</h2>{}",
+                bar_chart(&monthly_data, |timestamp| period_start(timestamp, mood_report.timezone, |date| date.pred().with_day(1).unwrap()).format("%Y-%m").to_string())),
+        format!("<h2>Weekly moods</h2>{}",
+                bar_chart(&weekly_data, |timestamp| period_start(timestamp, mood_report.timezone, |date| date - Duration::days(7)).format("%d/%m").to_string())),
+        format!("<h2>Last year</h2>{}", heatmap(mood_report, today)),
+        format!("<h2>Tag impact</h2>{}", tag_impact_table(mood_report)),
+        format!("<h2>Longest streaks</h2>{}", streaks_table(mood_report)),
+    ];
+
+    format!("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>howdy mood report</title>\n\
+            <style>{}</style>\n</head>\n<body>\n{}\n</body>\n</html>\n", STYLE, sections.join("\n"))
+}
+
+// Report data is timestamped with period ends, so period starts are calculated back from them.
+fn period_start<F>(timestamp: i64, timezone: Option<Timezone>, start_from_end: F) -> NaiveDate
+where
+    F: Fn(NaiveDate) -> NaiveDate,
+{
+    start_from_end(timezone::date_of(timezone, timestamp))
+}
+
+pub fn bar_chart<F>(data: &[(i64, f64)], label: F) -> String
+where
+    F: Fn(i64) -> String,
+{
+    if data.is_empty() {
+        return "<p>No data yet.</p>".to_string();
+    }
+
+    let width = CHART_MIN_WIDTH.max(data.len() as i64 * CHART_BAR_WIDTH);
+    let bar_width = width / data.len() as i64;
+    let max_value = data.iter().map(|(_, value)| value.abs()).fold(1.0, f64::max);
+    // half of the chart is for positive values, another half for negative ones, with a margin for labels
+    let half_height = (CHART_HEIGHT - 20) / 2;
+    let zero_y = 10 + half_height;
+
+    let mut elements = vec![
+        format!("<line x1=\"0\" y1=\"{0}\" x2=\"{1}\" y2=\"{0}\" stroke=\"#999\"/>", zero_y, width),
+    ];
+    let label_step = (data.len() / 12).max(1);
+
+    for (i, (timestamp, value)) in data.iter().enumerate() {
+        let bar_height = (value.abs() * half_height as f64 / max_value).round() as i64;
+        let (y, color) = if *value >= 0.0 { (zero_y - bar_height, POSITIVE_COLOR) } else { (zero_y, NEGATIVE_COLOR) };
+        let x = i as i64 * bar_width;
+        let label = escape(&label(*timestamp));
+
+        elements.push(format!("<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"><title>{}: {}</title></rect>",
+                              x + 1, y, (bar_width - 2).max(1), bar_height, color, label, ReportValue(*value)));
+        if i % label_step == 0 {
+            elements.push(format!("<text x=\"{}\" y=\"{}\">{}</text>", x, CHART_HEIGHT - 2, label));
+        }
+    }
+
+    svg(width, CHART_HEIGHT, &elements)
+}
+
+// One column per week, one row per weekday, cells are colored by daily score sums.
+pub fn heatmap(mood_report: &MoodReport, today: NaiveDate) -> String {
+    let daily_sums = mood_report.daily_sums();
+    let max_value = daily_sums.values().map(|value| value.abs()).fold(1.0, f64::max);
+    let first_monday = today - Duration::days(today.weekday().num_days_from_monday() as i64 + (HEATMAP_WEEKS - 1) * 7);
+
+    let mut elements = Vec::new();
+    let mut date = first_monday;
+    while date <= today {
+        let week = (date - first_monday).num_days() / 7;
+        let weekday = date.weekday().num_days_from_monday() as i64;
+        let (color, opacity, value_label) = match daily_sums.get(&date) {
+            Some(value) if *value > 0.0 => (POSITIVE_COLOR, 0.3 + 0.7 * value / max_value, ReportValue(*value).to_string()),
+            Some(value) if *value < 0.0 => (NEGATIVE_COLOR, 0.3 + 0.7 * value.abs() / max_value, ReportValue(*value).to_string()),
+            Some(value) => (EMPTY_COLOR, 1.0, ReportValue(*value).to_string()),
+            None => (EMPTY_COLOR, 1.0, "no records".to_string()),
+        };
+
+        elements.push(format!("<rect x=\"{}\" y=\"{}\" width=\"{2}\" height=\"{2}\" fill=\"{3}\" fill-opacity=\"{4:.2}\"><title>{5}: {6}</title></rect>",
+                              week * HEATMAP_CELL_SIZE, weekday * HEATMAP_CELL_SIZE, HEATMAP_CELL_SIZE - 2,
+                              color, opacity, date, value_label));
+        date = date.succ();
+    }
+
+    svg(HEATMAP_WEEKS * HEATMAP_CELL_SIZE, 7 * HEATMAP_CELL_SIZE, &elements)
+}
+
+fn tag_impact_table(mood_report: &MoodReport) -> String {
+    let rows = mood_report.tag_impacts().iter()
+        .map(|(tag, stats, impact)| format!("<tr><td>{}</td><td class=\"number\">{}</td><td class=\"number\">{:.2}</td><td class=\"number\">{:+.2}</td></tr>",
+                                            escape(tag), stats.count, stats.mean(), impact))
+        .collect::<Vec<String>>();
+
+    table(&["Tag", "Records", "Average", "Impact"], &rows)
+}
+
+fn streaks_table(mood_report: &MoodReport) -> String {
+    let mut streaks = mood_report.streaks();
+    streaks.sort_by_key(|streak| -streak.days());
+
+    let rows = streaks.iter()
+        .take(STREAKS_LIMIT)
+        .map(|streak| {
+            let kind = match streak.kind {
+                StreakKind::Positive => "positive",
+                StreakKind::Negative => "negative",
+            };
+            format!("<tr><td>{}</td><td>{}</td><td>{}</td><td class=\"number\">{}</td></tr>", kind, streak.start, streak.end, streak.days())
+        })
+        .collect::<Vec<String>>();
+
+    table(&["Streak", "Start", "End", "Days"], &rows)
+}
+
+fn table(header: &[&str], rows: &[String]) -> String {
+    if rows.is_empty() {
+        return "<p>No data yet.</p>".to_string();
+    }
+
+    let header_cells = header.iter().map(|name| format!("<th>{}</th>", name)).collect::<String>();
+    format!("<table><tr>{}</tr>{}</table>", header_cells, rows.concat())
+}
+
+fn svg(width: i64, height: i64, elements: &[String]) -> String {
+    format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" viewBox=\"0 0 {0} {1}\">{2}</svg>",
+            width, height, elements.concat())
+}
+
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{FixedOffset, TimeZone};
+
+    use super::*;
+    use crate::daily_score::DailyScore;
+    use crate::filter::Filter;
+
+    #[test]
+    fn page_rendering() {
+        let today = NaiveDate::from_ymd(2020, 3, 4);
+        let daily_scores = vec![
+            DailyScore {
+                score: 2.0,
+                metrics: Default::default(),
+                attributes: Default::default(),
+                tags: vec!["<b>run</b>".to_string()].into_iter().collect(),
+                comment: None,
+                datetime: FixedOffset::east(0).ymd(2020, 3, 2).and_hms(12, 0, 0),
+            },
+            DailyScore {
+                score: -1.0,
+                metrics: Default::default(),
+                attributes: Default::default(),
+                tags: vec!["work".to_string()].into_iter().collect(),
+                comment: None,
+                datetime: FixedOffset::east(0).ymd(2020, 3, 3).and_hms(12, 0, 0),
+            },
+        ];
+        let filter = Filter::default();
+        let mood_report = MoodReport { daily_scores: &daily_scores, filter: &filter, metric: &Metric::Score, timezone: None };
+        let page = render(&mood_report, today, Weekday::Mon);
+
+        assert!(page.starts_with("<!DOCTYPE html>"));
+        assert!(page.contains("Generated on 2020-03-04, 2 records."));
+        assert!(page.contains("<td>&lt;b&gt;run&lt;/b&gt;</td><td class=\"number\">1</td><td class=\"number\">2.00</td><td class=\"number\">+3.00</td>"));
+        assert!(page.contains("<tr><td>positive</td><td>2020-03-02</td><td>2020-03-02</td><td class=\"number\">1</td></tr>"));
+        assert!(page.contains("<title>2020-03-03: -1</title>"));
+        assert!(!page.contains("<script"));
+        assert!(!page.contains("<link"));
+        assert!(!page.contains(" src="));
+    }
+
+    #[test]
+    fn bar_chart_rendering() {
+        assert_eq!(bar_chart(&[], |_| String::new()), "<p>No data yet.</p>");
+
+        let chart = bar_chart(&[(0, 2.0), (1, -1.0)], |timestamp| format!("p{}", timestamp));
+        assert!(chart.contains(&format!("fill=\"{}\"><title>p0: 2</title>", POSITIVE_COLOR)));
+        assert!(chart.contains(&format!("fill=\"{}\"><title>p1: -1</title>", NEGATIVE_COLOR)));
+    }
+}
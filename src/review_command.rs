@@ -0,0 +1,358 @@
+use chrono::{Datelike, NaiveDate};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+
+use crate::daily_score::DailyScore;
+use crate::mood_report::{MoodReport, ScoreStats, StreakKind};
+use crate::filter::Filter;
+use crate::metric::{Metric, ReportValue};
+use crate::score_scale::ScoreScale;
+use crate::timezone::{self, Timezone};
+use crate::GlobalConfig;
+use crate::journal;
+
+const TOP_DAYS_LIMIT: usize = 3;
+const TOP_TAGS_LIMIT: usize = 5;
+const MONTH_NAMES: [&str; 12] = ["January", "February", "March", "April", "May", "June", "July",
+    "August", "September", "October", "November", "December"];
+
+pub struct ReviewCommand {
+    pub global_config: GlobalConfig,
+    pub period: ReviewPeriod,
+    pub filter: Filter,
+    pub metric: Metric,
+    pub output_file_path: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReviewPeriod {
+    Month { year: i32, month: u32 },
+    Year(i32),
+}
+
+impl ReviewPeriod {
+    // Accepts `YYYY-MM` for a month or `YYYY` for a year.
+    pub fn parse(period_str: &str) -> Option<Self> {
+        let mut parts = period_str.splitn(2, '-');
+        let year = parts.next()?.parse::<i32>().ok()?;
+
+        match parts.next() {
+            None => Some(Self::Year(year)),
+            Some(month_str) => {
+                let month = month_str.parse::<u32>().ok().filter(|month| (1..=12).contains(month))?;
+                Some(Self::Month { year, month })
+            },
+        }
+    }
+
+    pub fn current_month(timezone: Option<Timezone>) -> Self {
+        let today = timezone::today(timezone);
+        Self::Month { year: today.year(), month: today.month() }
+    }
+
+    pub fn first_day(&self) -> NaiveDate {
+        match *self {
+            Self::Month { year, month } => NaiveDate::from_ymd(year, month, 1),
+            Self::Year(year) => NaiveDate::from_ymd(year, 1, 1),
+        }
+    }
+
+    pub fn last_day(&self) -> NaiveDate {
+        match *self {
+            Self::Month { year: _, month: _ } => self.next().first_day().pred(),
+            Self::Year(year) => NaiveDate::from_ymd(year, 12, 31),
+        }
+    }
+
+    pub fn previous(&self) -> Self {
+        match *self {
+            Self::Month { year, month: 1 } => Self::Month { year: year - 1, month: 12 },
+            Self::Month { year, month } => Self::Month { year, month: month - 1 },
+            Self::Year(year) => Self::Year(year - 1),
+        }
+    }
+
+    fn next(&self) -> Self {
+        match *self {
+            Self::Month { year, month: 12 } => Self::Month { year: year + 1, month: 1 },
+            Self::Month { year, month } => Self::Month { year, month: month + 1 },
+            Self::Year(year) => Self::Year(year + 1),
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            Self::Month { .. } => "Month",
+            Self::Year(_) => "Year",
+        }
+    }
+}
+
+impl fmt::Display for ReviewPeriod {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Month { year, month } => write!(f, "{} {}", MONTH_NAMES[*month as usize - 1], year),
+            Self::Year(year) => write!(f, "{}", year),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ReviewCommandError {
+    JournalReadError(journal::JournalError),
+    CannotWriteReview { file_path: String, write_error: io::Error },
+}
+
+impl std::error::Error for ReviewCommandError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::JournalReadError(journal_error) => Some(journal_error),
+            Self::CannotWriteReview { file_path: _, write_error } => Some(write_error),
+        }
+    }
+}
+
+impl fmt::Display for ReviewCommandError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::JournalReadError(_journal_error) => write!(f, "cannot parse journal"),
+            Self::CannotWriteReview { file_path, write_error: _ } => write!(f, "cannot write review to '{}'", file_path),
+        }
+    }
+}
+
+impl ReviewCommand {
+    pub fn run(self) -> Result<(), ReviewCommandError> {
+        let daily_scores = journal::read(&self.global_config.journal_file_path, &self.global_config.tag_rules)
+            .map_err(ReviewCommandError::JournalReadError)?;
+        let daily_scores = self.global_config.report_daily_scores(daily_scores, &self.filter);
+        let review = render(&daily_scores, self.period, &Filter::default(), &self.metric, &self.global_config.score_scale);
+
+        match self.output_file_path {
+            Some(file_path) => {
+                fs::write(&file_path, review)
+                    .map_err(|write_error| ReviewCommandError::CannotWriteReview { file_path: file_path.clone(), write_error })?;
+                println!("Review is written to '{}'", file_path);
+            },
+            None => print!("{}", review),
+        }
+
+        Ok(())
+    }
+}
+
+// `--from` and `--to` can only narrow the period down.
+fn period_filter(filter: &Filter, period: ReviewPeriod) -> Filter {
+    Filter {
+        from: Some(filter.from.map_or(period.first_day(), |from| from.max(period.first_day()))),
+        to: Some(filter.to.map_or(period.last_day(), |to| to.min(period.last_day()))),
+        ..filter.clone()
+    }
+}
+
+pub fn render(daily_scores: &[DailyScore], period: ReviewPeriod, filter: &Filter, metric: &Metric, score_scale: &ScoreScale) -> String {
+    let current_filter = period_filter(filter, period);
+    let previous_filter = period_filter(filter, period.previous());
+    // periods are given by dates, so the reports do not need today of any zone
+    let mood_report = MoodReport { daily_scores, filter: &current_filter, metric, timezone: None };
+    let previous_stats = MoodReport { daily_scores, filter: &previous_filter, metric, timezone: None }.stats();
+    let stats = mood_report.stats();
+
+    let mut lines = vec![format!("# {} in review: {}", period.kind(), period), String::new()];
+
+    if stats.count == 0 {
+        lines.push("No records for this period.".to_string());
+        return lines.join("\n") + "\n";
+    }
+
+    let daily_sums = mood_report.daily_sums();
+    lines.push(format!("- Total: {} ({:+} compared to {})", ReportValue(stats.sum), ReportValue(stats.sum - previous_stats.sum), period.previous()));
+    lines.push(format!("- Average: {:.2} ({})", stats.mean(), compare_means(stats, previous_stats)));
+    lines.push(format!("- Records: {}, rated days: {}", stats.count, daily_sums.len()));
+    // labels are only known for scores, not for metrics
+    if matches!(metric, Metric::Score) && !score_scale.aliases.is_empty() {
+        lines.push(format!("- Scores: {}", score_counts(&mood_report, score_scale)));
+    }
+
+    let mut days = daily_sums.into_iter().collect::<Vec<(NaiveDate, f64)>>();
+    days.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap().then(a.0.cmp(&b.0)));
+    let comments = day_comments(&mood_report);
+
+    lines.push(String::new());
+    lines.push("## Best days".to_string());
+    lines.push(String::new());
+    lines.extend(days.iter().filter(|(_, sum)| *sum > 0.0).take(TOP_DAYS_LIMIT).map(|day| day_line(day, &comments)));
+
+    days.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap().then(a.0.cmp(&b.0)));
+    lines.push(String::new());
+    lines.push("## Worst days".to_string());
+    lines.push(String::new());
+    lines.extend(days.iter().filter(|(_, sum)| *sum < 0.0).take(TOP_DAYS_LIMIT).map(|day| day_line(day, &comments)));
+
+    let mut tag_stats = mood_report.tag_stats().into_iter().collect::<Vec<(String, ScoreStats)>>();
+    tag_stats.sort_by_key(|(_, stats)| std::cmp::Reverse(stats.count));
+    lines.push(String::new());
+    lines.push("## Most frequent tags".to_string());
+    lines.push(String::new());
+    lines.extend(tag_stats.iter().take(TOP_TAGS_LIMIT)
+                 .map(|(tag, stats)| format!("- {}: {}, average {:.2}", tag, counted(stats.count, "record"), stats.mean())));
+
+    lines.push(String::new());
+    lines.push("## Biggest tag impacts".to_string());
+    lines.push(String::new());
+    lines.extend(mood_report.tag_impacts().iter().take(TOP_TAGS_LIMIT)
+                 .map(|(tag, stats, impact)| format!("- {}: {:+.2} ({})", tag, impact, counted(stats.count, "record"))));
+
+    lines.push(String::new());
+    lines.push("## Longest streaks".to_string());
+    lines.push(String::new());
+    let streaks = mood_report.streaks();
+    for (kind, name) in [(StreakKind::Positive, "positive"), (StreakKind::Negative, "negative")].iter() {
+        let longest_streak = streaks.iter()
+            .filter(|streak| streak.kind == *kind)
+            .max_by_key(|streak| (streak.days(), std::cmp::Reverse(streak.start)));
+        if let Some(streak) = longest_streak {
+            lines.push(format!("- Longest {} streak: {}, {} to {}", name, counted(streak.days() as usize, "day"), streak.start, streak.end));
+        }
+    }
+
+    lines.join("\n") + "\n"
+}
+
+// Number of records with each score, highest score first, e.g. `1 (good): 5, -1 (bad): 2`.
+fn score_counts(mood_report: &MoodReport, score_scale: &ScoreScale) -> String {
+    // scores may be fractional, so they are counted in a list rather than a map
+    let mut counts: Vec<(f64, usize)> = Vec::new();
+    for daily_score in mood_report.filtered_daily_scores() {
+        match counts.iter_mut().find(|(score, _)| *score == daily_score.score) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((daily_score.score, 1)),
+        }
+    }
+    counts.sort_by(|(score, _), (other_score, _)| other_score.total_cmp(score));
+
+    counts.iter()
+        .map(|(score, count)| format!("{}: {}", score_scale.labeled(*score), count))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+// E.g. `1 record` or `2 records`.
+fn counted(count: usize, noun: &str) -> String {
+    if count == 1 { format!("{} {}", count, noun) } else { format!("{} {}s", count, noun) }
+}
+
+fn compare_means(stats: ScoreStats, previous_stats: ScoreStats) -> String {
+    if previous_stats.count == 0 {
+        "no records in previous period".to_string()
+    } else {
+        format!("{:+.2} compared to previous period", stats.mean() - previous_stats.mean())
+    }
+}
+
+fn day_comments(mood_report: &MoodReport) -> BTreeMap<NaiveDate, Vec<String>> {
+    let mut comments: BTreeMap<NaiveDate, Vec<String>> = BTreeMap::new();
+    for daily_score in mood_report.filtered_daily_scores() {
+        if let Some(comment) = daily_score.comment.as_deref().filter(|comment| !comment.is_empty()) {
+            comments.entry(daily_score.datetime.naive_local().date()).or_default().push(comment.to_string());
+        }
+    }
+    comments
+}
+
+fn day_line((date, sum): &(NaiveDate, f64), comments: &BTreeMap<NaiveDate, Vec<String>>) -> String {
+    match comments.get(date) {
+        Some(day_comments) => format!("- {} ({}): {:+}: {}", date, date.weekday(), ReportValue(*sum), day_comments.join("; ")),
+        None => format!("- {} ({}): {:+}", date, date.weekday(), ReportValue(*sum)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{FixedOffset, TimeZone};
+
+    use super::*;
+
+    #[test]
+    fn period_parsing() {
+        assert_eq!(ReviewPeriod::parse("2020-02"), Some(ReviewPeriod::Month { year: 2020, month: 2 }));
+        assert_eq!(ReviewPeriod::parse("2020"), Some(ReviewPeriod::Year(2020)));
+        assert_eq!(ReviewPeriod::parse("2020-13"), None);
+        assert_eq!(ReviewPeriod::parse("foo"), None);
+    }
+
+    #[test]
+    fn period_bounds() {
+        let february = ReviewPeriod::Month { year: 2020, month: 2 };
+        assert_eq!(february.first_day(), NaiveDate::from_ymd(2020, 2, 1));
+        assert_eq!(february.last_day(), NaiveDate::from_ymd(2020, 2, 29));
+        assert_eq!(ReviewPeriod::Month { year: 2020, month: 1 }.previous(), ReviewPeriod::Month { year: 2019, month: 12 });
+        assert_eq!(ReviewPeriod::Month { year: 2020, month: 12 }.last_day(), NaiveDate::from_ymd(2020, 12, 31));
+        assert_eq!(ReviewPeriod::Year(2020).last_day(), NaiveDate::from_ymd(2020, 12, 31));
+        assert_eq!(february.to_string(), "February 2020");
+    }
+
+    #[test]
+    fn review_rendering() {
+        let daily_score_at = |score, tags: &[&str], comment: Option<&str>, month, day| DailyScore {
+            score,
+            metrics: Default::default(),
+            attributes: Default::default(),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            comment: comment.map(str::to_string),
+            datetime: FixedOffset::east(0).ymd(2020, month, day).and_hms(12, 0, 0),
+        };
+        let daily_scores = vec![
+            daily_score_at(1.0, &[], None, 1, 10),
+            daily_score_at(1.0, &["run"], Some("good run"), 2, 3),
+            daily_score_at(1.0, &["run"], None, 2, 4),
+            daily_score_at(-1.0, &["work"], Some("deadline"), 2, 5),
+        ];
+
+        assert_eq!(render(&daily_scores, ReviewPeriod::Month { year: 2020, month: 2 }, &Filter::default(), &Metric::Score, &ScoreScale::default()),
+            "# Month in review: February 2020\n\
+            \n\
+            - Total: 1 (+0 compared to January 2020)\n\
+            - Average: 0.33 (-0.67 compared to previous period)\n\
+            - Records: 3, rated days: 3\n\
+            \n\
+            ## Best days\n\
+            \n\
+            - 2020-02-03 (Mon): +1: good run\n\
+            - 2020-02-04 (Tue): +1\n\
+            \n\
+            ## Worst days\n\
+            \n\
+            - 2020-02-05 (Wed): -1: deadline\n\
+            \n\
+            ## Most frequent tags\n\
+            \n\
+            - run: 2 records, average 1.00\n\
+            - work: 1 record, average -1.00\n\
+            \n\
+            ## Biggest tag impacts\n\
+            \n\
+            - run: +2.00 (2 records)\n\
+            - work: -2.00 (1 record)\n\
+            \n\
+            ## Longest streaks\n\
+            \n\
+            - Longest positive streak: 2 days, 2020-02-03 to 2020-02-04\n\
+            - Longest negative streak: 1 day, 2020-02-05 to 2020-02-05\n");
+
+        assert_eq!(render(&daily_scores, ReviewPeriod::Year(2019), &Filter::default(), &Metric::Score, &ScoreScale::default()),
+            "# Year in review: 2019\n\nNo records for this period.\n");
+
+        // the period is narrowed down by the filter, but not extended by it
+        let filter = Filter { from: Some(NaiveDate::from_ymd(2020, 1, 1)), to: Some(NaiveDate::from_ymd(2020, 2, 4)), ..Filter::default() };
+        assert!(render(&daily_scores, ReviewPeriod::Month { year: 2020, month: 2 }, &filter, &Metric::Score, &ScoreScale::default())
+            .contains("- Total: 2 (+1 compared to January 2020)\n"));
+
+        let score_scale = ScoreScale { aliases: vec![("good".to_string(), 1.0), ("+".to_string(), 1.0)].into_iter().collect(), ..ScoreScale::default() };
+        assert!(render(&daily_scores, ReviewPeriod::Month { year: 2020, month: 2 }, &Filter::default(), &Metric::Score, &score_scale)
+            .contains("- Records: 3, rated days: 3\n- Scores: 1 (good): 2, -1: 1\n\n## Best days"));
+    }
+}
@@ -0,0 +1,118 @@
+use std::collections::BTreeMap;
+
+use crate::metric;
+
+// Scores which can be given to a day, and names they can be typed with, e.g. `good` for `1`.
+// Any score is allowed by default.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ScoreScale {
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    // if set, only these scores are allowed, and `min` and `max` are not used
+    pub allowed: Option<Vec<f64>>,
+    pub aliases: BTreeMap<String, f64>,
+}
+
+impl ScoreScale {
+    pub fn alias(&self, name: &str) -> Option<f64> {
+        self.aliases.get(name.trim()).copied()
+    }
+
+    // Accepts numbers and aliases, even if the score is not allowed.
+    pub fn parse(&self, score_str: &str) -> Option<f64> {
+        self.alias(score_str).or_else(|| metric::parse_value(score_str))
+    }
+
+    pub fn is_allowed(&self, score: f64) -> bool {
+        match &self.allowed {
+            Some(allowed) => allowed.contains(&score),
+            None => self.min.is_none_or(|min| score >= min) && self.max.is_none_or(|max| score <= max),
+        }
+    }
+
+    // The longest alias of the score, so `good` is preferred to `+`.
+    pub fn label(&self, score: f64) -> Option<&str> {
+        self.aliases.iter()
+            .filter(|(_, alias_score)| **alias_score == score)
+            .map(|(alias, _)| alias.as_str())
+            .max_by_key(|alias| alias.chars().count())
+    }
+
+    // Score with its label, e.g. `1 (good)`.
+    pub fn labeled(&self, score: f64) -> String {
+        match self.label(score) {
+            Some(label) => format!("{} ({})", score, label),
+            None => score.to_string(),
+        }
+    }
+
+    // Allowed scores as they are described in prompts and errors, e.g. `from -1 to 1 or bad, good`.
+    pub fn description(&self) -> String {
+        let scores = match (&self.allowed, self.min, self.max) {
+            (Some(allowed), _, _) => format!("one of {}", allowed.iter().map(f64::to_string).collect::<Vec<String>>().join(", ")),
+            (None, Some(min), Some(max)) => format!("from {} to {}", min, max),
+            (None, Some(min), None) => format!("from {}", min),
+            (None, None, Some(max)) => format!("up to {}", max),
+            (None, None, None) => "any number".to_string(),
+        };
+
+        if self.aliases.is_empty() {
+            scores
+        } else {
+            format!("{} or {}", scores, self.aliases.keys().cloned().collect::<Vec<String>>().join(", "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scale() -> ScoreScale {
+        ScoreScale {
+            min: Some(-1.0),
+            max: Some(1.0),
+            allowed: None,
+            aliases: vec![("good", 1.0), ("+", 1.0), ("meh", 0.0), ("bad", -1.0)].into_iter()
+                .map(|(alias, score)| (alias.to_string(), score))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn scores_parsing() {
+        assert_eq!(scale().parse("good"), Some(1.0));
+        assert_eq!(scale().parse(" + "), Some(1.0));
+        assert_eq!(scale().parse("-1"), Some(-1.0));
+        assert_eq!(scale().parse("5"), Some(5.0));
+        assert_eq!(scale().parse("0.5"), Some(0.5));
+        assert_eq!(scale().parse("great"), None);
+        assert_eq!(ScoreScale::default().parse("good"), None);
+    }
+
+    #[test]
+    fn allowed_scores() {
+        assert!(scale().is_allowed(1.0));
+        assert!(scale().is_allowed(0.5));
+        assert!(!scale().is_allowed(2.0));
+        assert!(!scale().is_allowed(-2.0));
+        assert!(ScoreScale::default().is_allowed(-250.0));
+
+        let set_scale = ScoreScale { allowed: Some(vec![-2.0, 0.0, 2.5]), ..scale() };
+        assert!(set_scale.is_allowed(2.5));
+        assert!(!set_scale.is_allowed(1.0));
+    }
+
+    #[test]
+    fn labels_and_descriptions() {
+        assert_eq!(scale().label(1.0), Some("good"));
+        assert_eq!(scale().label(2.0), None);
+        assert_eq!(scale().labeled(-1.0), "-1 (bad)");
+        assert_eq!(scale().labeled(0.5), "0.5");
+
+        assert_eq!(scale().description(), "from -1 to 1 or +, bad, good, meh");
+        assert_eq!(ScoreScale::default().description(), "any number");
+        assert_eq!(ScoreScale { min: Some(1.0), ..ScoreScale::default() }.description(), "from 1");
+        assert_eq!(ScoreScale { allowed: Some(vec![0.0, 7.5]), ..ScoreScale::default() }.description(), "one of 0, 7.5");
+    }
+}
@@ -0,0 +1,572 @@
+use chrono::{Local, NaiveDate, Timelike};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashSet};
+use std::collections::hash_map::RandomState;
+use std::error::Error;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, BufReader, Read};
+use std::net::{IpAddr, SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::add_command::{self, AddCommand};
+use crate::attribute;
+use crate::daily_score::DailyScore;
+use crate::filter::Filter;
+use crate::journal::{self, json as journal_json};
+use crate::metric::{self, Metric};
+use crate::mood_command::MoodReportType;
+use crate::mood_report::MoodReport;
+use crate::tag::{self, TagRules};
+use crate::timezone;
+use crate::GlobalConfig;
+use crate::FILTER_DATE_FORMAT;
+
+mod http;
+
+use http::{HttpError, Request, Response};
+
+const PAGE: &str = include_str!("serve_command/page.html");
+// the whole request has to be read by then, so slow clients do not keep connections open
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub struct ServeCommand {
+    pub global_config: GlobalConfig,
+    pub bind_address: String,
+    pub token: Option<String>,
+}
+
+// Who may use the server: pages of other sites and DNS rebinding are kept out by `Host` and `Origin`
+// checks, and clients on the network by the token.
+struct Access {
+    address: SocketAddr,
+    token: Option<String>,
+}
+
+struct Server {
+    global_config: GlobalConfig,
+    access: Access,
+    // requests are handled in parallel, but journal is read and written by one of them at a time
+    journal_lock: Mutex<()>,
+}
+
+#[derive(Debug)]
+pub enum ServeCommandError {
+    CannotBind { address: String, bind_error: io::Error },
+}
+
+impl std::error::Error for ServeCommandError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::CannotBind { address: _, bind_error } => Some(bind_error),
+        }
+    }
+}
+
+impl fmt::Display for ServeCommandError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::CannotBind { address, bind_error: _ } => write!(f, "cannot listen on '{}'", address),
+        }
+    }
+}
+
+impl ServeCommand {
+    pub fn run(self) -> Result<(), ServeCommandError> {
+        let bind_error = |bind_error| ServeCommandError::CannotBind { address: self.bind_address.clone(), bind_error };
+        let listener = TcpListener::bind(&self.bind_address).map_err(bind_error)?;
+        let local_address = listener.local_addr().map_err(bind_error)?;
+
+        // the network can't be trusted, so its clients need the token
+        let token = self.token.or_else(|| if local_address.ip().is_loopback() { None } else { Some(generate_token()) });
+        match &token {
+            Some(token) => println!("Serving journal '{}' on http://{}/?token={}", self.global_config.journal_file_path, local_address, token),
+            None => println!("Serving journal '{}' on http://{}", self.global_config.journal_file_path, local_address),
+        }
+
+        let server = Arc::new(Server {
+            global_config: self.global_config,
+            access: Access { address: local_address, token },
+            journal_lock: Mutex::new(()),
+        });
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(accept_error) => {
+                    eprintln!("Warning: cannot accept connection: {}", accept_error);
+                    continue;
+                },
+            };
+            let server = Arc::clone(&server);
+            if let Err(spawn_error) = thread::Builder::new().spawn(move || server.serve_connection(stream)) {
+                eprintln!("Warning: cannot serve connection: {}", spawn_error);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Server {
+    fn serve_connection(&self, stream: TcpStream) {
+        let _ = stream.set_write_timeout(Some(WRITE_TIMEOUT));
+        let mut reader = BufReader::new(DeadlineReader { stream: &stream, deadline: Instant::now() + REQUEST_TIMEOUT });
+
+        let response = match http::read_request(&mut reader) {
+            Ok(request) => {
+                let response = match check_access(&request, &self.access) {
+                    Ok(()) => {
+                        let _journal_guard = self.journal_lock.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                        handle(&request, &self.global_config)
+                    },
+                    Err(response) => response,
+                };
+                println!("{} {} {}", request.method, request.path, response.status);
+                response
+            },
+            Err(HttpError::RequestTooLarge) => Response::error(413, &HttpError::RequestTooLarge.to_string()),
+            Err(HttpError::CannotRead(read_error)) if matches!(read_error.kind(), io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock) =>
+                Response::error(408, "request is not received in time"),
+            Err(http_error) => Response::error(400, &http_error.to_string()),
+        };
+
+        if let Err(write_error) = http::write_response(&mut &stream, &response) {
+            eprintln!("Warning: cannot send response: {}", write_error);
+        }
+    }
+}
+
+// Read timeout is shortened before every read, so the whole request has to come by the deadline.
+struct DeadlineReader<'a> {
+    stream: &'a TcpStream,
+    deadline: Instant,
+}
+
+impl Read for DeadlineReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let time_left = self.deadline.checked_duration_since(Instant::now())
+            .filter(|time_left| !time_left.is_zero())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::TimedOut, "request deadline has passed"))?;
+        self.stream.set_read_timeout(Some(time_left))?;
+
+        let mut stream = self.stream;
+        stream.read(buf)
+    }
+}
+
+// 128 random bits, std hasher keys are seeded by the OS random generator.
+fn generate_token() -> String {
+    (0..2)
+        .map(|_| format!("{:016x}", RandomState::new().build_hasher().finish()))
+        .collect()
+}
+
+// Browsers send `Host` and `Origin` of the page, which are the server's ones unless the request
+// is made by a page of another site. Writes need JSON content type, which forms of other sites can't send.
+fn check_access(request: &Request, access: &Access) -> Result<(), Response> {
+    if !request.header("host").is_some_and(|host| is_host_allowed(host, access.address)) {
+        return Err(Response::error(403, "host is not allowed"));
+    }
+    if let Some(origin) = request.header("origin") {
+        if !origin.strip_prefix("http://").is_some_and(|host| is_host_allowed(host, access.address)) {
+            return Err(Response::error(403, "origin is not allowed"));
+        }
+    }
+
+    if let Some(token) = &access.token {
+        let bearer_token = request.header("authorization").and_then(|authorization| authorization.strip_prefix("Bearer "));
+        let query_token = request.query_values("token").last();
+        if bearer_token.or(query_token) != Some(token.as_str()) {
+            return Err(Response::error(401, "token is not valid"));
+        }
+    }
+
+    let content_type = request.header("content-type").map(|content_type| content_type.split(';').next().unwrap_or("").trim());
+    if request.method == "POST" && !content_type.is_some_and(|content_type| content_type.eq_ignore_ascii_case("application/json")) {
+        return Err(Response::error(415, "content type is not valid, expected application/json"));
+    }
+
+    Ok(())
+}
+
+// `HOST[:PORT]` is allowed if it is the address the server listens on, or `localhost` for loopback addresses.
+// Any host is allowed for unspecified addresses like `0.0.0.0`, which are only listened on with a token.
+fn is_host_allowed(host: &str, address: SocketAddr) -> bool {
+    let (name, port) = match host.rsplit_once(':') {
+        Some((name, port)) if !port.ends_with(']') => (name, port.parse::<u16>().ok()),
+        _ => (host, Some(80)),
+    };
+    let ip_name = match address.ip() {
+        IpAddr::V4(ip) => ip.to_string(),
+        IpAddr::V6(ip) => format!("[{}]", ip),
+    };
+
+    port == Some(address.port()) &&
+        (address.ip().is_unspecified() ||
+         name.eq_ignore_ascii_case(&ip_name) ||
+         (address.ip().is_loopback() && name.eq_ignore_ascii_case("localhost")))
+}
+
+fn handle(request: &Request, global_config: &GlobalConfig) -> Response {
+    let path_segments = request.path.trim_matches('/').split('/').collect::<Vec<&str>>();
+
+    match (request.method.as_str(), path_segments.as_slice()) {
+        ("GET", [""]) => Response::new(200, "text/html", PAGE.to_string()),
+        ("GET", ["api", "entries"]) => list_entries(request, global_config),
+        ("POST", ["api", "entries"]) => add_entry(request, global_config),
+        ("GET", ["api", "reports", report_type]) => report(request, report_type, global_config),
+        (_, [""]) | (_, ["api", "entries"]) | (_, ["api", "reports", _]) => Response::error(405, "method is not allowed"),
+        _ => Response::error(404, "not found"),
+    }
+}
+
+// Journal records matching the filter, oldest first, `limit` takes the last ones.
+fn list_entries(request: &Request, global_config: &GlobalConfig) -> Response {
+    let filter = match query_filter(request, &global_config.tag_rules) {
+        Ok(filter) => filter,
+        Err(response) => return response,
+    };
+    let limit = match request.query_values("limit").last().map(str::parse::<usize>) {
+        None => None,
+        Some(Ok(limit)) => Some(limit),
+        Some(Err(_)) => return Response::error(400, "'limit' is not valid"),
+    };
+    let daily_scores = match journal::read_if_exists(&global_config.journal_file_path, &global_config.tag_rules) {
+        Ok(daily_scores) => daily_scores,
+        Err(journal_error) => return Response::error(500, &journal_error.to_string()),
+    };
+
+    let values = daily_scores.iter()
+        .filter(|daily_score| filter.matches(daily_score))
+        .map(journal_json::to_value)
+        .collect::<Vec<Value>>();
+    let skipped = limit.map_or(0, |limit| values.len().saturating_sub(limit));
+
+    Response::json(200, &Value::Array(values.into_iter().skip(skipped).collect()))
+}
+
+// Adds a record the same way `add` command does,
+// body is `{"score": 1, "metrics": {"sleep": 7.5}, "tags": [...], "attributes": {"weather": "rain"}, "comment": "..."}`.
+fn add_entry(request: &Request, global_config: &GlobalConfig) -> Response {
+    let value = match serde_json::from_slice::<Value>(&request.body) {
+        Ok(value) => value,
+        Err(_) => return Response::error(400, "request body is not valid JSON"),
+    };
+
+    // score is a number or a configured alias, e.g. `"good"`
+    let score_scale = &global_config.score_scale;
+    let score = match value.get("score") {
+        Some(Value::Number(score)) => score.as_f64().filter(|score| score.is_finite()),
+        Some(Value::String(alias)) => score_scale.alias(alias),
+        _ => None,
+    };
+    let score = match score {
+        Some(score) if score_scale.is_allowed(score) => score,
+        _ => return Response::error(400, &format!("'score' is not valid, expected {}", score_scale.description())),
+    };
+
+    // attributes are given separately, so `KEY=VALUE` tags are rejected the same as in JSON import
+    let mut tags = HashSet::new();
+    match value.get("tags") {
+        None | Some(Value::Null) => {},
+        Some(Value::Array(tag_values)) => for tag_value in tag_values {
+            match tag_value.as_str() {
+                Some(tag) if !tag::is_valid(tag) => return Response::error(400, &format!("'{}' is not a valid tag", tag)),
+                Some(tag) => { tags.insert(tag.trim().to_string()); },
+                None => return Response::error(400, "'tags' is not valid, expected array of strings"),
+            }
+        },
+        Some(_) => return Response::error(400, "'tags' is not valid, expected array of strings"),
+    }
+    let tags = global_config.tag_rules.apply_all(&tags);
+
+    let mut attributes = BTreeMap::new();
+    match value.get("attributes") {
+        None | Some(Value::Null) => {},
+        Some(Value::Object(attribute_values)) => for (key, attribute_value) in attribute_values {
+            match attribute_value.as_str().and_then(|value| attribute::parse_attribute(&attribute::format_attribute(key, value))) {
+                Some((key, value)) => { attributes.insert(key, value); },
+                None => return Response::error(400, &format!("'{}' is not a valid attribute", key)),
+            }
+        },
+        Some(_) => return Response::error(400, "'attributes' is not valid, expected object of strings"),
+    }
+
+    let comment = match value.get("comment") {
+        None | Some(Value::Null) => None,
+        Some(Value::String(comment)) => Some(comment.replace(&['\n', '\r'][..], " ")).filter(|comment| !comment.trim().is_empty()),
+        Some(_) => return Response::error(400, "'comment' is not valid, expected string"),
+    };
+
+    let mut metrics = BTreeMap::new();
+    match value.get("metrics") {
+        None | Some(Value::Null) => {},
+        Some(Value::Object(metric_values)) => for (name, metric_value) in metric_values {
+            match metric_value.as_f64() {
+                Some(metric_value) if metric::is_name_valid(name) => { metrics.insert(name.clone(), metric_value); },
+                _ => return Response::error(400, &format!("'{}' is not a valid metric", name)),
+            }
+        },
+        Some(_) => return Response::error(400, "'metrics' is not valid, expected object of numbers"),
+    }
+
+    // journal keeps seconds only, so returned record is the same as the written one
+    let now = Local::now().with_nanosecond(0).unwrap();
+    let daily_score = DailyScore {
+        score,
+        metrics: metrics.clone(),
+        tags: tags.clone(),
+        attributes: attributes.clone(),
+        comment: comment.clone(),
+        datetime: timezone::convert(global_config.timezone, &now),
+    };
+    let add_command = AddCommand {
+        score, metrics, tags, attributes, comment, datetime: Some(now), global_config: global_config.clone(),
+    };
+
+    match add_command.run() {
+        Ok(()) => Response::json(201, &journal_json::to_value(&daily_score)),
+        Err(add_error @ add_command::AddCommandError::DayAlreadyRated(_)) => Response::error(409, &add_error.to_string()),
+        Err(add_error) => Response::error(500, &add_error.to_string()),
+    }
+}
+
+// Any `mood` report type, each point is timestamped with the end of its period, `metric` selects reported metric.
+fn report(request: &Request, report_type_str: &str, global_config: &GlobalConfig) -> Response {
+    let report_type = match MoodReportType::parse(report_type_str) {
+        Some(report_type) => report_type,
+        None => return Response::error(404, &format!("'{}' is not a valid report type", report_type_str)),
+    };
+    let filter = match query_filter(request, &global_config.tag_rules) {
+        Ok(filter) => filter,
+        Err(response) => return response,
+    };
+    let metric = match request.query_values("metric").last().map(Metric::parse) {
+        None => Metric::Score,
+        Some(Some(metric)) => metric,
+        Some(None) => return Response::error(400, "'metric' is not valid"),
+    };
+    let daily_scores = match journal::read_if_exists(&global_config.journal_file_path, &global_config.tag_rules) {
+        Ok(daily_scores) => daily_scores,
+        Err(journal_error) => return Response::error(500, &journal_error.to_string()),
+    };
+    let daily_scores = global_config.report_daily_scores(daily_scores, &filter);
+
+    let mood_report = MoodReport { daily_scores: &daily_scores, filter: &Filter::default(), metric: &metric, timezone: global_config.timezone };
+    let series = report_type.series(&mood_report, global_config.week_start).into_iter()
+        .map(|(timestamp, value)| json!({
+            "timestamp": timestamp,
+            "date": timezone::date_of(global_config.timezone, timestamp).format(FILTER_DATE_FORMAT).to_string(),
+            "value": journal_json::number(value),
+        }))
+        .collect::<Vec<Value>>();
+
+    Response::json(200, &json!({ "report_type": report_type_str, "series": series }))
+}
+
+// Same filters as command line ones: `tag` (may be repeated), `from`, `to`, `min_score`, `max_score`.
+fn query_filter(request: &Request, tag_rules: &TagRules) -> Result<Filter, Response> {
+    let parse_date = |date: &str| NaiveDate::parse_from_str(date, FILTER_DATE_FORMAT)
+        .map_err(|_| Response::error(400, &format!("'{}' is not a valid date, expected YYYY-MM-DD", date)));
+    let parse_score = |score: &str| metric::parse_value(score)
+        .ok_or_else(|| Response::error(400, &format!("'{}' is not a valid score filter", score)));
+
+    let mut filter = Filter::default();
+    for (name, value) in request.query.iter() {
+        match name.as_str() {
+            "tag" => { filter.tags.insert(tag_rules.filter_tag(value)); },
+            "from" => filter.from = Some(parse_date(value)?),
+            "to" => filter.to = Some(parse_date(value)?),
+            "min_score" => filter.min_score = Some(parse_score(value)?),
+            "max_score" => filter.max_score = Some(parse_score(value)?),
+            _ => {},
+        }
+    }
+
+    Ok(filter)
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_fs::prelude::*;
+
+    use super::*;
+    use crate::config::Config;
+    use crate::add_command::SameDayPolicy;
+    use crate::score_scale::ScoreScale;
+
+    fn request(method: &str, target: &str, body: &str) -> Request {
+        let raw_request = format!("{} {} HTTP/1.1\r\nContent-Length: {}\r\n\r\n{}", method, target, body.len(), body);
+        http::read_request(&mut raw_request.as_bytes()).unwrap()
+    }
+
+    fn global_config(journal_file_path: &str) -> GlobalConfig {
+        let mut global_config = GlobalConfig::from(Config::default());
+        global_config.journal_file_path = journal_file_path.to_string();
+        global_config.timezone = None;
+        global_config
+    }
+
+    fn body(response: &Response) -> Value {
+        serde_json::from_str(&response.body).unwrap()
+    }
+
+    #[test]
+    fn entries() {
+        let temp_dir = assert_fs::TempDir::new().unwrap();
+        let journal = temp_dir.child("howdy.journal");
+        journal.write_str("2020-01-01 10:00:00 +0000 | 1 | run | \n\
+                           2020-01-02 10:00:00 +0000 | -1 | work | long day\n\
+                           2020-01-03 10:00:00 +0000 | 1 | run,work | \n").unwrap();
+        let global_config = global_config(journal.path().to_str().unwrap());
+
+        let all_entries = handle(&request("GET", "/api/entries", ""), &global_config);
+        assert_eq!(all_entries.status, 200);
+        assert_eq!(body(&all_entries).as_array().unwrap().len(), 3);
+
+        let filtered_entries = body(&handle(&request("GET", "/api/entries?tag=work&limit=1", ""), &global_config));
+        assert_eq!(filtered_entries, json!([{ "datetime": "2020-01-03T10:00:00+00:00", "score": 1, "metrics": {}, "tags": ["run", "work"], "attributes": {}, "comment": null }]));
+
+        let mut normalizing_global_config = global_config.clone();
+        normalizing_global_config.tag_rules = TagRules::new(true, vec![("jog".to_string(), "run".to_string())].into_iter().collect());
+        let aliased_entries = handle(&request("GET", "/api/entries?tag=%20Jog", ""), &normalizing_global_config);
+        assert_eq!(body(&aliased_entries).as_array().unwrap().len(), 2);
+
+        let added = handle(&request("POST", "/api/entries/",
+                                    r#"{"score": 0.5, "metrics": {"sleep": 7.5}, "tags": [" walk "], "attributes": {"location": "park", "weather": "sun"},
+                                       "comment": "nice\nday"}"#),
+                           &global_config);
+        assert_eq!(added.status, 201);
+        assert_eq!(body(&added)["score"], json!(0.5));
+        assert_eq!(body(&added)["metrics"], json!({ "sleep": 7.5 }));
+        assert_eq!(body(&added)["tags"], json!(["walk"]));
+        assert_eq!(body(&added)["attributes"], json!({ "location": "park", "weather": "sun" }));
+        assert_eq!(body(&added)["comment"], json!("nice day"));
+        let last_entry = body(&handle(&request("GET", "/api/entries?limit=1", ""), &global_config));
+        assert_eq!(last_entry, json!([body(&added)]));
+    }
+
+    #[test]
+    fn invalid_entries() {
+        let temp_dir = assert_fs::TempDir::new().unwrap();
+        let journal = temp_dir.child("howdy.journal");
+        let global_config = global_config(journal.path().to_str().unwrap());
+        let add = |body: &str| handle(&request("POST", "/api/entries", body), &global_config);
+
+        assert_eq!(body(&add("score=1")), json!({ "error": "request body is not valid JSON" }));
+        assert_eq!(add(r#"{"score": "1"}"#).status, 400);
+        assert_eq!(body(&add(r#"{"score": 1, "tags": ["a,b"]}"#)), json!({ "error": "'a,b' is not a valid tag" }));
+        assert_eq!(add(r#"{"score": 1, "tags": ["a\rb"]}"#).status, 400);
+        assert_eq!(add(r#"{"score": 1, "tags": [" "]}"#).status, 400);
+        assert_eq!(body(&add(r#"{"score": 1, "tags": ["location=park"]}"#)), json!({ "error": "'location=park' is not a valid tag" }));
+        assert_eq!(add(r#"{"score": 1, "tags": "run"}"#).status, 400);
+        assert_eq!(add(r#"{"score": 1, "comment": 1}"#).status, 400);
+        assert_eq!(body(&add(r#"{"score": 1, "metrics": {"sleep": "long"}}"#)), json!({ "error": "'sleep' is not a valid metric" }));
+        assert_eq!(body(&add(r#"{"score": 1, "attributes": {"weather": ""}}"#)), json!({ "error": "'weather' is not a valid attribute" }));
+        journal.assert(predicates::path::missing());
+
+        let mut scaled_global_config = global_config.clone();
+        scaled_global_config.score_scale = ScoreScale { min: Some(-1.0), max: Some(1.0), aliases: vec![("good".to_string(), 1.0)].into_iter().collect(), ..ScoreScale::default() };
+        let scaled_add = |body: &str| handle(&request("POST", "/api/entries", body), &scaled_global_config);
+        assert_eq!(body(&scaled_add(r#"{"score": 2}"#)), json!({ "error": "'score' is not valid, expected from -1 to 1 or good" }));
+        assert_eq!(body(&scaled_add(r#"{"score": "good"}"#))["score"], json!(1));
+
+        let mut rejecting_global_config = global_config.clone();
+        rejecting_global_config.same_day_policy = SameDayPolicy::Reject;
+        let rejecting_add = |body: &str| handle(&request("POST", "/api/entries", body), &rejecting_global_config);
+        // today is rated by the scaled add above
+        assert_eq!(rejecting_add(r#"{"score": 1}"#).status, 409);
+
+        let invalid_filter = handle(&request("GET", "/api/entries?from=yesterday", ""), &global_config);
+        assert_eq!(body(&invalid_filter), json!({ "error": "'yesterday' is not a valid date, expected YYYY-MM-DD" }));
+    }
+
+    #[test]
+    fn reports() {
+        let temp_dir = assert_fs::TempDir::new().unwrap();
+        let journal = temp_dir.child("howdy.journal");
+        let today = Local::now();
+        journal.write_str(&format!("{0} | 2 sleep=7.5 | run | \n{0} | 1 | | \n", today.format(crate::daily_score::DATE_FORMAT))).unwrap();
+        let global_config = global_config(journal.path().to_str().unwrap());
+
+        let last_month = body(&handle(&request("GET", "/api/reports/lm", ""), &global_config));
+        assert_eq!(last_month["report_type"], json!("lm"));
+        assert_eq!(last_month["series"][0]["value"], json!(3));
+        assert_eq!(last_month["series"][0]["date"], json!(today.format("%Y-%m-%d").to_string()));
+
+        let moving = body(&handle(&request("GET", "/api/reports/moving?tag=walk", ""), &global_config));
+        assert_eq!(moving["series"].as_array().unwrap().len(), 30);
+        assert!(moving["series"].as_array().unwrap().iter().all(|point| point["value"] == json!(0)));
+
+        let sleep = body(&handle(&request("GET", "/api/reports/lm?metric=sleep", ""), &global_config));
+        assert_eq!(sleep["series"][0]["value"], json!(7.5));
+
+        assert_eq!(handle(&request("GET", "/api/reports/lm?metric=1st", ""), &global_config).status, 400);
+        assert_eq!(handle(&request("GET", "/api/reports/daily", ""), &global_config).status, 404);
+    }
+
+    #[test]
+    fn routing() {
+        let global_config = global_config("/nonexistent/howdy.journal");
+
+        let page = handle(&request("GET", "/", ""), &global_config);
+        assert_eq!((page.status, page.content_type), (200, "text/html"));
+        assert!(page.body.contains("/api/entries"));
+        assert_eq!(body(&handle(&request("GET", "/api/entries", ""), &global_config)), json!([]));
+        assert_eq!(handle(&request("DELETE", "/api/entries", ""), &global_config).status, 405);
+        assert_eq!(handle(&request("GET", "/favicon.ico", ""), &global_config).status, 404);
+    }
+
+    #[test]
+    fn access_checks() {
+        let loopback = Access { address: "127.0.0.1:8080".parse().unwrap(), token: None };
+        let check = |raw_headers: &str, access: &Access| {
+            let raw_request = format!("POST /api/entries?token=secret HTTP/1.1\r\n{}\r\n", raw_headers);
+            check_access(&http::read_request(&mut raw_request.as_bytes()).unwrap(), access).map_err(|response| response.status)
+        };
+
+        assert_eq!(check("Host: localhost:8080\r\nContent-Type: application/json; charset=utf-8\r\n", &loopback), Ok(()));
+        assert_eq!(check("Host: 127.0.0.1:8080\r\nOrigin: http://localhost:8080\r\nContent-Type: application/json\r\n", &loopback), Ok(()));
+
+        // DNS rebinding and pages of other sites
+        assert_eq!(check("Host: evil.example:8080\r\nContent-Type: application/json\r\n", &loopback), Err(403));
+        assert_eq!(check("Host: localhost:8081\r\nContent-Type: application/json\r\n", &loopback), Err(403));
+        assert_eq!(check("Content-Type: application/json\r\n", &loopback), Err(403));
+        assert_eq!(check("Host: localhost:8080\r\nOrigin: http://evil.example\r\nContent-Type: application/json\r\n", &loopback), Err(403));
+        assert_eq!(check("Host: localhost:8080\r\nOrigin: null\r\nContent-Type: application/json\r\n", &loopback), Err(403));
+
+        // forms can post only form and text content types
+        assert_eq!(check("Host: localhost:8080\r\nContent-Type: text/plain\r\n", &loopback), Err(415));
+        assert_eq!(check("Host: localhost:8080\r\n", &loopback), Err(415));
+
+        let network = Access { address: "0.0.0.0:8080".parse().unwrap(), token: Some("secret".to_string()) };
+        assert_eq!(check("Host: 192.168.1.5:8080\r\nContent-Type: application/json\r\n", &network), Ok(()));
+        let other_token = Access { token: Some("other".to_string()), ..network };
+        assert_eq!(check("Host: 192.168.1.5:8080\r\nContent-Type: application/json\r\n", &other_token), Err(401));
+        assert_eq!(check("Host: 192.168.1.5:8080\r\nAuthorization: Bearer other\r\nContent-Type: application/json\r\n", &other_token), Ok(()));
+    }
+
+    #[test]
+    fn allowed_hosts() {
+        let address = |address: &str| address.parse::<SocketAddr>().unwrap();
+
+        assert!(is_host_allowed("LOCALHOST:8080", address("127.0.0.1:8080")));
+        assert!(is_host_allowed("localhost", address("127.0.0.1:80")));
+        assert!(is_host_allowed("[::1]:8080", address("[::1]:8080")));
+        assert!(is_host_allowed("192.168.1.5:8080", address("192.168.1.5:8080")));
+        assert!(!is_host_allowed("localhost:8080", address("192.168.1.5:8080")));
+        assert!(!is_host_allowed("[::1]", address("[::1]:8080")));
+        assert!(!is_host_allowed("localhost:x", address("127.0.0.1:8080")));
+        assert_eq!(generate_token().len(), 32);
+        assert_ne!(generate_token(), generate_token());
+    }
+
+    #[test]
+    fn errors_display() {
+        let error = ServeCommandError::CannotBind { address: "localhost:80".to_string(), bind_error: io::Error::other("error text") };
+        assert_eq!(error.to_string(), "cannot listen on 'localhost:80'");
+    }
+}
@@ -0,0 +1,233 @@
+use std::error::Error;
+use std::fmt;
+use std::io::{self, BufRead, Read, Write};
+
+const MAX_HEADERS_LENGTH: usize = 16 * 1024;
+const MAX_BODY_LENGTH: usize = 64 * 1024;
+
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub query: Vec<(String, String)>,
+    // names are lowercase
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: String,
+}
+
+#[derive(Debug)]
+pub enum HttpError {
+    CannotRead(io::Error),
+    RequestInvalid,
+    RequestTooLarge,
+}
+
+impl std::error::Error for HttpError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::CannotRead(read_error) => Some(read_error),
+            Self::RequestInvalid | Self::RequestTooLarge => None,
+        }
+    }
+}
+
+impl fmt::Display for HttpError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::CannotRead(_read_error) => write!(f, "cannot read request"),
+            Self::RequestInvalid => write!(f, "request is not valid"),
+            Self::RequestTooLarge => write!(f, "request is too large"),
+        }
+    }
+}
+
+impl Request {
+    pub fn query_values<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> {
+        self.query.iter().filter(move |(key, _)| key == name).map(|(_, value)| value.as_str())
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(header_name, _)| header_name.eq_ignore_ascii_case(name)).map(|(_, value)| value.as_str())
+    }
+}
+
+impl Response {
+    pub fn new(status: u16, content_type: &'static str, body: String) -> Self {
+        Self { status, content_type, body }
+    }
+
+    pub fn json(status: u16, value: &serde_json::Value) -> Self {
+        Self::new(status, "application/json", value.to_string())
+    }
+
+    pub fn error(status: u16, message: &str) -> Self {
+        Self::json(status, &serde_json::json!({ "error": message }))
+    }
+}
+
+// Reads HTTP/1.x request: request line, headers and `Content-Length` sized body.
+pub fn read_request<R: BufRead>(reader: &mut R) -> Result<Request, HttpError> {
+    let mut headers_length = 0;
+    let mut read_line = |reader: &mut R| -> Result<String, HttpError> {
+        let mut line = String::new();
+        let read_bytes = reader.by_ref().take((MAX_HEADERS_LENGTH - headers_length) as u64)
+            .read_line(&mut line)
+            .map_err(HttpError::CannotRead)?;
+        headers_length += read_bytes;
+        match read_bytes {
+            0 => Err(HttpError::RequestInvalid),
+            _ if !line.ends_with('\n') => Err(HttpError::RequestTooLarge),
+            _ => Ok(line.trim_end().to_string()),
+        }
+    };
+
+    let request_line = read_line(reader)?;
+    let mut request_line_parts = request_line.split_whitespace();
+    let (method, target) = match (request_line_parts.next(), request_line_parts.next()) {
+        (Some(method), Some(target)) => (method.to_string(), target.to_string()),
+        _ => return Err(HttpError::RequestInvalid),
+    };
+
+    let mut headers = Vec::new();
+    loop {
+        let header = read_line(reader)?;
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            headers.push((name.trim().to_lowercase(), value.trim().to_string()));
+        }
+    }
+
+    let content_length = match headers.iter().find(|(name, _)| name == "content-length") {
+        Some((_, value)) => value.parse::<usize>().map_err(|_| HttpError::RequestInvalid)?,
+        None => 0,
+    };
+    if content_length > MAX_BODY_LENGTH {
+        return Err(HttpError::RequestTooLarge);
+    }
+
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).map_err(HttpError::CannotRead)?;
+
+    let (path, query_str) = target.split_once('?').unwrap_or((&target, ""));
+    let query = query_str.split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(key), percent_decode(value))
+        })
+        .collect();
+
+    Ok(Request { method, path: percent_decode(path), query, headers, body })
+}
+
+pub fn write_response<W: Write>(writer: &mut W, response: &Response) -> io::Result<()> {
+    write!(writer, "HTTP/1.1 {} {}\r\nContent-Type: {}; charset=utf-8\r\nContent-Length: {}\r\n\
+                    Cache-Control: no-store\r\nConnection: close\r\n\r\n{}",
+           response.status, reason_phrase(response.status), response.content_type, response.body.len(), response.body)?;
+    writer.flush()
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        408 => "Request Timeout",
+        409 => "Conflict",
+        413 => "Payload Too Large",
+        415 => "Unsupported Media Type",
+        _ => "Internal Server Error",
+    }
+}
+
+// Invalid escapes are kept as is.
+fn percent_decode(encoded: &str) -> String {
+    let bytes = encoded.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let escaped = bytes.get(i + 1..i + 3)
+            .filter(|_| bytes[i] == b'%')
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], escaped) {
+            (_, Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            },
+            (b'+', None) => {
+                decoded.push(b' ');
+                i += 1;
+            },
+            (byte, None) => {
+                decoded.push(byte);
+                i += 1;
+            },
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn request_reading() {
+        let mut input = "POST /api/entries?tag=run&tag=long%20walk&from=2020-01-01 HTTP/1.1\r\n\
+                         Host: localhost\r\ncontent-type: application/json\r\nContent-Length: 12\r\n\r\n{\"score\":1}\n".as_bytes();
+        let request = read_request(&mut input).unwrap();
+
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/api/entries");
+        assert_eq!(request.query_values("tag").collect::<Vec<&str>>(), vec!["run", "long walk"]);
+        assert_eq!(request.query_values("from").collect::<Vec<&str>>(), vec!["2020-01-01"]);
+        assert_eq!(request.header("Content-Type"), Some("application/json"));
+        assert_eq!(request.header("host"), Some("localhost"));
+        assert_eq!(request.header("origin"), None);
+        assert_eq!(request.body, b"{\"score\":1}\n");
+    }
+
+    #[test]
+    fn invalid_requests() {
+        assert!(matches!(read_request(&mut "".as_bytes()), Err(HttpError::RequestInvalid)));
+        assert!(matches!(read_request(&mut "GET\r\n\r\n".as_bytes()), Err(HttpError::RequestInvalid)));
+        assert!(matches!(read_request(&mut "GET / HTTP/1.1\r\nContent-Length: x\r\n\r\n".as_bytes()),
+                         Err(HttpError::RequestInvalid)));
+        assert!(matches!(read_request(&mut "GET / HTTP/1.1\r\nContent-Length: 1000000\r\n\r\n".as_bytes()),
+                         Err(HttpError::RequestTooLarge)));
+
+        let long_header = format!("GET / HTTP/1.1\r\nX: {}\r\n\r\n", "a".repeat(MAX_HEADERS_LENGTH));
+        assert!(matches!(read_request(&mut long_header.as_bytes()), Err(HttpError::RequestTooLarge)));
+    }
+
+    #[test]
+    fn response_writing() {
+        let mut output = Vec::new();
+        write_response(&mut output, &Response::error(404, "not found")).unwrap();
+
+        assert_eq!(String::from_utf8(output).unwrap(),
+                   "HTTP/1.1 404 Not Found\r\nContent-Type: application/json; charset=utf-8\r\nContent-Length: 21\r\n\
+                    Cache-Control: no-store\r\nConnection: close\r\n\r\n{\"error\":\"not found\"}");
+    }
+
+    #[test]
+    fn percent_decoding() {
+        assert_eq!(percent_decode("a%20b+c%2Cd"), "a b c,d");
+        assert_eq!(percent_decode("%zz%4"), "%zz%4");
+        assert_eq!(percent_decode("%D0%B1"), "б");
+    }
+}
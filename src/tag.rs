@@ -0,0 +1,162 @@
+use std::collections::{BTreeMap, HashSet};
+use std::iter;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::attribute;
+use crate::{JOURNAL_SEPARATOR, TAGS_SEPARATOR};
+
+const TAG_HIERARCHY_SEPARATOR: char = '/';
+
+// Configured rules which make differently typed tags the same, e.g. `Sports ` and `спорт` into `sport`.
+#[derive(Clone, Default)]
+pub struct TagRules {
+    // trim, case fold and NFC-normalize tags
+    normalize: bool,
+    aliases: BTreeMap<String, String>,
+}
+
+impl TagRules {
+    // Aliases and tags they stand for are normalized the same way as tags.
+    pub fn new(normalize: bool, aliases: BTreeMap<String, String>) -> Self {
+        let rules = Self { normalize, aliases: BTreeMap::new() };
+        let aliases = aliases.iter()
+            .map(|(alias, tag)| (rules.normalized(alias), rules.normalized(tag)))
+            .collect();
+
+        Self { aliases, ..rules }
+    }
+
+    pub fn apply(&self, tag: &str) -> String {
+        let tag = self.normalized(tag);
+        match self.aliases.get(&tag) {
+            Some(aliased_tag) => aliased_tag.clone(),
+            None => tag,
+        }
+    }
+
+    // Tags which become empty are dropped.
+    pub fn apply_all(&self, tags: &HashSet<String>) -> HashSet<String> {
+        tags.iter()
+            .map(|tag| self.apply(tag))
+            .filter(|tag| !tag.is_empty())
+            .collect()
+    }
+
+    // Filter tags are made the same as journal tags, so `--tag Sports` finds `sport` records.
+    // `KEY=VALUE` attributes aren't changed in the journal, so they are left as is.
+    pub fn filter_tag(&self, tag: &str) -> String {
+        match attribute::parse_attribute(tag) {
+            Some(_) => tag.to_string(),
+            None => self.apply(tag),
+        }
+    }
+
+    // Every level of a nested tag is trimmed, so `Sport / Running` becomes `sport/running`.
+    fn normalized(&self, tag: &str) -> String {
+        if !self.normalize {
+            return tag.to_string();
+        }
+
+        tag.split(TAG_HIERARCHY_SEPARATOR)
+            .map(|part| part.trim().to_lowercase().nfc().collect::<String>())
+            .collect::<Vec<String>>()
+            .join(&TAG_HIERARCHY_SEPARATOR.to_string())
+    }
+}
+
+// Tags can not contain journal separators, and `KEY=VALUE` would be read back as an attribute.
+pub fn is_valid(tag: &str) -> bool {
+    !tag.trim().is_empty() &&
+        !tag.contains(TAGS_SEPARATOR) &&
+        !tag.contains(JOURNAL_SEPARATOR) &&
+        !tag.contains(&['\n', '\r'][..]) &&
+        attribute::parse_attribute(tag).is_none()
+}
+
+// Renames the tag and its children, e.g. `sport` to `sports` renames `sport/running` to `sports/running`.
+// Returns `None` if the tag is neither the one nor its child.
+pub fn renamed(tag: &str, from: &str, to: &str) -> Option<String> {
+    if matches(tag, from) { Some(format!("{}{}", to, &tag[from.len()..])) } else { None }
+}
+
+// `sport/running` is matched by itself and by its parent `sport`, but not by `sport/run`.
+pub fn matches(tag: &str, filter_tag: &str) -> bool {
+    tag.strip_prefix(filter_tag)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with(TAG_HIERARCHY_SEPARATOR))
+}
+
+// The tag followed by its parents, e.g. `sport/running/trail`, `sport/running`, `sport`.
+pub fn with_parents(tag: &str) -> impl Iterator<Item = &str> {
+    iter::successors(Some(tag), |tag| tag.rsplit_once(TAG_HIERARCHY_SEPARATOR).map(|(parent, _)| parent))
+        .filter(|tag| !tag.is_empty())
+}
+
+// Path of the tag in the hierarchy, sorting by it keeps children right after their parents.
+pub fn path(tag: &str) -> Vec<&str> {
+    tag.split(TAG_HIERARCHY_SEPARATOR).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tags_matching() {
+        assert!(matches("sport", "sport"));
+        assert!(matches("sport/running", "sport"));
+        assert!(matches("sport/running/trail", "sport/running"));
+        assert!(!matches("sport/running", "sport/run"));
+        assert!(!matches("sports", "sport"));
+        assert!(!matches("sport", "sport/running"));
+    }
+
+    #[test]
+    fn tag_parents() {
+        assert_eq!(with_parents("sport/running/trail").collect::<Vec<&str>>(), vec!["sport/running/trail", "sport/running", "sport"]);
+        assert_eq!(with_parents("sport").collect::<Vec<&str>>(), vec!["sport"]);
+        assert_eq!(with_parents("/sport").collect::<Vec<&str>>(), vec!["/sport"]);
+    }
+
+    #[test]
+    fn tags_renaming() {
+        assert_eq!(renamed("sport", "sport", "sports"), Some("sports".to_string()));
+        assert_eq!(renamed("sport/running", "sport", "hobby/sport"), Some("hobby/sport/running".to_string()));
+        assert_eq!(renamed("sports", "sport", "hobby"), None);
+        assert!(is_valid("sport/running"));
+        assert!(!is_valid(" "));
+        assert!(!is_valid("run,swim"));
+        assert!(!is_valid("weather=rain"));
+    }
+
+    #[test]
+    fn tag_rules() {
+        let aliases = vec![("Sports".to_string(), "sport".to_string()), ("спорт".to_string(), "Sport".to_string())];
+        let rules = TagRules::new(true, aliases.clone().into_iter().collect());
+
+        assert_eq!(rules.apply("Sports "), "sport");
+        assert_eq!(rules.apply("СПОРТ"), "sport");
+        assert_eq!(rules.apply(" Sport / Running"), "sport/running");
+        // `e` followed by combining acute accent is composed into `é`
+        assert_eq!(rules.apply("Cafe\u{301}"), "caf\u{e9}");
+        assert_eq!(rules.apply_all(&vec![" ".to_string(), "sport".to_string(), "Sports".to_string()].into_iter().collect()),
+            vec!["sport".to_string()].into_iter().collect());
+
+        let aliases_only = TagRules::new(false, aliases.into_iter().collect());
+        assert_eq!(aliases_only.apply("Sports"), "sport");
+        assert_eq!(aliases_only.apply("спорт"), "Sport");
+        assert_eq!(aliases_only.apply("sports "), "sports ");
+        assert_eq!(TagRules::default().apply("Sports"), "Sports");
+
+        let rules = TagRules::new(true, BTreeMap::new());
+        assert_eq!(rules.filter_tag("Sport/Running "), "sport/running");
+        assert_eq!(rules.filter_tag("weather=Rain"), "weather=Rain");
+    }
+
+    #[test]
+    fn tags_sorting_by_path() {
+        let mut tags = vec!["sport-club", "sport/running", "sport"];
+        tags.sort_by_key(|tag| path(tag));
+
+        assert_eq!(tags, vec!["sport", "sport/running", "sport-club"]);
+    }
+}
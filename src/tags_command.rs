@@ -0,0 +1,177 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+
+use crate::daily_score::DailyScore;
+use crate::mood_report::{MoodReport, ScoreStats};
+use crate::filter::Filter;
+use crate::metric::{Metric, ReportValue};
+use crate::GlobalConfig;
+use crate::journal;
+use crate::tag::{self, TagRules};
+
+const TREE_INDENT: &str = "  ";
+
+pub struct TagsCommand {
+    pub global_config: GlobalConfig,
+    pub action: TagsAction,
+    pub filter: Filter,
+    pub metric: Metric,
+}
+
+pub enum TagsAction {
+    List,
+    // renames the tag and its children in records matching the filter
+    Rename { from: String, to: String },
+}
+
+#[derive(Debug)]
+pub enum TagsCommandError {
+    JournalReadError(journal::JournalError),
+    JournalWriteError(journal::JournalError),
+}
+
+impl std::error::Error for TagsCommandError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::JournalReadError(journal_error) => Some(journal_error),
+            Self::JournalWriteError(journal_error) => Some(journal_error),
+        }
+    }
+}
+
+impl fmt::Display for TagsCommandError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::JournalReadError(_journal_error) => write!(f, "cannot parse journal"),
+            Self::JournalWriteError(_journal_error) => write!(f, "cannot write journal"),
+        }
+    }
+}
+
+impl TagsCommand {
+    pub fn run(self) -> Result<(), TagsCommandError> {
+        match &self.action {
+            TagsAction::List => self.list(),
+            TagsAction::Rename { from, to } => self.rename(from, to),
+        }
+    }
+
+    fn list(&self) -> Result<(), TagsCommandError> {
+        let daily_scores = journal::read_if_exists(&self.global_config.journal_file_path, &self.global_config.tag_rules)
+            .map_err(TagsCommandError::JournalReadError)?;
+        let daily_scores = self.global_config.report_daily_scores(daily_scores, &self.filter);
+
+        let mood_report = MoodReport { daily_scores: &daily_scores, filter: &Filter::default(), metric: &self.metric, timezone: self.global_config.timezone };
+        for line in tree_lines(&mood_report.tag_stats()) {
+            println!("{}", line);
+        }
+
+        Ok(())
+    }
+
+    // Journal is read as is, so nothing but the renamed tag is changed, and then written at once.
+    fn rename(&self, from: &str, to: &str) -> Result<(), TagsCommandError> {
+        let journal_file_path = &self.global_config.journal_file_path;
+        let mut daily_scores = journal::read_if_exists(journal_file_path, &TagRules::default())
+            .map_err(TagsCommandError::JournalReadError)?;
+
+        let to = self.global_config.tag_rules.apply(to);
+        let renamed_count = rename_tag(&mut daily_scores, &self.filter, from, &to);
+        if renamed_count == 0 {
+            println!("No records with '{}' tag", from);
+            return Ok(());
+        }
+
+        journal::write(journal_file_path, &daily_scores)
+            .map_err(TagsCommandError::JournalWriteError)?;
+        println!("'{}' is renamed to '{}' in {} records", from, to, renamed_count);
+
+        Ok(())
+    }
+}
+
+// Returns number of changed records.
+fn rename_tag(daily_scores: &mut [DailyScore], filter: &Filter, from: &str, to: &str) -> usize {
+    let mut renamed_count = 0;
+
+    for daily_score in daily_scores.iter_mut().filter(|daily_score| filter.matches(daily_score)) {
+        if !daily_score.tags.iter().any(|own_tag| tag::matches(own_tag, from)) {
+            continue;
+        }
+        daily_score.tags = daily_score.tags.iter()
+            .map(|own_tag| tag::renamed(own_tag, from, to).unwrap_or_else(|| own_tag.clone()))
+            .collect();
+        renamed_count += 1;
+    }
+
+    renamed_count
+}
+
+// Child tags are printed under their parents by the last part of their name.
+fn tree_lines(tag_stats: &BTreeMap<String, ScoreStats>) -> Vec<String> {
+    let mut tags = tag_stats.iter().collect::<Vec<(&String, &ScoreStats)>>();
+    tags.sort_by_key(|(tag, _)| tag::path(tag));
+
+    tags.into_iter()
+        .map(|(tag, stats)| {
+            // stats of parents are rolled up, so every parent is printed above
+            let depth = tag::with_parents(tag).count() - 1;
+            let name = if depth > 0 { tag::path(tag).last().copied().unwrap_or_default() } else { tag.as_str() };
+
+            format!("{}{}: {} ({} records, average {})",
+                    TREE_INDENT.repeat(depth), name, ReportValue(stats.sum), stats.count, ReportValue(stats.mean()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tag_renaming() {
+        let daily_score = |tags: &[&str]| {
+            let mut daily_score = DailyScore::with_score(1);
+            daily_score.tags = tags.iter().map(|tag| tag.to_string()).collect();
+            daily_score
+        };
+        let mut daily_scores = vec![daily_score(&["Sports", "work"]), daily_score(&["Sports/running", "sport"]), daily_score(&["sports"])];
+
+        assert_eq!(rename_tag(&mut daily_scores, &Filter::default(), "Sports", "sport"), 2);
+        assert_eq!(daily_scores.iter().map(DailyScore::tags_string).collect::<Vec<String>>(),
+            vec!["sport,work", "sport,sport/running", "sports"]);
+
+        let filter = Filter::with_tags(vec!["work".to_string()].into_iter().collect());
+        assert_eq!(rename_tag(&mut daily_scores, &filter, "sport", "hobby"), 1);
+        assert_eq!(daily_scores[0].tags_string(), "hobby,work");
+        assert_eq!(rename_tag(&mut daily_scores, &Filter::default(), "games", "hobby"), 0);
+    }
+
+    #[test]
+    fn tags_tree() {
+        let tag_stats = vec![
+            ("sport".to_string(), ScoreStats { count: 3, sum: 4.0 }),
+            ("sport-club".to_string(), ScoreStats { count: 1, sum: -1.0 }),
+            ("sport/running".to_string(), ScoreStats { count: 2, sum: 3.0 }),
+            ("sport/running/trail".to_string(), ScoreStats { count: 1, sum: 2.0 }),
+            ("/root".to_string(), ScoreStats { count: 1, sum: 1.0 }),
+        ].into_iter().collect();
+
+        assert_eq!(tree_lines(&tag_stats), vec![
+            "/root: 1 (1 records, average 1)",
+            "sport: 4 (3 records, average 1.33)",
+            "  running: 3 (2 records, average 1.5)",
+            "    trail: 2 (1 records, average 2)",
+            "sport-club: -1 (1 records, average -1)",
+        ]);
+    }
+
+    #[test]
+    fn errors_display() {
+        let journal_error = || journal::JournalError::XlsxWriteError(std::io::Error::other("error text"));
+
+        assert_eq!(TagsCommandError::JournalReadError(journal_error()).to_string(), "cannot parse journal");
+        assert_eq!(TagsCommandError::JournalWriteError(journal_error()).to_string(), "cannot write journal");
+    }
+}
@@ -2,3 +2,12 @@
 pub fn build_cli_args(args_str: &str) -> impl Iterator<Item = String> + '_ {
     args_str.split(' ').map(|s| s.to_string())
 }
+
+// Runs the command as if there were no config files, so the ones of the machine do not change results.
+#[cfg(test)]
+pub fn run_without_config<I>(cli_args: I) -> Result<(), crate::CliError>
+where
+    I: Iterator<Item = String>,
+{
+    crate::run_with_config(cli_args, || Ok(crate::config::Config::default()))
+}
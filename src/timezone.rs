@@ -0,0 +1,184 @@
+use chrono::{DateTime, FixedOffset, Local, NaiveDate, NaiveDateTime, Offset, TimeZone, Utc};
+use chrono_tz::Tz;
+
+use crate::daily_score::DailyScore;
+
+// Zone records are written and reported in, either a fixed offset like `+03:00`
+// or an IANA zone like `Europe/Berlin`, which follows its DST changes.
+// Helpers take `None` for the system local zone.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Timezone {
+    Offset(FixedOffset),
+    Zone(Tz),
+}
+
+impl Timezone {
+    pub fn parse(timezone_str: &str) -> Option<Self> {
+        parse_offset(timezone_str).map(Self::Offset)
+            .or_else(|| timezone_str.parse::<Tz>().ok().map(Self::Zone))
+    }
+}
+
+// Which dates records are reported on: the ones they were made on wherever that was,
+// or the ones of the reporting zone, so a late evening record made abroad can move to the next day.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ReportDays {
+    #[default]
+    Entry,
+    Timezone,
+}
+
+impl ReportDays {
+    pub fn parse(report_days_str: &str) -> Option<Self> {
+        match report_days_str {
+            "entry" => Some(Self::Entry),
+            "timezone" => Some(Self::Timezone),
+            _ => None,
+        }
+    }
+}
+
+pub fn convert<Z: TimeZone>(timezone: Option<Timezone>, datetime: &DateTime<Z>) -> DateTime<FixedOffset> {
+    match timezone {
+        Some(Timezone::Offset(offset)) => datetime.with_timezone(&offset),
+        Some(Timezone::Zone(zone)) => with_fixed_offset(datetime.with_timezone(&zone)),
+        None => with_fixed_offset(datetime.with_timezone(&Local)),
+    }
+}
+
+pub fn now(timezone: Option<Timezone>) -> DateTime<FixedOffset> {
+    convert(timezone, &Utc::now())
+}
+
+pub fn today(timezone: Option<Timezone>) -> NaiveDate {
+    now(timezone).naive_local().date()
+}
+
+// Date of the moment in the zone, report data is timestamped with such moments.
+pub fn date_of(timezone: Option<Timezone>, timestamp: i64) -> NaiveDate {
+    convert(timezone, &Utc.timestamp(timestamp, 0)).naive_local().date()
+}
+
+// `None` for times skipped when clocks are moved forward, the earlier one for times repeated when they are moved back.
+pub fn from_local_datetime(timezone: Option<Timezone>, naive_datetime: &NaiveDateTime) -> Option<DateTime<FixedOffset>> {
+    match timezone {
+        Some(Timezone::Offset(offset)) => offset.from_local_datetime(naive_datetime).earliest(),
+        Some(Timezone::Zone(zone)) => zone.from_local_datetime(naive_datetime).earliest().map(with_fixed_offset),
+        None => Local.from_local_datetime(naive_datetime).earliest().map(with_fixed_offset),
+    }
+}
+
+// Days which skip midnight for DST start at the first hour they have.
+pub fn start_of_day(timezone: Option<Timezone>, date: NaiveDate) -> DateTime<FixedOffset> {
+    (0..24)
+        .find_map(|hour| from_local_datetime(timezone, &date.and_hms(hour, 0, 0)))
+        .unwrap_or_else(|| convert(timezone, &Utc.from_utc_datetime(&date.and_hms(0, 0, 0))))
+}
+
+// Records are moved into the zone if days are reported by it, so their local dates are the zone's ones.
+pub fn apply(report_days: ReportDays, timezone: Option<Timezone>, daily_scores: Vec<DailyScore>) -> Vec<DailyScore> {
+    match report_days {
+        ReportDays::Entry => daily_scores,
+        ReportDays::Timezone => daily_scores.into_iter()
+            .map(|daily_score| DailyScore { datetime: convert(timezone, &daily_score.datetime), ..daily_score })
+            .collect(),
+    }
+}
+
+fn with_fixed_offset<Z: TimeZone>(datetime: DateTime<Z>) -> DateTime<FixedOffset> {
+    let offset = datetime.offset().fix();
+    datetime.with_timezone(&offset)
+}
+
+// Accepts offsets like `+03:00`, `-0530` or `UTC`.
+fn parse_offset(offset_str: &str) -> Option<FixedOffset> {
+    if offset_str.eq_ignore_ascii_case("utc") || offset_str == "Z" {
+        return Some(FixedOffset::east(0));
+    }
+
+    let sign = match offset_str.chars().next()? {
+        '+' => 1,
+        '-' => -1,
+        _ => return None,
+    };
+    let digits = offset_str[1..].replace(':', "");
+    if digits.len() != 4 || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let hours = digits[..2].parse::<i32>().ok()?;
+    let minutes = digits[2..].parse::<i32>().ok()?;
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono_tz::{America, Europe};
+
+    use super::*;
+
+    fn berlin() -> Option<Timezone> {
+        Some(Timezone::Zone(Europe::Berlin))
+    }
+
+    #[test]
+    fn timezones_parsing() {
+        assert_eq!(Timezone::parse("+03:30"), Some(Timezone::Offset(FixedOffset::east(3 * 3600 + 30 * 60))));
+        assert_eq!(Timezone::parse("UTC"), Some(Timezone::Offset(FixedOffset::east(0))));
+        assert_eq!(Timezone::parse("Europe/Berlin"), berlin());
+        assert_eq!(Timezone::parse("Mars"), None);
+
+        assert_eq!(ReportDays::parse("timezone"), Some(ReportDays::Timezone));
+        assert_eq!(ReportDays::parse("local"), None);
+    }
+
+    #[test]
+    fn offset_parsing() {
+        assert_eq!(parse_offset("+03:00"), Some(FixedOffset::east(3 * 3600)));
+        assert_eq!(parse_offset("-0530"), Some(FixedOffset::west(5 * 3600 + 30 * 60)));
+        assert_eq!(parse_offset("UTC"), Some(FixedOffset::east(0)));
+        assert_eq!(parse_offset("03:00"), None);
+        assert_eq!(parse_offset("+3"), None);
+    }
+
+    #[test]
+    fn zone_offsets_follow_dst() {
+        let winter = convert(berlin(), &Utc.ymd(2021, 3, 28).and_hms(0, 30, 0));
+        let summer = convert(berlin(), &Utc.ymd(2021, 3, 28).and_hms(1, 30, 0));
+
+        assert_eq!(winter.to_rfc3339(), "2021-03-28T01:30:00+01:00");
+        assert_eq!(summer.to_rfc3339(), "2021-03-28T03:30:00+02:00");
+        assert_eq!(date_of(berlin(), Utc.ymd(2021, 10, 30).and_hms(22, 30, 0).timestamp()), NaiveDate::from_ymd(2021, 10, 31));
+    }
+
+    #[test]
+    fn days_across_dst() {
+        // clocks are moved forward at 2:00 and back at 3:00, so these days are 23 and 25 hours long
+        let day_hours = |date: NaiveDate| (start_of_day(berlin(), date.succ()) - start_of_day(berlin(), date)).num_hours();
+        assert_eq!(day_hours(NaiveDate::from_ymd(2021, 3, 28)), 23);
+        assert_eq!(day_hours(NaiveDate::from_ymd(2021, 10, 31)), 25);
+        assert_eq!(day_hours(NaiveDate::from_ymd(2021, 11, 1)), 24);
+
+        // repeated time is taken before clocks are moved back, skipped time does not exist
+        assert_eq!(from_local_datetime(berlin(), &NaiveDate::from_ymd(2021, 10, 31).and_hms(2, 30, 0)).unwrap().to_rfc3339(),
+            "2021-10-31T02:30:00+02:00");
+        assert_eq!(from_local_datetime(berlin(), &NaiveDate::from_ymd(2021, 3, 28).and_hms(2, 30, 0)), None);
+
+        // DST used to start at midnight in Brazil
+        let sao_paulo = Some(Timezone::Zone(America::Sao_Paulo));
+        assert_eq!(start_of_day(sao_paulo, NaiveDate::from_ymd(2018, 11, 4)).to_rfc3339(), "2018-11-04T01:00:00-02:00");
+    }
+
+    #[test]
+    fn report_days() {
+        let record_at = |datetime| DailyScore { datetime, ..DailyScore::with_score(1) };
+        // made in Moscow at half past midnight, while it is still the previous evening in Berlin
+        let daily_scores = vec![record_at(FixedOffset::east(3 * 3600).ymd(2021, 10, 31).and_hms(0, 30, 0))];
+
+        let entry_days = apply(ReportDays::Entry, berlin(), daily_scores.clone());
+        assert_eq!(entry_days[0].datetime.naive_local().date(), NaiveDate::from_ymd(2021, 10, 31));
+
+        let zone_days = apply(ReportDays::Timezone, berlin(), daily_scores);
+        assert_eq!(zone_days[0].datetime.to_rfc3339(), "2021-10-30T23:30:00+02:00");
+    }
+}
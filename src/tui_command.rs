@@ -0,0 +1,165 @@
+use crossterm::{cursor, event, queue, style, terminal};
+use crossterm::event::{Event, KeyCode, KeyEventKind, KeyModifiers};
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Write};
+
+use crate::GlobalConfig;
+use crate::journal;
+use crate::timezone;
+
+mod app;
+mod view;
+
+use app::{App, Key};
+use view::{Color, Line};
+
+pub struct TuiCommand {
+    pub global_config: GlobalConfig,
+}
+
+#[derive(Debug)]
+pub enum TuiCommandError {
+    JournalReadError(journal::JournalError),
+    JournalWriteError(journal::JournalError),
+    CannotUseTerminal(io::Error),
+}
+
+impl std::error::Error for TuiCommandError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::JournalReadError(journal_error) => Some(journal_error),
+            Self::JournalWriteError(journal_error) => Some(journal_error),
+            Self::CannotUseTerminal(terminal_error) => Some(terminal_error),
+        }
+    }
+}
+
+impl fmt::Display for TuiCommandError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::JournalReadError(_journal_error) => write!(f, "cannot parse journal"),
+            Self::JournalWriteError(_journal_error) => write!(f, "cannot write journal"),
+            Self::CannotUseTerminal(_terminal_error) => write!(f, "cannot use terminal"),
+        }
+    }
+}
+
+// Raw mode and alternate screen are left on drop, so the terminal is restored on errors too.
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn enter() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        let guard = Self;
+        crossterm::execute!(io::stdout(), terminal::EnterAlternateScreen, cursor::Hide)?;
+        Ok(guard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = crossterm::execute!(io::stdout(), style::ResetColor, cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+impl TuiCommand {
+    pub fn run(self) -> Result<(), TuiCommandError> {
+        let journal_file_path = &self.global_config.journal_file_path;
+        let daily_scores = journal::read_if_exists(journal_file_path, &self.global_config.tag_rules)
+            .map_err(TuiCommandError::JournalReadError)?;
+
+        let today = timezone::today(self.global_config.timezone);
+        let mut app = App::new(daily_scores, today, self.global_config.timezone, self.global_config.tag_rules.clone(),
+                               self.global_config.score_scale.clone(), self.global_config.same_day_policy);
+
+        let _terminal_guard = TerminalGuard::enter().map_err(TuiCommandError::CannotUseTerminal)?;
+        let mut stdout = io::stdout();
+
+        while !app.quit {
+            let (width, height) = terminal::size().map_err(TuiCommandError::CannotUseTerminal)?;
+            let lines = view::render(&app, &self.global_config, width.into(), height.into());
+            draw(&mut stdout, &lines).map_err(TuiCommandError::CannotUseTerminal)?;
+
+            // redraw on resize and other events
+            let key_event = match event::read().map_err(TuiCommandError::CannotUseTerminal)? {
+                Event::Key(key_event) if key_event.kind != KeyEventKind::Release => key_event,
+                _ => continue,
+            };
+            if key_event.modifiers.contains(KeyModifiers::CONTROL) && key_event.code == KeyCode::Char('c') {
+                break;
+            }
+            if let Some(key) = map_key(key_event.code) {
+                app.handle_key(key);
+            }
+
+            // journal is written right away, so nothing is lost if the terminal is closed
+            if app.dirty {
+                journal::write(journal_file_path, &app.daily_scores)
+                    .map_err(TuiCommandError::JournalWriteError)?;
+                app.dirty = false;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn map_key(key_code: KeyCode) -> Option<Key> {
+    match key_code {
+        KeyCode::Char(c) => Some(Key::Char(c)),
+        KeyCode::Left => Some(Key::Left),
+        KeyCode::Right => Some(Key::Right),
+        KeyCode::Up => Some(Key::Up),
+        KeyCode::Down => Some(Key::Down),
+        KeyCode::PageUp => Some(Key::PageUp),
+        KeyCode::PageDown => Some(Key::PageDown),
+        KeyCode::Tab => Some(Key::Tab),
+        KeyCode::Enter => Some(Key::Enter),
+        KeyCode::Backspace => Some(Key::Backspace),
+        KeyCode::Esc => Some(Key::Esc),
+        _ => None,
+    }
+}
+
+fn draw<W: Write>(output: &mut W, lines: &[Line]) -> io::Result<()> {
+    queue!(output, terminal::Clear(terminal::ClearType::All))?;
+
+    for (row, line) in lines.iter().enumerate() {
+        queue!(output, cursor::MoveTo(0, row as u16))?;
+        for span in line {
+            let color = match span.color {
+                Color::Default => style::Color::Reset,
+                Color::Positive => style::Color::Green,
+                Color::Negative => style::Color::Red,
+                Color::Dim => style::Color::DarkGrey,
+                Color::Title => style::Color::Cyan,
+            };
+            queue!(output, style::SetForegroundColor(color))?;
+            if span.reversed {
+                queue!(output, style::SetAttribute(style::Attribute::Reverse))?;
+            }
+            queue!(output, style::Print(&span.text), style::SetAttribute(style::Attribute::Reset))?;
+        }
+    }
+
+    output.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_mapping() {
+        assert_eq!(map_key(KeyCode::Char('a')), Some(Key::Char('a')));
+        assert_eq!(map_key(KeyCode::PageDown), Some(Key::PageDown));
+        assert_eq!(map_key(KeyCode::F(1)), None);
+    }
+
+    #[test]
+    fn errors_display() {
+        assert_eq!(TuiCommandError::CannotUseTerminal(io::Error::other("error text")).to_string(), "cannot use terminal");
+    }
+}
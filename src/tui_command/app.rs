@@ -0,0 +1,465 @@
+use chrono::{Datelike, DateTime, Duration, FixedOffset, NaiveDate};
+
+use crate::add_command::{self, SameDayPolicy};
+use crate::attribute;
+use crate::cli::CommandSpec;
+use crate::daily_score::DailyScore;
+use crate::score_scale::ScoreScale;
+use crate::tag::TagRules;
+use crate::timezone::{self, Timezone};
+
+// Terminal independent keys, so the app can be driven from tests.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Key {
+    Char(char),
+    Left,
+    Right,
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    Tab,
+    Enter,
+    Backspace,
+    Esc,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Mode {
+    Normal,
+    Input { purpose: InputPurpose, text: String },
+    ConfirmDelete,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InputPurpose {
+    Add,
+    Edit,
+}
+
+pub struct App {
+    pub daily_scores: Vec<DailyScore>,
+    pub today: NaiveDate,
+    pub selected_date: NaiveDate,
+    pub selected_entry: usize,
+    pub mode: Mode,
+    pub message: Option<String>,
+    // journal has to be written
+    pub dirty: bool,
+    pub quit: bool,
+    timezone: Option<Timezone>,
+    tag_rules: TagRules,
+    pub score_scale: ScoreScale,
+    same_day_policy: SameDayPolicy,
+}
+
+impl App {
+    pub fn new(daily_scores: Vec<DailyScore>, today: NaiveDate, timezone: Option<Timezone>, tag_rules: TagRules, score_scale: ScoreScale,
+               same_day_policy: SameDayPolicy) -> Self {
+        Self {
+            daily_scores,
+            today,
+            selected_date: today,
+            selected_entry: 0,
+            mode: Mode::Normal,
+            message: None,
+            dirty: false,
+            quit: false,
+            timezone,
+            tag_rules,
+            score_scale,
+            same_day_policy,
+        }
+    }
+
+    // Indices of records made on the date.
+    pub fn day_entries(&self, date: NaiveDate) -> Vec<usize> {
+        self.daily_scores.iter()
+            .enumerate()
+            .filter(|(_, daily_score)| daily_score.datetime.naive_local().date() == date)
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    pub fn day_sum(&self, date: NaiveDate) -> Option<f64> {
+        let entries = self.day_entries(date);
+        if entries.is_empty() {
+            None
+        } else {
+            Some(entries.iter().map(|i| self.daily_scores[*i].score).sum())
+        }
+    }
+
+    fn selected_daily_score_index(&self) -> Option<usize> {
+        self.day_entries(self.selected_date).get(self.selected_entry).copied()
+    }
+
+    pub fn handle_key(&mut self, key: Key) {
+        let mode = std::mem::replace(&mut self.mode, Mode::Normal);
+
+        match mode {
+            Mode::Normal => self.handle_normal_key(key),
+            Mode::Input { purpose, mut text } => match key {
+                Key::Char(c) => {
+                    text.push(c);
+                    self.mode = Mode::Input { purpose, text };
+                },
+                Key::Backspace => {
+                    text.pop();
+                    self.mode = Mode::Input { purpose, text };
+                },
+                Key::Enter => self.submit_input(purpose, &text),
+                Key::Esc => {},
+                _ => self.mode = Mode::Input { purpose, text },
+            },
+            Mode::ConfirmDelete => match key {
+                Key::Char('y') | Key::Char('Y') => self.delete_selected_entry(),
+                _ => self.message = Some("Nothing is deleted".to_string()),
+            },
+        }
+    }
+
+    fn handle_normal_key(&mut self, key: Key) {
+        self.message = None;
+
+        match key {
+            Key::Left | Key::Char('h') => self.select_date(self.selected_date - Duration::days(1)),
+            Key::Right | Key::Char('l') => self.select_date(self.selected_date + Duration::days(1)),
+            Key::Up | Key::Char('k') => self.select_date(self.selected_date - Duration::days(7)),
+            Key::Down | Key::Char('j') => self.select_date(self.selected_date + Duration::days(7)),
+            Key::PageUp | Key::Char('[') => self.select_date(shift_months(self.selected_date, -1)),
+            Key::PageDown | Key::Char(']') => self.select_date(shift_months(self.selected_date, 1)),
+            Key::Char('T') => self.select_date(self.today),
+            Key::Tab => {
+                let entries_count = self.day_entries(self.selected_date).len();
+                if entries_count > 0 {
+                    self.selected_entry = (self.selected_entry + 1) % entries_count;
+                }
+            },
+            Key::Char('+') => self.rate_today(1.0),
+            Key::Char('0') => self.rate_today(0.0),
+            Key::Char('-') => self.rate_today(-1.0),
+            Key::Char('a') => self.mode = Mode::Input { purpose: InputPurpose::Add, text: String::new() },
+            Key::Char('e') => {
+                if let Some(i) = self.selected_daily_score_index() {
+                    self.mode = Mode::Input { purpose: InputPurpose::Edit, text: input_text(&self.daily_scores[i]) };
+                }
+            },
+            Key::Char('x') if self.selected_daily_score_index().is_some() => self.mode = Mode::ConfirmDelete,
+            Key::Char('q') | Key::Esc => self.quit = true,
+            _ => {},
+        }
+    }
+
+    fn select_date(&mut self, date: NaiveDate) {
+        self.selected_date = date;
+        self.selected_entry = 0;
+    }
+
+    fn rate_today(&mut self, score: f64) {
+        if !self.score_scale.is_allowed(score) {
+            self.message = Some(format!("score {} is not allowed, expected {}", score, self.score_scale.description()));
+            return;
+        }
+        self.select_date(self.today);
+        self.add(DailyScore { score, metrics: Default::default(), attributes: Default::default(), tags: Default::default(), comment: None, datetime: self.new_record_datetime() });
+    }
+
+    // Same-day records are handled by `same_day` policy the way `add` command does, except that
+    // there is no `--force` to add a record to a day rated with `reject` policy.
+    fn add(&mut self, daily_score: DailyScore) {
+        let date = daily_score.datetime.naive_local().date();
+        let same_day_count = add_command::day_records_count(&self.daily_scores, date);
+        let added_message = format!("Added {:+} to {}", daily_score.score, date);
+
+        let (position, message) = match self.same_day_policy {
+            _ if same_day_count == 0 => (self.daily_scores.len(), added_message),
+            SameDayPolicy::Allow => (self.daily_scores.len(), added_message),
+            SameDayPolicy::Warn =>
+                (self.daily_scores.len(), format!("{}, the day is already rated ({} records)", added_message, same_day_count)),
+            SameDayPolicy::Reject => {
+                self.message = Some(format!("{} is already rated, nothing is added", date));
+                return;
+            },
+            SameDayPolicy::Replace => {
+                let daily_scores = std::mem::take(&mut self.daily_scores);
+                self.daily_scores = add_command::replace_day_records(daily_scores, daily_score);
+                self.selected_entry = 0;
+                self.message = Some(format!("Replaced {} records of {}", same_day_count, date));
+                self.dirty = true;
+                return;
+            },
+        };
+
+        // new record goes to the end, as it does when appended to the journal
+        self.daily_scores.push(daily_score);
+        self.selected_entry = self.day_entries(self.selected_date).iter().position(|i| *i == position).unwrap_or(0);
+        self.message = Some(message);
+        self.dirty = true;
+    }
+
+    // Records for the current day are made at current time, records for other days at noon.
+    fn new_record_datetime(&self) -> DateTime<FixedOffset> {
+        let now = timezone::now(self.timezone);
+        if self.selected_date == now.naive_local().date() {
+            return now;
+        }
+
+        let noon = self.selected_date.and_hms(12, 0, 0);
+        timezone::from_local_datetime(self.timezone, &noon).unwrap_or(now)
+    }
+
+    // Input is parsed the same way as `add` command arguments: `SCORE [TAG ...] [-c COMMENT]`.
+    fn submit_input(&mut self, purpose: InputPurpose, text: &str) {
+        let add_spec = CommandSpec::find("add").unwrap();
+        let mut words = text.split_whitespace().map(str::to_string);
+        let parsed_args = match add_spec.parse(&mut words, false) {
+            Ok(parsed_args) => parsed_args,
+            Err(_) => {
+                self.message = Some(format!("cannot parse '{}'", text));
+                return;
+            },
+        };
+
+        let mut positionals = parsed_args.positionals.into_iter();
+        let score = match positionals.next().map(|score_str| (self.score_scale.parse(&score_str), score_str)) {
+            Some((Some(score), _)) if self.score_scale.is_allowed(score) => score,
+            Some((Some(score), _)) => {
+                self.message = Some(format!("score {} is not allowed, expected {}", score, self.score_scale.description()));
+                return;
+            },
+            Some((None, score_str)) => {
+                self.message = Some(format!("'{}' is not a valid score", score_str));
+                return;
+            },
+            None => {
+                self.message = Some("score is not provided".to_string());
+                return;
+            },
+        };
+        let (tags, attributes) = attribute::split_tags(positionals);
+        let tags = self.tag_rules.apply_all(&tags);
+        let comment = parsed_args.options.into_iter()
+            .find(|option| option.name == "--comment")
+            .and_then(|option| option.value)
+            .filter(|comment| !comment.is_empty());
+
+        match purpose {
+            InputPurpose::Add => self.add(DailyScore { score, metrics: Default::default(), tags, attributes, comment, datetime: self.new_record_datetime() }),
+            InputPurpose::Edit => {
+                if let Some(i) = self.selected_daily_score_index() {
+                    let daily_score = &mut self.daily_scores[i];
+                    daily_score.score = score;
+                    daily_score.tags = tags;
+                    daily_score.attributes = attributes;
+                    daily_score.comment = comment;
+                    self.message = Some("Record is updated".to_string());
+                    self.dirty = true;
+                }
+            },
+        }
+    }
+
+    fn delete_selected_entry(&mut self) {
+        if let Some(i) = self.selected_daily_score_index() {
+            self.daily_scores.remove(i);
+            self.selected_entry = self.selected_entry.saturating_sub(1);
+            self.message = Some("Record is deleted".to_string());
+            self.dirty = true;
+        }
+    }
+}
+
+fn input_text(daily_score: &DailyScore) -> String {
+    let mut tags = daily_score.tags.iter().map(String::as_str).collect::<Vec<&str>>();
+    tags.sort_unstable();
+
+    let mut words = vec![daily_score.score.to_string()];
+    words.extend(tags.into_iter().map(str::to_string));
+    words.extend(daily_score.attributes.iter().map(|(key, value)| attribute::format_attribute(key, value)));
+    if let Some(comment) = daily_score.comment.as_deref().filter(|comment| !comment.is_empty()) {
+        words.push(format!("-c {}", comment));
+    }
+
+    words.join(" ")
+}
+
+// Day of month is clamped to the length of the target month.
+pub fn shift_months(date: NaiveDate, months: i32) -> NaiveDate {
+    let month0 = date.year() * 12 + date.month0() as i32 + months;
+    let (year, month) = (month0.div_euclid(12), month0.rem_euclid(12) as u32 + 1);
+
+    (1..=date.day()).rev()
+        .find_map(|day| NaiveDate::from_ymd_opt(year, month, day))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn daily_score_on(date: NaiveDate, score: f64, tags: &[&str]) -> DailyScore {
+        DailyScore {
+            score,
+            metrics: Default::default(),
+            attributes: Default::default(),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            comment: None,
+            datetime: FixedOffset::east(0).from_local_datetime(&date.and_hms(9, 0, 0)).unwrap(),
+        }
+    }
+
+    fn type_text(app: &mut App, text: &str) {
+        for c in text.chars() {
+            app.handle_key(Key::Char(c));
+        }
+        app.handle_key(Key::Enter);
+    }
+
+    #[test]
+    fn navigation() {
+        let today = NaiveDate::from_ymd(2020, 3, 31);
+        let mut app = App::new(Vec::new(), today, None, TagRules::default(), ScoreScale::default(), SameDayPolicy::Allow);
+
+        app.handle_key(Key::Left);
+        assert_eq!(app.selected_date, NaiveDate::from_ymd(2020, 3, 30));
+        app.handle_key(Key::Down);
+        assert_eq!(app.selected_date, NaiveDate::from_ymd(2020, 4, 6));
+        app.handle_key(Key::Char('T'));
+        app.handle_key(Key::Char('['));
+        assert_eq!(app.selected_date, NaiveDate::from_ymd(2020, 2, 29));
+        app.handle_key(Key::PageDown);
+        assert_eq!(app.selected_date, NaiveDate::from_ymd(2020, 3, 29));
+
+        assert!(!app.quit);
+        app.handle_key(Key::Char('q'));
+        assert!(app.quit);
+    }
+
+    #[test]
+    fn quick_rating() {
+        let today = NaiveDate::from_ymd(2020, 3, 4);
+        let mut app = App::new(Vec::new(), today, None, TagRules::default(), ScoreScale::default(), SameDayPolicy::Allow);
+        app.handle_key(Key::Left);
+        app.handle_key(Key::Char('+'));
+        app.handle_key(Key::Char('-'));
+
+        assert!(app.dirty);
+        assert_eq!(app.selected_date, today);
+        assert_eq!(app.day_sum(today), Some(0.0));
+        assert_eq!(app.day_entries(today).len(), 2);
+        assert_eq!(app.selected_entry, 1);
+        assert_eq!(app.daily_scores.iter().map(|daily_score| daily_score.score).collect::<Vec<f64>>(), vec![1.0, -1.0]);
+    }
+
+    #[test]
+    fn journal_order() {
+        let today = NaiveDate::from_ymd(2020, 3, 4);
+        // records of an imported or edited journal may be out of order
+        let daily_scores = vec![daily_score_on(today, 1.0, &["late"]), daily_score_on(NaiveDate::from_ymd(2020, 3, 1), -1.0, &[])];
+        let mut app = App::new(daily_scores, today, None, TagRules::default(), ScoreScale::default(), SameDayPolicy::Allow);
+        app.handle_key(Key::Left);
+        app.handle_key(Key::Char('a'));
+        type_text(&mut app, "2");
+
+        let scores = app.daily_scores.iter().map(|daily_score| daily_score.score).collect::<Vec<f64>>();
+        assert_eq!(scores, vec![1.0, -1.0, 2.0]);
+    }
+
+    #[test]
+    fn same_day_policies() {
+        let today = NaiveDate::from_ymd(2020, 3, 4);
+        let daily_scores = || vec![daily_score_on(today, 1.0, &[]), daily_score_on(NaiveDate::from_ymd(2020, 3, 1), -1.0, &[]), daily_score_on(today, 2.0, &[])];
+        let app_with = |same_day_policy| App::new(daily_scores(), today, None, TagRules::default(), ScoreScale::default(), same_day_policy);
+
+        let mut warning_app = app_with(SameDayPolicy::Warn);
+        warning_app.handle_key(Key::Char('a'));
+        type_text(&mut warning_app, "0");
+        assert_eq!(warning_app.message.as_deref(), Some("Added +0 to 2020-03-04, the day is already rated (2 records)"));
+        assert_eq!(warning_app.day_entries(today).len(), 3);
+
+        let mut rejecting_app = app_with(SameDayPolicy::Reject);
+        rejecting_app.handle_key(Key::Char('a'));
+        type_text(&mut rejecting_app, "0");
+        assert_eq!(rejecting_app.message.as_deref(), Some("2020-03-04 is already rated, nothing is added"));
+        assert_eq!(rejecting_app.daily_scores.len(), 3);
+        assert!(!rejecting_app.dirty);
+
+        let mut replacing_app = app_with(SameDayPolicy::Replace);
+        replacing_app.handle_key(Key::Char('a'));
+        type_text(&mut replacing_app, "0");
+        assert_eq!(replacing_app.message.as_deref(), Some("Replaced 2 records of 2020-03-04"));
+        assert_eq!(replacing_app.daily_scores.iter().map(|daily_score| daily_score.score).collect::<Vec<f64>>(), vec![0.0, -1.0]);
+        assert_eq!(replacing_app.selected_entry, 0);
+    }
+
+    #[test]
+    fn adding_and_editing() {
+        let today = NaiveDate::from_ymd(2020, 3, 4);
+        let yesterday = NaiveDate::from_ymd(2020, 3, 3);
+        let tag_rules = TagRules::new(true, vec![("walking".to_string(), "walk".to_string())].into_iter().collect());
+        let score_scale = ScoreScale { min: Some(-2.0), max: Some(2.0), aliases: vec![("good".to_string(), 1.0)].into_iter().collect(), ..ScoreScale::default() };
+        let mut app = App::new(vec![daily_score_on(yesterday, 1.0, &["run"])], today, Some(Timezone::Offset(FixedOffset::east(3600))), tag_rules, score_scale,
+                               SameDayPolicy::Allow);
+
+        app.handle_key(Key::Left);
+        app.handle_key(Key::Char('a'));
+        type_text(&mut app, "2 Work -c long day");
+        let entries = app.day_entries(yesterday);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(app.selected_entry, 1);
+        let added = &app.daily_scores[entries[1]];
+        assert_eq!(added.to_s(), "2020-03-03 12:00:00 +0100 | 2 | work | long day");
+
+        app.handle_key(Key::Tab);
+        assert_eq!(app.selected_entry, 0);
+        app.handle_key(Key::Char('e'));
+        assert_eq!(app.mode, Mode::Input { purpose: InputPurpose::Edit, text: "1 run".to_string() });
+        app.handle_key(Key::Backspace);
+        app.handle_key(Key::Backspace);
+        app.handle_key(Key::Backspace);
+        type_text(&mut app, "Walking -c fine");
+        assert_eq!(app.daily_scores[0].to_s(), "2020-03-03 09:00:00 +0000 | 1 | walk | fine");
+
+        app.handle_key(Key::Char('a'));
+        type_text(&mut app, "great");
+        assert_eq!(app.message.as_deref(), Some("'great' is not a valid score"));
+        assert_eq!(app.mode, Mode::Normal);
+
+        app.handle_key(Key::Char('a'));
+        type_text(&mut app, "3");
+        assert_eq!(app.message.as_deref(), Some("score 3 is not allowed, expected from -2 to 2 or good"));
+
+        app.handle_key(Key::Char('a'));
+        type_text(&mut app, "good");
+        assert_eq!(app.message.as_deref(), Some("Added +1 to 2020-03-03"));
+    }
+
+    #[test]
+    fn deleting() {
+        let today = NaiveDate::from_ymd(2020, 3, 4);
+        let mut app = App::new(vec![daily_score_on(today, 1.0, &[]), daily_score_on(today, -1.0, &[])], today, None, TagRules::default(), ScoreScale::default(), SameDayPolicy::Allow);
+
+        app.handle_key(Key::Tab);
+        app.handle_key(Key::Char('x'));
+        app.handle_key(Key::Char('n'));
+        assert_eq!(app.daily_scores.len(), 2);
+        assert!(!app.dirty);
+
+        app.handle_key(Key::Char('x'));
+        app.handle_key(Key::Char('y'));
+        assert_eq!(app.daily_scores.len(), 1);
+        assert_eq!(app.daily_scores[0].score, 1.0);
+        assert_eq!(app.selected_entry, 0);
+        assert!(app.dirty);
+    }
+
+    #[test]
+    fn months_shifting() {
+        assert_eq!(shift_months(NaiveDate::from_ymd(2020, 1, 31), 1), NaiveDate::from_ymd(2020, 2, 29));
+        assert_eq!(shift_months(NaiveDate::from_ymd(2020, 1, 15), -1), NaiveDate::from_ymd(2019, 12, 15));
+        assert_eq!(shift_months(NaiveDate::from_ymd(2020, 12, 1), 13), NaiveDate::from_ymd(2022, 1, 1));
+    }
+}
@@ -0,0 +1,310 @@
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+
+use super::app::{App, InputPurpose, Mode};
+use crate::filter::Filter;
+use crate::metric::{Metric, ReportValue};
+use crate::mood_report::MoodReport;
+use crate::timezone::{self, Timezone};
+use crate::GlobalConfig;
+
+const MONTH_NAMES: [&str; 12] = ["January", "February", "March", "April", "May", "June", "July",
+    "August", "September", "October", "November", "December"];
+const CALENDAR_WIDTH: usize = 49;
+const PANEL_MIN_WIDTH: usize = 30;
+const CHART_PERIODS: usize = 8;
+const CHART_BAR_WIDTH: usize = 12;
+const TAG_STATS_LIMIT: usize = 6;
+const HELP: &str = "arrows/hjkl move  [ ] month  T today  Tab record  + 0 - rate today  a add  e edit  x delete  q quit";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Color {
+    Default,
+    Positive,
+    Negative,
+    Dim,
+    Title,
+}
+
+pub struct Span {
+    pub text: String,
+    pub color: Color,
+    pub reversed: bool,
+}
+
+pub type Line = Vec<Span>;
+
+fn span(text: impl Into<String>, color: Color) -> Span {
+    Span { text: text.into(), color, reversed: false }
+}
+
+fn score_color(score: f64) -> Color {
+    match score {
+        score if score > 0.0 => Color::Positive,
+        score if score < 0.0 => Color::Negative,
+        _ => Color::Default,
+    }
+}
+
+// Calendar and records of the selected day on the left, report panels on the right,
+// status line at the bottom.
+pub fn render(app: &App, global_config: &GlobalConfig, width: usize, height: usize) -> Vec<Line> {
+    let mut left = calendar(app, global_config.week_start);
+    left.push(Vec::new());
+    left.extend(day_records(app));
+
+    let right = if width >= CALENDAR_WIDTH + PANEL_MIN_WIDTH { panels(app, global_config) } else { Vec::new() };
+
+    let body_height = height.saturating_sub(2);
+    let mut lines = Vec::new();
+    let mut left = left.into_iter();
+    let mut right = right.into_iter();
+    for _ in 0..body_height {
+        let mut line = left.next().unwrap_or_default();
+        if let Some(right_line) = right.next() {
+            let left_width = line.iter().map(|span| span.text.chars().count()).sum::<usize>();
+            line.push(span(" ".repeat(CALENDAR_WIDTH.saturating_sub(left_width) + 2), Color::Default));
+            line.extend(right_line);
+        }
+        lines.push(line);
+    }
+
+    lines.push(Vec::new());
+    lines.push(status_line(app));
+
+    lines.into_iter().map(|line| truncate(line, width)).collect()
+}
+
+fn calendar(app: &App, week_start: Weekday) -> Vec<Line> {
+    let date = app.selected_date;
+    let first_day = NaiveDate::from_ymd(date.year(), date.month(), 1);
+    let days_before = (first_day.weekday().num_days_from_monday() + 7 - week_start.num_days_from_monday()) % 7;
+
+    let mut lines = vec![
+        vec![span(format!("{} {}", MONTH_NAMES[date.month0() as usize], date.year()), Color::Title)],
+        (0..7).map(|i| {
+            let weekday = (0..i).fold(week_start, |weekday, _| weekday.succ());
+            span(format!("{:<7}", format!("{:?}", weekday)), Color::Dim)
+        }).collect(),
+    ];
+
+    let mut day = first_day - Duration::days(days_before.into());
+    while day.month() == date.month() || day < first_day {
+        let mut line = Vec::new();
+        for _ in 0..7 {
+            if day.month() != date.month() {
+                line.push(span("       ", Color::Default));
+            } else {
+                let (sum_text, color) = match app.day_sum(day) {
+                    // cells are 3 characters wide, so fractional sums are rounded
+                    Some(sum) => (format!("{:+}", ReportValue(sum.round().clamp(-99.0, 99.0))), score_color(sum)),
+                    None => ("·".to_string(), Color::Dim),
+                };
+                let mut cell = span(format!("{:>2} {:>3}", day.day(), sum_text), color);
+                cell.reversed = day == app.selected_date;
+                line.push(cell);
+                line.push(span(" ", Color::Default));
+            }
+            day = day.succ();
+        }
+        lines.push(line);
+    }
+
+    lines
+}
+
+fn day_records(app: &App) -> Vec<Line> {
+    let mut lines = vec![vec![span(app.selected_date.format("%a %d %b %Y").to_string(), Color::Title)]];
+    let entries = app.day_entries(app.selected_date);
+
+    if entries.is_empty() {
+        lines.push(vec![span("No records, press a to add one", Color::Dim)]);
+    }
+
+    for (n, i) in entries.into_iter().enumerate() {
+        let daily_score = &app.daily_scores[i];
+        let mut tags = daily_score.tags.iter().map(String::as_str).collect::<Vec<&str>>();
+        tags.sort_unstable();
+
+        let mut line = vec![
+            span(daily_score.datetime.format("%H:%M ").to_string(), Color::Dim),
+            span(format!("{:>+4}", daily_score.score), score_color(daily_score.score)),
+        ];
+        if let Some(label) = app.score_scale.label(daily_score.score) {
+            line.push(span(format!(" {}", label), Color::Dim));
+        }
+        line.push(span(format!("  {}", tags.join(", ")), Color::Default));
+        if let Some(comment) = daily_score.comment.as_deref().filter(|comment| !comment.is_empty()) {
+            line.push(span(format!("  {}", comment), Color::Dim));
+        }
+        if n == app.selected_entry {
+            line.iter_mut().for_each(|span| span.reversed = true);
+        }
+        lines.push(line);
+    }
+
+    lines
+}
+
+// Panels report days the way reports do, while the calendar shows every record.
+fn panels(app: &App, global_config: &GlobalConfig) -> Vec<Line> {
+    let filter = Filter::default();
+    let daily_scores = global_config.report_daily_scores(app.daily_scores.clone(), &filter);
+    let mood_report = MoodReport { daily_scores: &daily_scores, filter: &filter, metric: &Metric::Score, timezone: global_config.timezone };
+
+    let mut lines = vec![vec![span("Weekly moods", Color::Title)]];
+    lines.extend(chart(&mood_report.iterative_weekly_mood(global_config.week_start), global_config.timezone,
+                       |date| (date - Duration::days(7)).format("%d/%m").to_string()));
+    lines.push(Vec::new());
+    lines.push(vec![span("Monthly moods", Color::Title)]);
+    lines.extend(chart(&mood_report.iterative_monthly_mood(), global_config.timezone,
+                       |date| date.pred().format("%Y-%m").to_string()));
+    lines.push(Vec::new());
+    lines.push(vec![span("Tag impact", Color::Title)]);
+
+    let tag_impacts = mood_report.tag_impacts();
+    if tag_impacts.is_empty() {
+        lines.push(vec![span("No tags yet", Color::Dim)]);
+    }
+    for (tag, stats, impact) in tag_impacts.into_iter().take(TAG_STATS_LIMIT) {
+        lines.push(vec![
+            span(format!("{:<14.14} {:>4} ", tag, stats.count), Color::Default),
+            span(format!("{:+.2}", impact), if impact >= 0.0 { Color::Positive } else { Color::Negative }),
+        ]);
+    }
+
+    lines
+}
+
+// Horizontal bars for the last periods, data is timestamped with period ends.
+fn chart<F>(data: &[(i64, f64)], timezone: Option<Timezone>, label: F) -> Vec<Line>
+where
+    F: Fn(NaiveDate) -> String,
+{
+    if data.is_empty() {
+        return vec![vec![span("No data yet", Color::Dim)]];
+    }
+
+    let data = &data[data.len().saturating_sub(CHART_PERIODS)..];
+    let max_value = data.iter().map(|(_, value)| value.abs()).fold(1.0, f64::max);
+
+    data.iter()
+        .map(|(timestamp, value)| {
+            let period_end = timezone::date_of(timezone, *timestamp);
+            let bar_width = (value.abs() * CHART_BAR_WIDTH as f64 / max_value).ceil() as usize;
+            vec![
+                span(format!("{:<8}{:>4} ", label(period_end), ReportValue(*value)), Color::Default),
+                span("█".repeat(bar_width), score_color(*value)),
+            ]
+        })
+        .collect()
+}
+
+fn status_line(app: &App) -> Line {
+    match &app.mode {
+        Mode::Input { purpose, text } => {
+            let caption = match purpose {
+                InputPurpose::Add => "Add",
+                InputPurpose::Edit => "Edit",
+            };
+            vec![
+                span(format!("{} (SCORE [TAG ...] [-c COMMENT], Enter to save, Esc to cancel): ", caption), Color::Title),
+                span(format!("{}_", text), Color::Default),
+            ]
+        },
+        Mode::ConfirmDelete => vec![span("Delete selected record? [y/N]", Color::Negative)],
+        Mode::Normal => match &app.message {
+            Some(message) => vec![span(message.clone(), Color::Default)],
+            None => vec![span(HELP, Color::Dim)],
+        },
+    }
+}
+
+fn truncate(line: Line, width: usize) -> Line {
+    let mut remaining = width;
+    let mut truncated = Vec::new();
+
+    for mut span in line {
+        if remaining == 0 {
+            break;
+        }
+        let length = span.text.chars().count();
+        if length > remaining {
+            span.text = span.text.chars().take(remaining).collect();
+        }
+        remaining -= length.min(remaining);
+        truncated.push(span);
+    }
+
+    truncated
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{FixedOffset, TimeZone};
+
+    use super::*;
+    use crate::add_command::SameDayPolicy;
+    use crate::config::Config;
+    use crate::daily_score::DailyScore;
+    use crate::score_scale::ScoreScale;
+    use crate::tag::TagRules;
+
+    fn global_config(week_start: Weekday) -> GlobalConfig {
+        GlobalConfig { week_start, ..GlobalConfig::from(Config::default()) }
+    }
+
+    fn text(line: &Line) -> String {
+        line.iter().map(|span| span.text.as_str()).collect()
+    }
+
+    #[test]
+    fn screen_rendering() {
+        let today = NaiveDate::from_ymd(2020, 3, 4);
+        let daily_scores = vec![
+            DailyScore {
+                score: 2.0,
+                metrics: Default::default(),
+                attributes: Default::default(),
+                tags: vec!["run".to_string()].into_iter().collect(),
+                comment: Some("fine".to_string()),
+                datetime: FixedOffset::east(0).ymd(2020, 3, 4).and_hms(9, 30, 0),
+            },
+        ];
+        let score_scale = ScoreScale { aliases: vec![("great".to_string(), 2.0)].into_iter().collect(), ..ScoreScale::default() };
+        let app = App::new(daily_scores, today, None, TagRules::default(), score_scale, SameDayPolicy::Allow);
+        let lines = render(&app, &global_config(Weekday::Sun), 100, 30);
+        let texts = lines.iter().map(text).collect::<Vec<String>>();
+
+        assert_eq!(lines.len(), 30);
+        assert!(texts[0].starts_with("March 2020"));
+        assert!(texts[0].ends_with("Weekly moods"));
+        assert!(texts[1].starts_with("Sun    Mon    Tue    Wed    Thu    Fri    Sat"));
+        assert!(texts[2].starts_with(" 1   ·  2   ·  3   ·  4  +2  5   · "));
+        assert!(texts.iter().any(|text| text.starts_with("Wed 04 Mar 2020")));
+        assert!(texts.iter().any(|text| text.starts_with("09:30   +2 great  run  fine")));
+        assert!(texts.iter().any(|text| text.contains("Tag impact")));
+        assert_eq!(texts[29], HELP);
+
+        let selected_cell = lines[2].iter().find(|span| span.reversed).unwrap();
+        assert_eq!(selected_cell.text, " 4  +2");
+        assert_eq!(selected_cell.color, Color::Positive);
+    }
+
+    #[test]
+    fn narrow_screen() {
+        let app = App::new(Vec::new(), NaiveDate::from_ymd(2020, 3, 4), None, TagRules::default(), ScoreScale::default(), SameDayPolicy::Allow);
+        let lines = render(&app, &global_config(Weekday::Mon), 60, 10);
+
+        assert!(!lines.iter().any(|line| text(line).contains("Weekly moods")));
+        assert_eq!(text(&lines[2]), format!("{} 1   · ", " ".repeat(42)));
+        assert!(render(&app, &global_config(Weekday::Mon), 40, 10).iter().all(|line| text(line).chars().count() <= 40));
+    }
+
+    #[test]
+    fn bars() {
+        let lines = chart(&[(0, 2.0), (0, -4.0)], None, |_| "p".to_string());
+
+        assert_eq!(text(&lines[0]), format!("p          2 {}", "█".repeat(6)));
+        assert_eq!(lines[1][1].color, Color::Negative);
+    }
+}
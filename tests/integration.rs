@@ -10,12 +10,115 @@ fn add_record_to_journal() {
     let mut cmd = Command::cargo_bin("howdy").unwrap();
     cmd.arg("-f")
         .arg(journal.path())
-        .args(&["add", "3", "tag", "another tag", "-c", "comment"]);
+        .args(["add", "3", "tag", "another tag", "-c", "comment"]);
     cmd.assert().success();
 
     journal.assert(predicate::str::contains("| 3 | another tag,tag | comment"));
 }
 
+#[test]
+fn add_with_options_anywhere() {
+    let journal = prepare_empty_journal_file();
+
+    Command::cargo_bin("howdy").unwrap()
+        .args(["add", "-1", "-c", "nice", "day", "-f"])
+        .arg(journal.path())
+        .assert()
+        .success();
+    Command::cargo_bin("howdy").unwrap()
+        .arg("-f")
+        .arg(journal.path())
+        .args(["add", "2", "tag", "-c", "text", "--", "-c", "-t"])
+        .assert()
+        .success();
+
+    journal.assert(predicate::str::contains("| -1 |  | nice day\n"));
+    journal.assert(predicate::str::is_match(r"\| 2 \| (-c,-t,tag) \| text\n").unwrap());
+}
+
+#[test]
+fn help_and_version() {
+    Command::cargo_bin("howdy").unwrap()
+        .arg("--version")
+        .assert()
+        .success()
+        .stdout(format!("howdy {}\n", env!("CARGO_PKG_VERSION")));
+
+    Command::cargo_bin("howdy").unwrap()
+        .arg("--help")
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("Usage: howdy [-f FILEPATH] [COMMAND [ARGS ...]]\n"));
+
+    Command::cargo_bin("howdy").unwrap()
+        .args(["mood", "--help"])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("Usage: howdy [-f FILEPATH] mood [TAG ...]"));
+
+    Command::cargo_bin("howdy").unwrap()
+        .args(["mood", "--foo"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("'--foo' is not a valid mood command option"))
+        .stderr(predicate::str::contains("run 'howdy help' for usage"));
+}
+
+#[test]
+fn shell_completions() {
+    let journal = prepare_empty_journal_file();
+    journal
+        .write_str("2020-01-01 09:10:11 +0400 | 1 | run,games | foo\n2020-01-02 10:00:00 +0400 | -1 | work,run |\n")
+        .unwrap();
+
+    Command::cargo_bin("howdy").unwrap()
+        .arg("-f")
+        .arg(journal.path())
+        .arg("__complete-tags")
+        .assert()
+        .success()
+        .stdout("games\nrun\nwork\n");
+
+    Command::cargo_bin("howdy").unwrap()
+        .args(["completions", "bash"])
+        .assert()
+        .success()
+        .stdout(predicate::str::ends_with("complete -F _howdy howdy\n"));
+
+    Command::cargo_bin("howdy").unwrap()
+        .arg("--help")
+        .assert()
+        .stdout(predicate::str::contains("__complete-tags").not());
+}
+
+#[test]
+fn interactive_ask() {
+    let journal = prepare_empty_journal_file();
+    journal.write_str("2020-01-01 09:10:11 +0400 | 1 | work,run |\n").unwrap();
+
+    Command::cargo_bin("howdy").unwrap()
+        .arg("-f")
+        .arg(journal.path())
+        .write_stdin("great\n1\n1, wo, new\nnice day\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("'great' is not a valid score"))
+        .stdout(predicate::str::contains("Used tags: 1) run  2) work"))
+        .stderr(predicate::str::contains("Warning: today is already rated").not());
+
+    journal.assert(predicate::str::is_match(r"\| 1 \| new,run,work \| nice day\n$").unwrap());
+
+    Command::cargo_bin("howdy").unwrap()
+        .arg("-f")
+        .arg(journal.path())
+        .arg("ask")
+        .write_stdin("")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Warning: today is already rated (1 records)"))
+        .stdout(predicate::str::contains("Nothing is added"));
+}
+
 #[test]
 fn check_mood() {
     let journal = prepare_empty_journal_file();
@@ -35,18 +138,906 @@ fn check_mood() {
     let mut cmd = Command::cargo_bin("howdy").unwrap();
     cmd.arg("-f")
         .arg(journal.path())
-        .args(&["mood", "-t", "lm"])
+        .args(["mood", "-t", "lm"])
         .assert()
         .stdout("30-days mood: [5]\n");
 
     let mut tagged_cmd = Command::cargo_bin("howdy").unwrap();
     tagged_cmd.arg("-f")
         .arg(journal.path())
-        .args(&["mood", "tag", "--type", "lm"])
+        .args(["mood", "tag", "--type", "lm"])
         .assert()
         .stdout("30-days mood: [3]\n");
 }
 
+#[test]
+fn fractional_scores_mood() {
+    let journal = prepare_empty_journal_file();
+
+    for score in ["7.5", "0.5", "-1"] {
+        Command::cargo_bin("howdy").unwrap()
+            .arg("-f")
+            .arg(journal.path())
+            .args(["add", score])
+            .assert()
+            .success();
+    }
+    journal.assert(predicate::str::is_match(r"\| 7\.5 \|  \|\n.* \| 0\.5 \|  \|\n.* \| -1 \|  \|\n$").unwrap());
+
+    Command::cargo_bin("howdy").unwrap()
+        .arg("-f")
+        .arg(journal.path())
+        .args(["mood", "-t", "lm"])
+        .assert()
+        .stdout("30-days mood: [7]\n");
+
+    Command::cargo_bin("howdy").unwrap()
+        .arg("-f")
+        .arg(journal.path())
+        .args(["mood", "-t", "lm", "--min-score", "0.5"])
+        .assert()
+        .stdout("30-days mood: [8]\n");
+}
+
+#[test]
+fn metrics_mood() {
+    let journal = prepare_empty_journal_file();
+
+    for args in [["add", "1", "-m", "sleep=7.5", "--metric", "energy=-1"], ["add", "-1", "-m", "sleep=6", "-m", "energy=2"]] {
+        Command::cargo_bin("howdy").unwrap()
+            .arg("-f")
+            .arg(journal.path())
+            .args(args)
+            .assert()
+            .success();
+    }
+    Command::cargo_bin("howdy").unwrap()
+        .arg("-f")
+        .arg(journal.path())
+        .args(["add", "2"])
+        .assert()
+        .success();
+
+    journal.assert(predicate::str::contains("| 1 energy=-1 sleep=7.5 |  |\n"));
+
+    Command::cargo_bin("howdy").unwrap()
+        .arg("-f")
+        .arg(journal.path())
+        .args(["mood", "-t", "lm", "--metric", "sleep"])
+        .assert()
+        .stdout("30-days sleep: [13.5]\n");
+
+    Command::cargo_bin("howdy").unwrap()
+        .arg("-f")
+        .arg(journal.path())
+        .args(["mood", "-t", "lm", "-m", "score"])
+        .assert()
+        .stdout("30-days mood: [2]\n");
+}
+
+#[test]
+fn attributes_mood() {
+    let journal = prepare_empty_journal_file();
+
+    for args in [["add", "1", "run", "weather=rain"], ["add", "-1", "weather=rain", "location=home"], ["add", "3", "weather=sun", "run"]] {
+        Command::cargo_bin("howdy").unwrap()
+            .arg("-f")
+            .arg(journal.path())
+            .args(args)
+            .assert()
+            .success();
+    }
+
+    journal.assert(predicate::str::contains("| -1 | location=home,weather=rain |\n"));
+
+    Command::cargo_bin("howdy").unwrap()
+        .arg("-f")
+        .arg(journal.path())
+        .args(["mood", "--group-by", "weather"])
+        .assert()
+        .stdout("mood by weather:\nrain: 0 (2 records, average 0)\nsun: 3 (1 records, average 3)\n");
+
+    Command::cargo_bin("howdy").unwrap()
+        .arg("-f")
+        .arg(journal.path())
+        .args(["mood", "-g", "weather", "run"])
+        .assert()
+        .stdout("mood by weather:\nrain: 1 (1 records, average 1)\nsun: 3 (1 records, average 3)\n");
+
+    Command::cargo_bin("howdy").unwrap()
+        .arg("-f")
+        .arg(journal.path())
+        .args(["mood", "-t", "lm", "--tag", "weather=rain"])
+        .assert()
+        .stdout("30-days mood: [0]\n");
+}
+
+#[test]
+fn hierarchical_tags() {
+    let journal = prepare_empty_journal_file();
+
+    for args in [["add", "2", "sport/running", "sport/swimming"], ["add", "1", "sport/running", "work"], ["add", "-1", "work", "sports"]] {
+        Command::cargo_bin("howdy").unwrap()
+            .arg("-f")
+            .arg(journal.path())
+            .args(args)
+            .assert()
+            .success();
+    }
+
+    Command::cargo_bin("howdy").unwrap()
+        .arg("-f")
+        .arg(journal.path())
+        .args(["mood", "-t", "lm", "sport"])
+        .assert()
+        .stdout("30-days mood: [3]\n");
+
+    Command::cargo_bin("howdy").unwrap()
+        .arg("-f")
+        .arg(journal.path())
+        .arg("tags")
+        .assert()
+        .stdout(concat!(
+            "sport: 3 (2 records, average 1.5)\n",
+            "  running: 3 (2 records, average 1.5)\n",
+            "  swimming: 2 (1 records, average 2)\n",
+            "sports: -1 (1 records, average -1)\n",
+            "work: 0 (2 records, average 0)\n",
+        ));
+
+    Command::cargo_bin("howdy").unwrap()
+        .arg("-f")
+        .arg(journal.path())
+        .args(["tags", "--tag", "work"])
+        .assert()
+        .stdout("sport: 1 (1 records, average 1)\n  running: 1 (1 records, average 1)\nsports: -1 (1 records, average -1)\nwork: 0 (2 records, average 0)\n");
+
+    Command::cargo_bin("howdy").unwrap()
+        .arg("-f")
+        .arg(journal.path())
+        .arg("__complete-tags")
+        .assert()
+        .stdout("sport\nsport/running\nsport/swimming\nsports\nwork\n");
+}
+
+#[test]
+fn csv_export_and_import() {
+    let journal = prepare_empty_journal_file();
+    journal
+        .write_str("2020-01-01 09:10:11 +0400 | 1 sleep=7.5 | games,run | foo | bar, \"baz\"\n2020-01-02 10:00:00 +0400 | -1 |  |\n")
+        .unwrap();
+    let csv_file = assert_fs::NamedTempFile::new("export.csv").unwrap();
+
+    Command::cargo_bin("howdy").unwrap()
+        .arg("-f")
+        .arg(journal.path())
+        .arg("export")
+        .arg(csv_file.path())
+        .assert()
+        .success();
+
+    csv_file.assert(
+        "Date,Score,Tags,Comment,Metrics\n\
+        2020-01-01 09:10:11 +0400,1,\"games,run\",\"foo | bar, \"\"baz\"\"\",sleep=7.5\n\
+        2020-01-02 10:00:00 +0400,-1,,,\n"
+    );
+
+    let new_journal = prepare_empty_journal_file();
+    new_journal.write_str("2020-01-02 10:00:00 +0400 | -1 |  |\n").unwrap();
+
+    Command::cargo_bin("howdy").unwrap()
+        .arg("-f")
+        .arg(new_journal.path())
+        .arg("import")
+        .arg(csv_file.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("+ 2020-01-01 09:10:11 +0400 | 1 sleep=7.5 | games,run | foo | bar, \"baz\"\n"))
+        .stdout(predicate::str::contains("1 records to add, 0 records to remove"));
+
+    new_journal.assert(
+        "2020-01-01 09:10:11 +0400 | 1 sleep=7.5 | games,run | foo | bar, \"baz\"\n\
+        2020-01-02 10:00:00 +0400 | -1 |  |\n"
+    );
+}
+
+#[test]
+fn csv_import_with_column_mapping() {
+    let journal = prepare_empty_journal_file();
+    let csv_file = assert_fs::NamedTempFile::new("import.csv").unwrap();
+    csv_file.write_str("\"01.02.2020 10:30 +0000\",nice day,1\n").unwrap();
+
+    Command::cargo_bin("howdy").unwrap()
+        .arg("-f")
+        .arg(journal.path())
+        .arg("import")
+        .arg(csv_file.path())
+        .args(["--columns", "date,comment,score", "--date-format", "%d.%m.%Y %H:%M %z"])
+        .assert()
+        .success();
+
+    journal.assert("2020-02-01 10:30:00 +0000 | 1 |  | nice day\n");
+}
+
+#[test]
+fn json_and_ndjson_round_trip() {
+    let journal_content = "2020-01-01 09:10:11 +0400 | 1 | games,run | foo | bar, baz\n2020-01-02 10:00:00 +0400 | -1 |  |\n";
+    let journal = prepare_empty_journal_file();
+    journal.write_str(journal_content).unwrap();
+
+    for file_name in ["export.json", "export.ndjson"].iter() {
+        let export_file = assert_fs::NamedTempFile::new(file_name).unwrap();
+        Command::cargo_bin("howdy").unwrap()
+            .arg("-f")
+            .arg(journal.path())
+            .arg("export")
+            .arg(export_file.path())
+            .assert()
+            .success();
+
+        let new_journal = prepare_empty_journal_file();
+        Command::cargo_bin("howdy").unwrap()
+            .arg("-f")
+            .arg(new_journal.path())
+            .arg("import")
+            .arg(export_file.path())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("2 records to add, 0 records to remove"));
+
+        new_journal.assert(journal_content);
+    }
+}
+
+#[test]
+fn ndjson_export_format() {
+    let journal = prepare_empty_journal_file();
+    journal.write_str("2020-01-01 09:10:11 +0400 | 1 | games,run | foo\n").unwrap();
+    let export_file = assert_fs::NamedTempFile::new("export.ndjson").unwrap();
+
+    Command::cargo_bin("howdy").unwrap()
+        .arg("-f")
+        .arg(journal.path())
+        .arg("export")
+        .arg(export_file.path())
+        .assert()
+        .success();
+
+    export_file.assert(
+        "{\"attributes\":{},\"comment\":\"foo\",\"datetime\":\"2020-01-01T09:10:11+04:00\",\"metrics\":{},\"score\":1,\"tags\":[\"games\",\"run\"]}\n"
+    );
+}
+
+#[test]
+fn xlsx_import_round_trip() {
+    let journal = prepare_empty_journal_file();
+    journal.write_str("2020-01-01 09:10:11 +0400 | 1 | games,run | foo\n2020-01-02 10:00:00 +0400 | -1 |  |\n").unwrap();
+    let xlsx_file = assert_fs::NamedTempFile::new("export.xlsx").unwrap();
+
+    Command::cargo_bin("howdy").unwrap()
+        .arg("-f")
+        .arg(journal.path())
+        .arg("export")
+        .arg(xlsx_file.path())
+        .assert()
+        .success();
+
+    Command::cargo_bin("howdy").unwrap()
+        .arg("-f")
+        .arg(journal.path())
+        .arg("import")
+        .arg(xlsx_file.path())
+        .assert()
+        .success()
+        .stdout("Nothing to import from '".to_string() + xlsx_file.path().to_str().unwrap() + "'\n");
+
+    journal.write_str("2020-01-01 09:10:11 +0400 | 1 | games,run | foo\n2020-01-03 10:00:00 +0400 | 2 |  |\n").unwrap();
+
+    Command::cargo_bin("howdy").unwrap()
+        .arg("-f")
+        .arg(journal.path())
+        .arg("import")
+        .arg(xlsx_file.path())
+        .arg("--replace")
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("- 2020-01-03 10:00:00 +0400 | 2 |  |\n"))
+        .stdout(predicate::str::contains("1 records to add, 1 records to remove"))
+        .stderr(predicate::str::contains("journal records would be removed, use --yes to confirm the import"));
+
+    journal.assert("2020-01-01 09:10:11 +0400 | 1 | games,run | foo\n2020-01-03 10:00:00 +0400 | 2 |  |\n");
+
+    Command::cargo_bin("howdy").unwrap()
+        .arg("-f")
+        .arg(journal.path())
+        .arg("import")
+        .arg(xlsx_file.path())
+        .args(["--replace", "--yes"])
+        .assert()
+        .success()
+        .stdout(predicate::str::ends_with("' done\n"));
+
+    journal.assert(predicate::str::starts_with("2020-01-01 09:10:11 +0400 | 1 | games,run | foo\n2020-01-02 00:00:00"));
+    journal.assert(predicate::str::ends_with(" | -1 |  |\n"));
+}
+
+#[test]
+fn filtered_export() {
+    let journal = prepare_empty_journal_file();
+    journal.write_str(
+        "2020-01-01 09:10:11 +0400 | 1 | work |\n\
+        2020-02-01 09:10:11 +0400 | 2 | work,run |\n\
+        2020-02-02 09:10:11 +0400 | -1 | work |\n\
+        2020-02-03 09:10:11 +0400 | 1 | run |\n\
+        2020-04-01 09:10:11 +0400 | 1 | work |\n"
+    ).unwrap();
+    let csv_file = assert_fs::NamedTempFile::new("export.csv").unwrap();
+
+    Command::cargo_bin("howdy").unwrap()
+        .arg("-f")
+        .arg(journal.path())
+        .arg("export")
+        .arg(csv_file.path())
+        .args(["--tag", "work", "--from", "2020-02-01", "--to", "2020-03-31", "--min-score", "0"])
+        .assert()
+        .success();
+
+    csv_file.assert("Date,Score,Tags,Comment,Metrics\n2020-02-01 09:10:11 +0400,2,\"run,work\",,\n");
+}
+
+#[test]
+fn redacted_export() {
+    let journal = prepare_empty_journal_file();
+    journal.write_str("2020-01-01 09:10:11 +0400 | 1 | doctor,run,work,location=home | private note\n").unwrap();
+    let ndjson_file = assert_fs::NamedTempFile::new("export.ndjson").unwrap();
+
+    Command::cargo_bin("howdy").unwrap()
+        .arg("-f")
+        .arg(journal.path())
+        .arg("export")
+        .arg(ndjson_file.path())
+        .args(["--redact-comments", "--date-only", "--allow-tags", "run,work"])
+        .assert()
+        .success();
+
+    ndjson_file.assert("{\"attributes\":{},\"comment\":null,\"datetime\":\"2020-01-01T00:00:00+00:00\",\"metrics\":{},\"score\":1,\"tags\":[\"run\",\"work\"]}\n");
+
+    Command::cargo_bin("howdy").unwrap()
+        .arg("-f")
+        .arg(journal.path())
+        .arg("export")
+        .arg(ndjson_file.path())
+        .args(["--hash-tags", "salt"])
+        .assert()
+        .success();
+
+    ndjson_file.assert(predicate::str::contains("private note"));
+    ndjson_file.assert(predicate::str::contains("doctor").not());
+}
+
+#[test]
+fn aggregated_calendar_export() {
+    let project_dir = assert_fs::TempDir::new().unwrap();
+    project_dir.child(".howdy.toml").write_str("daily_aggregation = \"mean\"\n").unwrap();
+    let journal = project_dir.child("howdy.journal");
+    journal.write_str("2020-01-01 09:10:11 +0000 | 2 | run |\n2020-01-01 20:00:00 +0000 | 1 | work |\n2020-01-02 09:00:00 +0000 | -1 |  |\n").unwrap();
+    let ics_file = project_dir.child("export.ics");
+
+    Command::cargo_bin("howdy").unwrap()
+        .env("XDG_CONFIG_HOME", project_dir.path())
+        .current_dir(project_dir.path())
+        .arg("-f")
+        .arg(journal.path())
+        .arg("export")
+        .arg(ics_file.path())
+        .args(["--tag", "run"])
+        .assert()
+        .success();
+
+    ics_file.assert(predicate::str::contains("SUMMARY:Mood: 2 (run)\r\n"));
+    ics_file.assert(predicate::str::contains("DTSTART;VALUE=DATE:20200102").not());
+
+    Command::cargo_bin("howdy").unwrap()
+        .env("XDG_CONFIG_HOME", project_dir.path())
+        .current_dir(project_dir.path())
+        .arg("-f")
+        .arg(journal.path())
+        .arg("export")
+        .arg(ics_file.path())
+        .assert()
+        .success();
+
+    ics_file.assert(predicate::str::contains("SUMMARY:Mood: 1.5 (run\\, work)\r\n"));
+}
+
+#[test]
+fn html_report() {
+    let journal = prepare_empty_journal_file();
+    journal.write_str(format!("{} | 1 | run |\n", Utc::now().format("%Y-%m-%d %H:%M:%S %z")).as_str()).unwrap();
+    let output_dir = assert_fs::TempDir::new().unwrap();
+
+    Command::cargo_bin("howdy").unwrap()
+        .arg("-f")
+        .arg(journal.path())
+        .args(["report", "--html"])
+        .arg(output_dir.path().join("report"))
+        .assert()
+        .success();
+
+    output_dir.child("report/index.html").assert(predicate::str::contains("<svg"));
+    output_dir.child("report/index.html").assert(predicate::str::contains("<td>run</td>"));
+}
+
+#[test]
+fn markdown_review() {
+    let journal = prepare_empty_journal_file();
+    journal.write_str("2020-01-31 09:10:11 +0000 | 2 | run | long run\n2021-01-01 09:10:11 +0000 | -1 | work |\n").unwrap();
+    let review_file = assert_fs::NamedTempFile::new("review.md").unwrap();
+
+    Command::cargo_bin("howdy").unwrap()
+        .arg("-f")
+        .arg(journal.path())
+        .args(["review", "2020-01"])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("# Month in review: January 2020\n"))
+        .stdout(predicate::str::contains("- 2020-01-31 (Fri): +2: long run\n"));
+
+    Command::cargo_bin("howdy").unwrap()
+        .arg("-f")
+        .arg(journal.path())
+        .args(["review", "2021", "-o"])
+        .arg(review_file.path())
+        .assert()
+        .success();
+
+    review_file.assert(predicate::str::contains("- Total: -1 (-3 compared to 2020)\n"));
+}
+
+#[test]
+fn tag_aliases_and_renaming() {
+    let project_dir = assert_fs::TempDir::new().unwrap();
+    project_dir.child(".howdy.toml")
+        .write_str("[tags]\nnormalize = true\n[tags.aliases]\nsports = \"sport\"\n\"спорт\" = \"sport\"\n")
+        .unwrap();
+    let journal = project_dir.child("howdy.journal");
+    journal
+        .write_str(concat!(
+            "2020-01-01 09:00:00 +0000 | 1 | Sports,work |\n",
+            "2020-01-02 09:00:00 +0000 | 2 | спорт |\n",
+            "2020-01-03 09:00:00 +0000 | -1 | work, sports  |\n",
+        ))
+        .unwrap();
+    let howdy = || {
+        let mut cmd = Command::cargo_bin("howdy").unwrap();
+        cmd.env("XDG_CONFIG_HOME", project_dir.path())
+            .current_dir(project_dir.path())
+            .arg("-f")
+            .arg(journal.path());
+        cmd
+    };
+
+    howdy()
+        .arg("tags")
+        .assert()
+        .stdout("sport: 2 (3 records, average 0.67)\nwork: 0 (2 records, average 0)\n");
+
+    howdy()
+        .args(["tags", "--tag", "Sports"])
+        .assert()
+        .stdout("sport: 2 (3 records, average 0.67)\nwork: 0 (2 records, average 0)\n");
+
+    howdy()
+        .args(["add", "1", "SPORTS"])
+        .assert()
+        .success();
+    journal.assert(predicate::str::ends_with("| 1 | sport |\n"));
+
+    howdy()
+        .args(["mood", "-t", "lm", " Sports"])
+        .assert()
+        .stdout("30-days mood: [1]\n");
+
+    howdy()
+        .args(["tags", "rename", "Sports", "Sport/Gym"])
+        .assert()
+        .stdout("'Sports' is renamed to 'sport/gym' in 1 records\n");
+    journal.assert(predicate::str::starts_with("2020-01-01 09:00:00 +0000 | 1 | sport/gym,work |\n2020-01-02 09:00:00 +0000 | 2 | спорт |\n"));
+
+    howdy()
+        .args(["tags", "rename", "games", "hobby"])
+        .assert()
+        .stdout("No records with 'games' tag\n");
+}
+
+#[test]
+fn config_file_override() {
+    let project_dir = assert_fs::TempDir::new().unwrap();
+    project_dir.child(".howdy.toml").write_str("[scores]\nallowed = [1]\n").unwrap();
+    let config = project_dir.child("shared.toml");
+    config.write_str("[scores]\nallowed = [2]\n").unwrap();
+    let journal = prepare_empty_journal_file();
+
+    Command::cargo_bin("howdy").unwrap()
+        .env("XDG_CONFIG_HOME", project_dir.path())
+        .env("HOWDY_CONFIG", config.path())
+        .current_dir(project_dir.path())
+        .arg("-f")
+        .arg(journal.path())
+        .args(["add", "2"])
+        .assert()
+        .success();
+
+    journal.assert(predicate::str::contains(" | 2 |  |\n"));
+}
+
+#[test]
+fn score_scale_and_aliases() {
+    let project_dir = assert_fs::TempDir::new().unwrap();
+    project_dir.child(".howdy.toml")
+        .write_str("[scores]\nallowed = [-1, 0, 1]\n[scores.aliases]\ngood = 1\nmeh = 0\nbad = -1\n\"+\" = 1\n")
+        .unwrap();
+    let journal = project_dir.child("howdy.journal");
+    let howdy = || {
+        let mut cmd = Command::cargo_bin("howdy").unwrap();
+        cmd.env("XDG_CONFIG_HOME", project_dir.path())
+            .current_dir(project_dir.path())
+            .arg("-f")
+            .arg(journal.path());
+        cmd
+    };
+
+    for score in ["good", "+", "bad"] {
+        howdy().args(["add", score]).assert().success();
+    }
+    journal.assert(predicate::str::is_match(r"\| 1 \|  \|\n.* \| 1 \|  \|\n.* \| -1 \|  \|\n$").unwrap());
+
+    howdy()
+        .args(["add", "2"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("score 2 is not allowed, expected one of -1, 0, 1 or +, bad, good, meh"));
+
+    howdy()
+        .arg("review")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("- Scores: 1 (good): 2, -1 (bad): 1\n"));
+}
+
+#[test]
+fn daily_aggregation_and_hours() {
+    let project_dir = assert_fs::TempDir::new().unwrap();
+    project_dir.child(".howdy.toml").write_str("daily_aggregation = \"mean\"\n").unwrap();
+    let journal = project_dir.child("howdy.journal");
+    let howdy = || {
+        let mut cmd = Command::cargo_bin("howdy").unwrap();
+        cmd.env("XDG_CONFIG_HOME", project_dir.path())
+            .current_dir(project_dir.path())
+            .arg("-f")
+            .arg(journal.path());
+        cmd
+    };
+
+    for score in ["2", "1"] {
+        howdy().args(["add", score]).assert().success();
+    }
+    howdy()
+        .args(["mood", "-t", "lm"])
+        .assert()
+        .success()
+        .stdout("30-days mood: [1.5]\n");
+
+    // only records with the tag are aggregated, not the whole day
+    howdy().args(["add", "3", "run"]).assert().success();
+    howdy()
+        .args(["mood", "-t", "lm", "run"])
+        .assert()
+        .success()
+        .stdout("30-days mood: [3]\n");
+    howdy()
+        .args(["mood", "-t", "lm", "--max-score", "2"])
+        .assert()
+        .success()
+        .stdout("30-days mood: [1.5]\n");
+
+    journal.write_str("2020-01-01 08:10:00 +0000 | -1 | work | \n\
+                       2020-01-01 20:00:00 +0000 | 2 | run | \n\
+                       2020-01-02 08:45:00 +0000 | 0.5 | work | \n").unwrap();
+    howdy()
+        .args(["mood", "--by-hour"])
+        .assert()
+        .success()
+        .stdout("mood by hour of day:\n08: -0.5 (2 records, average -0.25)\n20: 2 (1 records, average 2)\n");
+    howdy()
+        .args(["tags"])
+        .assert()
+        .success()
+        .stdout("run: 0.5 (1 records, average 0.5)\nwork: 1 (2 records, average 0.5)\n");
+}
+
+#[test]
+fn same_day_policies() {
+    let project_dir = assert_fs::TempDir::new().unwrap();
+    let config = project_dir.child(".howdy.toml");
+    let journal = project_dir.child("howdy.journal");
+    let howdy = || {
+        let mut cmd = Command::cargo_bin("howdy").unwrap();
+        cmd.env("XDG_CONFIG_HOME", project_dir.path())
+            .current_dir(project_dir.path())
+            .arg("-f")
+            .arg(journal.path());
+        cmd
+    };
+
+    config.write_str("same_day = \"reject\"\n").unwrap();
+    howdy().args(["add", "1", "run"]).assert().success();
+    howdy()
+        .args(["add", "-1"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("is already rated, use --force to add another record"));
+    howdy()
+        .arg("ask")
+        .write_stdin("1\n")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("is already rated, nothing is added"));
+    howdy().args(["add", "-1", "--force"]).assert().success();
+    journal.assert(predicate::str::is_match(r"^[^\n]+ \| 1 \| run \|\n[^\n]+ \| -1 \|  \|\n$").unwrap());
+
+    config.write_str("same_day = \"warn\"\n").unwrap();
+    howdy()
+        .args(["add", "0"])
+        .assert()
+        .success()
+        .stdout("")
+        .stderr(predicate::str::contains("Warning: ").and(predicate::str::contains("is already rated (2 records), a new record is added")));
+
+    config.write_str("same_day = \"replace\"\n").unwrap();
+    howdy()
+        .args(["add", "2", "walk"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Replaced 3 records of "));
+    journal.assert(predicate::str::is_match(r"^[^\n]+ \| 2 \| walk \|\n$").unwrap());
+}
+
+#[test]
+fn timezone_report_days() {
+    let project_dir = assert_fs::TempDir::new().unwrap();
+    let config = project_dir.child(".howdy.toml");
+    let journal = project_dir.child("howdy.journal");
+    let howdy = || {
+        let mut cmd = Command::cargo_bin("howdy").unwrap();
+        cmd.env("XDG_CONFIG_HOME", project_dir.path())
+            .current_dir(project_dir.path())
+            .arg("-f")
+            .arg(journal.path());
+        cmd
+    };
+
+    // clocks are moved forward in New York at 2:00 of March 14th 2021
+    journal.write_str("2021-03-14 05:30:00 +0000 | 1 | | \n\
+                       2021-03-14 08:30:00 +0000 | 2 | | \n\
+                       2021-03-15 03:00:00 +0000 | 4 | | \n").unwrap();
+    config.write_str("timezone = \"America/New_York\"\n").unwrap();
+    howdy()
+        .args(["mood", "--by-hour", "--to", "2021-03-14"])
+        .assert()
+        .success()
+        .stdout("mood by hour of day:\n05: 1 (1 records, average 1)\n08: 2 (1 records, average 2)\n");
+
+    config.write_str("timezone = \"America/New_York\"\nreport_days = \"timezone\"\n").unwrap();
+    howdy()
+        .args(["mood", "--by-hour", "--to", "2021-03-14"])
+        .assert()
+        .success()
+        .stdout("mood by hour of day:\n00: 1 (1 records, average 1)\n04: 2 (1 records, average 2)\n23: 4 (1 records, average 4)\n");
+
+    howdy().args(["add", "1"]).assert().success();
+    journal.assert(predicate::str::is_match(r"\n[^\n]+ -0[45]00 \| 1 \|  \|\n$").unwrap());
+
+    config.write_str("timezone = \"Mars/Olympus\"\n").unwrap();
+    howdy()
+        .args(["mood"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("has invalid 'timezone' value"));
+}
+
+#[test]
+fn config_file_defaults() {
+    let config_home = assert_fs::TempDir::new().unwrap();
+    config_home.child("howdy/config.toml")
+        .write_str("journal = \"journal.howdy\"\nreport_type = \"lm\"\ntimezone = \"+05:00\"\nplot_backend = \"none\"\n")
+        .unwrap();
+    let project_dir = assert_fs::TempDir::new().unwrap();
+    project_dir.child(".howdy.toml").write_str("report_type = \"ly\"\n").unwrap();
+
+    Command::cargo_bin("howdy").unwrap()
+        .env("XDG_CONFIG_HOME", config_home.path())
+        .env_remove("HOWDY_JOURNAL")
+        .current_dir(project_dir.path())
+        .args(["add", "2", "tag"])
+        .assert()
+        .success();
+
+    config_home.child("howdy/journal.howdy").assert(predicate::str::contains("+0500 | 2 | tag"));
+
+    Command::cargo_bin("howdy").unwrap()
+        .env("XDG_CONFIG_HOME", config_home.path())
+        .env_remove("HOWDY_JOURNAL")
+        .current_dir(project_dir.path())
+        .arg("mood")
+        .assert()
+        .stdout("365-days mood: [2]\n");
+
+    let journal = prepare_empty_journal_file();
+    Command::cargo_bin("howdy").unwrap()
+        .env("XDG_CONFIG_HOME", config_home.path())
+        .env_remove("HOWDY_JOURNAL")
+        .current_dir(project_dir.path())
+        .arg("-f")
+        .arg(journal.path())
+        .args(["mood", "-t", "lm"])
+        .assert()
+        .stdout("30-days mood: [0]\n");
+
+    project_dir.child(".howdy.toml").write_str("week_start = \"someday\"\n").unwrap();
+    Command::cargo_bin("howdy").unwrap()
+        .env("XDG_CONFIG_HOME", config_home.path())
+        .env_remove("HOWDY_JOURNAL")
+        .current_dir(project_dir.path())
+        .arg("mood")
+        .assert()
+        .failure();
+}
+
+#[test]
+fn default_journal_location() {
+    let config_home = assert_fs::TempDir::new().unwrap();
+    let data_home = assert_fs::TempDir::new().unwrap();
+    let project_dir = assert_fs::TempDir::new().unwrap();
+    project_dir.child("howdy.journal").touch().unwrap();
+    let howdy = || {
+        let mut cmd = Command::cargo_bin("howdy").unwrap();
+        cmd.env("XDG_CONFIG_HOME", config_home.path())
+            .env("XDG_DATA_HOME", data_home.path())
+            .env_remove("HOWDY_JOURNAL")
+            .current_dir(project_dir.path());
+        cmd
+    };
+
+    howdy()
+        .args(["add", "1", "tag"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Warning: './howdy.journal' is ignored"));
+    data_home.child("howdy/howdy.journal").assert(predicate::str::contains("| 1 | tag"));
+    project_dir.child("howdy.journal").assert("");
+
+    // completion output goes into the shell, so it does not warn about the journal or the config
+    howdy()
+        .arg("__complete-tags")
+        .assert()
+        .success()
+        .stdout("tag\n")
+        .stderr("");
+    project_dir.child(".howdy.toml").write_str("week_start = \"someday\"\n").unwrap();
+    howdy()
+        .args(["completions", "bash"])
+        .assert()
+        .success()
+        .stderr("");
+    howdy()
+        .arg("__complete-tags")
+        .assert()
+        .success()
+        .stdout("")
+        .stderr("");
+    project_dir.child(".howdy.toml").write_str("").unwrap();
+
+    howdy()
+        .args(["-f", "./howdy.journal", "add", "2"])
+        .assert()
+        .success()
+        .stderr("");
+    project_dir.child("howdy.journal").assert(predicate::str::contains("| 2 |"));
+
+    let env_journal = assert_fs::NamedTempFile::new("env.journal").unwrap();
+    howdy()
+        .env("HOWDY_JOURNAL", env_journal.path())
+        .args(["add", "3"])
+        .assert()
+        .success();
+    env_journal.assert(predicate::str::contains("| 3 |"));
+}
+
+#[test]
+fn serve_api() {
+    let journal = prepare_empty_journal_file();
+    let mut server = std::process::Command::new(assert_cmd::cargo::cargo_bin("howdy"))
+        .arg("-f")
+        .arg(journal.path())
+        .args(["serve", "--bind", "127.0.0.1:0"])
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    let mut server_output = std::io::BufReader::new(server.stdout.take().unwrap());
+    let mut first_line = String::new();
+    std::io::BufRead::read_line(&mut server_output, &mut first_line).unwrap();
+    let address = first_line.trim().rsplit("http://").next().unwrap().to_string();
+
+    let send = |raw_request: String| {
+        let mut stream = std::net::TcpStream::connect(&address).unwrap();
+        std::io::Write::write_all(&mut stream, raw_request.as_bytes()).unwrap();
+        let mut response = String::new();
+        std::io::Read::read_to_string(&mut stream, &mut response).unwrap();
+        response
+    };
+    // a client which sends nothing does not hold other ones up
+    let _idle_connection = std::net::TcpStream::connect(&address).unwrap();
+    let body = r#"{"score": 1, "tags": ["run"], "comment": "from phone"}"#;
+    let added = send(format!("POST /api/entries HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                             address, body.len(), body));
+    let entries = send(format!("GET /api/entries?tag=run HTTP/1.1\r\nHost: {}\r\n\r\n", address));
+    let page = send(format!("GET / HTTP/1.1\r\nHost: {}\r\n\r\n", address));
+    let form_post = send(format!("POST /api/entries HTTP/1.1\r\nHost: {}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\n\r\n{}",
+                                 address, body.len(), body));
+    let rebound = send("GET /api/entries HTTP/1.1\r\nHost: evil.example\r\n\r\n".to_string());
+    let cross_site = send(format!("GET /api/entries HTTP/1.1\r\nHost: {}\r\nOrigin: http://evil.example\r\n\r\n", address));
+    server.kill().unwrap();
+    server.wait().unwrap();
+
+    assert!(added.starts_with("HTTP/1.1 201 Created"));
+    assert!(entries.starts_with("HTTP/1.1 200 OK"));
+    assert!(entries.contains(r#""comment":"from phone""#));
+    assert!(page.contains("<!DOCTYPE html>"));
+    assert!(form_post.starts_with("HTTP/1.1 415 Unsupported Media Type"));
+    assert!(rebound.starts_with("HTTP/1.1 403 Forbidden"));
+    assert!(cross_site.starts_with("HTTP/1.1 403 Forbidden"));
+    journal.assert(predicate::str::contains("| 1 | run | from phone"));
+    journal.assert(predicate::str::contains("from phone").count(1));
+}
+
+#[test]
+fn serve_with_token() {
+    let journal = prepare_empty_journal_file();
+    let mut server = std::process::Command::new(assert_cmd::cargo::cargo_bin("howdy"))
+        .arg("-f")
+        .arg(journal.path())
+        .args(["serve", "--bind", "127.0.0.1:0", "--token", "secret"])
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    let mut server_output = std::io::BufReader::new(server.stdout.take().unwrap());
+    let mut first_line = String::new();
+    std::io::BufRead::read_line(&mut server_output, &mut first_line).unwrap();
+    let address = first_line.trim().rsplit("http://").next().unwrap().trim_end_matches("/?token=secret").to_string();
+
+    let send = |target: &str, authorization: &str| {
+        let mut stream = std::net::TcpStream::connect(&address).unwrap();
+        let raw_request = format!("GET {} HTTP/1.1\r\nHost: {}\r\n{}\r\n", target, address, authorization);
+        std::io::Write::write_all(&mut stream, raw_request.as_bytes()).unwrap();
+        let mut response = String::new();
+        std::io::Read::read_to_string(&mut stream, &mut response).unwrap();
+        response
+    };
+    let without_token = send("/api/entries", "");
+    let with_query_token = send("/?token=secret", "");
+    let with_bearer_token = send("/api/entries", "Authorization: Bearer secret\r\n");
+    server.kill().unwrap();
+    server.wait().unwrap();
+
+    assert!(first_line.ends_with("/?token=secret\n"));
+    assert!(without_token.starts_with("HTTP/1.1 401 Unauthorized"));
+    assert!(with_query_token.starts_with("HTTP/1.1 200 OK"));
+    assert!(with_bearer_token.starts_with("HTTP/1.1 200 OK"));
+}
+
 fn prepare_empty_journal_file() -> assert_fs::NamedTempFile {
     let journal = assert_fs::NamedTempFile::new("howdy.journal").unwrap();
     journal.touch().unwrap();